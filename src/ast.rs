@@ -1 +1,3 @@
+//! AST nodes produced by the parser
+
 pub mod object;
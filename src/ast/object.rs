@@ -1,7 +1,251 @@
 //! AST nodes for PDF objects. See PDF Spec section 7.3
 
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+
+/// Any PDF object. See PDF Spec section 7.3.1
+#[derive(Clone, Debug, PartialEq)]
+pub enum Object {
+    Boolean(bool),
+    Number(Number),
+    String(PdfString),
+    Name(Name),
+    Array(Vec<Object>),
+    Dictionary(Dictionary),
+    Stream(Stream),
+    Null,
+    /// A reference to an indirect object, e.g. `12 0 R`. See PDF Spec section 7.3.10
+    Reference(Reference),
+}
+
+impl Object {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Object::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            Object::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of this object if it's an integer. Reals are *not* converted.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Object::Number(Number::Integer(i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_string(&self) -> Option<&PdfString> {
+        match self {
+            Object::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_name(&self) -> Option<&Name> {
+        match self {
+            Object::Name(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Object]> {
+        match self {
+            Object::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns the dictionary of this object. For streams, this is the stream dictionary.
+    pub fn as_dictionary(&self) -> Option<&Dictionary> {
+        match self {
+            Object::Dictionary(d) => Some(d),
+            Object::Stream(s) => Some(&s.dict),
+            _ => None,
+        }
+    }
+
+    pub fn as_stream(&self) -> Option<&Stream> {
+        match self {
+            Object::Stream(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_reference(&self) -> Option<Reference> {
+        match self {
+            Object::Reference(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Object::Null)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Number {
     Integer(i64),
     Real(f64),
 }
+
+impl Number {
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Number::Integer(i) => i as f64,
+            Number::Real(r) => r,
+        }
+    }
+}
+
+/// A PDF string, which remembers whether it was written as a literal (`(...)`) or hexadecimal
+/// (`<...>`) string. See PDF Spec section 7.3.4
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PdfString {
+    Literal(Vec<u8>),
+    Hex(Vec<u8>),
+}
+
+impl PdfString {
+    /// The bytes of the string, after escapes (or hex digits) have been decoded
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            PdfString::Literal(b) | PdfString::Hex(b) => b,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            PdfString::Literal(b) | PdfString::Hex(b) => b,
+        }
+    }
+}
+
+/// A PDF name, without the leading `/` and with `#xx` escapes decoded. See PDF Spec section 7.3.5
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Name(pub Vec<u8>);
+
+impl Name {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Borrow<[u8]> for Name {
+    fn borrow(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<&[u8]> for Name {
+    fn from(value: &[u8]) -> Self {
+        Name(value.to_vec())
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for Name {
+    fn from(value: &[u8; N]) -> Self {
+        Name(value.to_vec())
+    }
+}
+
+impl PartialEq<[u8]> for Name {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.0 == other
+    }
+}
+
+/// A PDF dictionary. See PDF Spec section 7.3.7
+///
+/// Per the spec, a dictionary entry whose value is `null` is equivalent to an absent entry, so
+/// such entries are never stored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dictionary(BTreeMap<Name, Object>);
+
+impl Dictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&Object> {
+        self.0.get(key)
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Inserts an entry, returning the previous value for `key` if there was one. Inserting
+    /// [`Object::Null`] removes the entry.
+    pub fn insert(&mut self, key: Name, value: Object) -> Option<Object> {
+        if value.is_null() {
+            self.0.remove(key.as_bytes())
+        } else {
+            self.0.insert(key, value)
+        }
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<Object> {
+        self.0.remove(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Name, &Object)> {
+        self.0.iter()
+    }
+}
+
+impl IntoIterator for Dictionary {
+    type Item = (Name, Object);
+    type IntoIter = std::collections::btree_map::IntoIter<Name, Object>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl FromIterator<(Name, Object)> for Dictionary {
+    fn from_iter<T: IntoIterator<Item = (Name, Object)>>(iter: T) -> Self {
+        let mut dict = Dictionary::new();
+        for (k, v) in iter {
+            dict.insert(k, v);
+        }
+        dict
+    }
+}
+
+/// A PDF stream: a dictionary followed by a sequence of (still encoded) bytes. See PDF Spec
+/// section 7.3.8
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stream {
+    pub dict: Dictionary,
+    pub data: Vec<u8>,
+}
+
+/// A reference to an indirect object. See PDF Spec section 7.3.10
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Reference {
+    pub number: u32,
+    pub generation: u16,
+}
+
+/// An indirect object definition, e.g. `12 0 obj ... endobj`. See PDF Spec section 7.3.10
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndirectObject {
+    pub id: Reference,
+    pub object: Object,
+}
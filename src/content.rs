@@ -0,0 +1,617 @@
+//! Content streams, the sequences of operators that describe the appearance of a page or form.
+//! See PDF Spec section 7.8
+//!
+//! Content streams use the same tokens as the rest of the file, but each operator follows its
+//! operands, so they're parsed forwards here rather than with the object grammar.
+
+mod interpreter;
+mod op;
+
+use std::fmt::Display;
+
+pub use interpreter::{
+    Clip, Color, ColorSpace, Dash, FillRule, Font, Glyph, GraphicsState, Interpreter, Paint, Path,
+    PathSegment, TextState, Visitor,
+};
+pub use op::{Op, OpParser, TextItem};
+
+use crate::ast::object::{Dictionary, Name, Object, PdfString, Reference, Stream};
+use crate::error::{ParseError, ParseErrorKind, ParseMode, Result, Warning};
+use crate::lexer::{MAX_NESTING, PdfLexError, PdfLexerForwards, Tok, find_subslice, is_whitespace};
+use crate::parser_helper::{
+    ParseContext, handle_hex_str, handle_name_escapes, handle_number, handle_raw_str_escapes,
+};
+
+/// How many tokens after a possible end of inline image data we check look like content
+static INLINE_IMAGE_LOOKAHEAD: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContentError {
+    /// An operator that isn't in PDF Spec Annex A, outside a `BX`/`EX` compatibility section
+    UnknownOperator(Vec<u8>),
+    /// An operator with the wrong number or types of operands
+    InvalidOperands(Vec<u8>),
+    /// An operator names a resource that isn't in the resource dictionary, or has the wrong type
+    MissingResource(Name),
+    /// A `Q` without a matching `q` in the same content stream
+    UnbalancedRestore,
+    /// The referenced Form XObject draws itself, directly or through other forms
+    FormCycle(Reference),
+    /// Form XObjects draw each other too deeply
+    FormsTooDeep,
+    /// A content stream draws too many Form XObjects, including those drawn by other forms
+    TooManyForms,
+}
+
+impl Display for ContentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentError::UnknownOperator(operator) => write!(
+                f,
+                "Unknown operator `{}`",
+                String::from_utf8_lossy(operator)
+            ),
+            ContentError::InvalidOperands(operator) => write!(
+                f,
+                "Invalid operands for `{}`",
+                String::from_utf8_lossy(operator)
+            ),
+            ContentError::MissingResource(name) => write!(
+                f,
+                "Missing resource /{}",
+                String::from_utf8_lossy(name.as_bytes())
+            ),
+            ContentError::UnbalancedRestore => write!(f, "`Q` without a matching `q`"),
+            ContentError::FormCycle(r) => {
+                write!(f, "Form {} {} draws itself", r.number, r.generation)
+            }
+            ContentError::FormsTooDeep => write!(f, "Forms nested too deeply"),
+            ContentError::TooManyForms => write!(f, "Too many forms drawn"),
+        }
+    }
+}
+
+impl std::error::Error for ContentError {}
+
+/// An operator with its operands, e.g. `1 0 0 1 72 72 cm`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation {
+    pub operator: Vec<u8>,
+    pub operands: Vec<Object>,
+}
+
+impl Operation {
+    pub fn new(operator: &[u8], operands: Vec<Object>) -> Self {
+        Self {
+            operator: operator.to_vec(),
+            operands,
+        }
+    }
+}
+
+/// Parses the operations in (decoded) content stream data.
+///
+/// An inline image, `BI ... ID ... EI`, is a single `BI` operation whose only operand is the
+/// image as a stream: its dictionary has the entries between `BI` and `ID`, abbreviations and
+/// all, and its data is the (still encoded) bytes between `ID` and `EI`. See PDF Spec section
+/// 8.9.7
+///
+/// Operands at the end of the data without an operator are ignored. Parsing stops at the first
+/// error.
+pub struct ContentParser<'input> {
+    input: &'input [u8],
+    lexer: PdfLexerForwards<'input>,
+    ctx: ParseContext,
+    done: bool,
+}
+
+impl<'input> ContentParser<'input> {
+    /// Parses `input` strictly
+    pub fn new(input: &'input [u8]) -> Self {
+        Self::new_with(input, ParseMode::Strict)
+    }
+
+    /// Parses `input` according to `mode`. In lenient mode, the deviations from the spec that
+    /// were accepted are available from [`ContentParser::into_warnings`].
+    pub fn new_with(input: &'input [u8], mode: ParseMode) -> Self {
+        Self {
+            input,
+            lexer: PdfLexerForwards::new_at(input, 0),
+            ctx: ParseContext::new(mode),
+            done: false,
+        }
+    }
+
+    /// The deviations from the spec accepted so far in lenient mode
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.ctx.into_warnings()
+    }
+
+    fn next_operation(&mut self) -> Option<Result<Operation>> {
+        let mut operands = Vec::new();
+        loop {
+            let (l, tok, r) = match self.lexer.next()? {
+                Ok(tok) => tok,
+                Err(e) => return Some(Err(self.lex_error(e))),
+            };
+            match tok {
+                Tok::UnknownTok(b"BI") => return Some(self.inline_image()),
+                Tok::UnknownTok(operator) => return Some(Ok(Operation::new(operator, operands))),
+                tok => match self.object(l, tok, r, 0) {
+                    Ok(obj) => operands.push(obj),
+                    Err(e) => return Some(Err(e)),
+                },
+            }
+        }
+    }
+
+    /// Reads the next token, which must exist
+    fn token(&mut self) -> Result<(usize, Tok<'input>, usize)> {
+        match self.lexer.next() {
+            Some(Ok(tok)) => Ok(tok),
+            Some(Err(e)) => Err(self.lex_error(e)),
+            None => Err(self.lex_error(PdfLexError::UnexpectedEOF)),
+        }
+    }
+
+    /// Parses the operand starting with the token `tok`, which spans `l..r`, inside `depth`
+    /// arrays and dictionaries
+    fn object(&mut self, l: usize, tok: Tok<'input>, r: usize, depth: usize) -> Result<Object> {
+        if depth >= MAX_NESTING && matches!(tok, Tok::ArrayOpen | Tok::DictOpen) {
+            return Err(ParseError::new(ParseErrorKind::TooDeep, l, &self.input[l..r]).into());
+        }
+        Ok(match tok {
+            Tok::Number(n) => Object::Number(
+                handle_number(&self.ctx, l, n)
+                    .ok_or_else(|| self.lex_error(PdfLexError::InvalidNumber(n)))?,
+            ),
+            Tok::True => Object::Boolean(true),
+            Tok::False => Object::Boolean(false),
+            Tok::Null => Object::Null,
+            Tok::Name(n) => Object::Name(self.name(n)?),
+            Tok::RawStrDelimOpen => {
+                let content = match self.token()? {
+                    (_, Tok::RawStrContent(content), _) => content,
+                    (l, _, r) => return Err(self.unexpected(l, r)),
+                };
+                // An unterminated string runs to the end of the input
+                match self.token()? {
+                    (_, Tok::RawStrDelimClose, _) => {}
+                    (l, _, r) => return Err(self.unexpected(l, r)),
+                }
+                Object::String(PdfString::Literal(handle_raw_str_escapes(content)))
+            }
+            Tok::HexStrDelimOpen => {
+                let content = match self.token()? {
+                    (_, Tok::HexStrContent(content), _) => content,
+                    (l, _, r) => return Err(self.unexpected(l, r)),
+                };
+                self.token()?;
+                let bytes = handle_hex_str(content)
+                    .ok_or_else(|| self.lex_error(PdfLexError::InvalidHexString(content)))?;
+                Object::String(PdfString::Hex(bytes))
+            }
+            Tok::ArrayOpen => {
+                let mut objects = Vec::new();
+                loop {
+                    match self.token()? {
+                        (_, Tok::ArrayClose, _) => break,
+                        (l, tok, r) => objects.push(self.object(l, tok, r, depth + 1)?),
+                    }
+                }
+                Object::Array(objects)
+            }
+            Tok::DictOpen => {
+                let mut dict = Dictionary::new();
+                loop {
+                    match self.token()? {
+                        (_, Tok::DictClose, _) => break,
+                        (_, Tok::Name(key), _) => {
+                            let key = self.name(key)?;
+                            let (l, tok, r) = self.token()?;
+                            dict.insert(key, self.object(l, tok, r, depth + 1)?);
+                        }
+                        (l, _, r) => return Err(self.unexpected(l, r)),
+                    }
+                }
+                Object::Dictionary(dict)
+            }
+            // Operators can't appear inside arrays or dictionaries, and the rest of the tokens
+            // (such as `R`) have no place in content streams
+            _ => return Err(self.unexpected(l, r)),
+        })
+    }
+
+    fn name(&self, tok: &'input [u8]) -> Result<Name> {
+        handle_name_escapes(tok)
+            .map(Name)
+            .ok_or_else(|| self.lex_error(PdfLexError::InvalidName(tok)))
+    }
+
+    /// Parses an inline image, after its `BI`
+    fn inline_image(&mut self) -> Result<Operation> {
+        let mut dict = Dictionary::new();
+        let id_end = loop {
+            match self.token()? {
+                (_, Tok::UnknownTok(b"ID"), r) => break r,
+                (_, Tok::Name(key), _) => {
+                    let key = self.name(key)?;
+                    let (l, tok, r) = self.token()?;
+                    dict.insert(key, self.object(l, tok, r, 0)?);
+                }
+                (l, _, r) => return Err(self.unexpected(l, r)),
+            }
+        };
+
+        // A single whitespace character separates `ID` from the data
+        let start = match self.input.get(id_end) {
+            Some(c) if is_whitespace(*c) => id_end + 1,
+            _ => id_end,
+        };
+        let (end, resume) = inline_image_end(self.input, start, &dict)
+            .ok_or_else(|| self.lex_error(PdfLexError::UnexpectedEOF))?;
+        self.lexer = PdfLexerForwards::new_at(self.input, resume);
+        let image = Stream {
+            dict,
+            data: self.input[start..end].to_vec(),
+        };
+        Ok(Operation::new(b"BI", vec![Object::Stream(image)]))
+    }
+
+    fn lex_error(&self, err: PdfLexError) -> crate::error::Error {
+        ParseError::from_lex(self.input, err).into()
+    }
+
+    fn unexpected(&self, l: usize, r: usize) -> crate::error::Error {
+        ParseError::new(ParseErrorKind::UnexpectedToken, l, &self.input[l..r]).into()
+    }
+}
+
+impl Iterator for ContentParser<'_> {
+    type Item = Result<Operation>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_operation();
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+        next
+    }
+}
+
+/// Finds the end of the inline image data starting at byte `start` of `input`, returning where
+/// the data ends and where the `EI` after it ends.
+///
+/// The data is binary, so `EI` may appear in it. If its length is known, from `/Length` or from
+/// the dimensions of an unfiltered image, we check for `EI` there. Otherwise we take the first
+/// `EI` between whitespace that's followed by what looks like more content.
+fn inline_image_end(input: &[u8], start: usize, dict: &Dictionary) -> Option<(usize, usize)> {
+    let rest = &input[start..];
+    if let Some(len) = inline_image_len(dict)
+        && let Some(after) = rest.get(len..)
+    {
+        let ws = after.iter().take_while(|c| is_whitespace(**c)).count();
+        if is_ei(&after[ws..]) {
+            return Some((start + len, start + len + ws + 2));
+        }
+    }
+
+    let mut pos = 0;
+    while let Some(i) = find_subslice(&rest[pos..], b"EI").map(|i| pos + i) {
+        // The whitespace before `EI` isn't part of the data
+        let end = match i {
+            0 => Some(0),
+            _ if is_whitespace(rest[i - 1]) => Some(i - 1),
+            _ => None,
+        };
+        if let Some(end) = end
+            && is_ei(&rest[i..])
+            && looks_like_content(input, start + i + 2)
+        {
+            return Some((start + end, start + i + 2));
+        }
+        pos = i + 1;
+    }
+    None
+}
+
+/// Whether `rest` starts with an `EI` token
+fn is_ei(rest: &[u8]) -> bool {
+    rest.starts_with(b"EI") && rest.get(2).is_none_or(|c| is_whitespace(*c))
+}
+
+/// Whether the next few tokens from byte `start` of `input` lex as content, rather than more
+/// binary data
+fn looks_like_content(input: &[u8], start: usize) -> bool {
+    PdfLexerForwards::new_at(input, start)
+        .take(INLINE_IMAGE_LOOKAHEAD)
+        .all(|tok| match tok {
+            Ok((_, Tok::UnknownTok(operator), _)) => {
+                operator.len() <= 3
+                    && operator
+                        .iter()
+                        .all(|c| c.is_ascii_alphanumeric() || b"*'\"".contains(c))
+            }
+            Ok(_) => true,
+            Err(_) => false,
+        })
+}
+
+/// The length of an inline image's data, if it's given by `/L` or `/Length` (PDF 2.0), or can be
+/// worked out because the image isn't filtered and has a device colour space
+fn inline_image_len(dict: &Dictionary) -> Option<usize> {
+    let get = |short: &[u8], long: &[u8]| dict.get(short).or_else(|| dict.get(long));
+    let int = |short: &[u8], long: &[u8]| {
+        get(short, long)
+            .and_then(Object::as_integer)
+            .and_then(|val| usize::try_from(val).ok())
+    };
+    if let Some(len) = int(b"L", b"Length") {
+        return Some(len);
+    }
+    if get(b"F", b"Filter").is_some() {
+        return None;
+    }
+
+    let is_mask = get(b"IM", b"ImageMask").and_then(Object::as_bool) == Some(true);
+    let (components, bits) = if is_mask {
+        (1, 1)
+    } else {
+        let color_space = get(b"CS", b"ColorSpace")?;
+        let components = match color_space.as_name().map(Name::as_bytes) {
+            Some(b"G" | b"DeviceGray") => 1,
+            Some(b"RGB" | b"DeviceRGB") => 3,
+            Some(b"CMYK" | b"DeviceCMYK") => 4,
+            // An indexed colour space has one component, the index
+            _ => match color_space.as_array()?.first()?.as_name()?.as_bytes() {
+                b"I" | b"Indexed" => 1,
+                _ => return None,
+            },
+        };
+        (components, int(b"BPC", b"BitsPerComponent")?)
+    };
+    let width = int(b"W", b"Width")?;
+    let height = int(b"H", b"Height")?;
+    let row_len = width
+        .checked_mul(components)?
+        .checked_mul(bits)?
+        .div_ceil(8);
+    row_len.checked_mul(height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::object::Number;
+    use crate::error::{Error, WarningKind};
+
+    fn parse(input: &[u8]) -> Result<Vec<Operation>> {
+        ContentParser::new(input).collect()
+    }
+
+    fn int(val: i64) -> Object {
+        Object::Number(Number::Integer(val))
+    }
+
+    fn real(val: f64) -> Object {
+        Object::Number(Number::Real(val))
+    }
+
+    fn name(name: &[u8]) -> Object {
+        Object::Name(Name::from(name))
+    }
+
+    fn op(operator: &str, operands: Vec<Object>) -> Operation {
+        Operation::new(operator.as_bytes(), operands)
+    }
+
+    #[test]
+    fn operations() {
+        let input = b"q 1 0 0 1 72.5 -7 cm % a comment\n\
+            /GS0 gs BT/F1 12 Tf[(Hello, \\(world\\))-250<48 69>]TJ 0 -14 TD(a)' 1 2(b)\" ET\n\
+            /Span<</MCID 3/Alt(x)>>BDC EMC true false null d0 T* Q";
+        assert_eq!(
+            parse(input).unwrap(),
+            [
+                op("q", vec![]),
+                op(
+                    "cm",
+                    vec![int(1), int(0), int(0), int(1), real(72.5), int(-7)]
+                ),
+                op("gs", vec![name(b"GS0")]),
+                op("BT", vec![]),
+                op("Tf", vec![name(b"F1"), int(12)]),
+                op(
+                    "TJ",
+                    vec![Object::Array(vec![
+                        Object::String(PdfString::Literal(b"Hello, (world)".to_vec())),
+                        int(-250),
+                        Object::String(PdfString::Hex(b"Hi".to_vec())),
+                    ])]
+                ),
+                op("TD", vec![int(0), int(-14)]),
+                op("'", vec![Object::String(PdfString::Literal(b"a".to_vec()))]),
+                op(
+                    "\"",
+                    vec![
+                        int(1),
+                        int(2),
+                        Object::String(PdfString::Literal(b"b".to_vec()))
+                    ]
+                ),
+                op("ET", vec![]),
+                op(
+                    "BDC",
+                    vec![
+                        name(b"Span"),
+                        Object::Dictionary(
+                            [
+                                (Name::from(b"MCID"), int(3)),
+                                (
+                                    Name::from(b"Alt"),
+                                    Object::String(PdfString::Literal(b"x".to_vec()))
+                                ),
+                            ]
+                            .into_iter()
+                            .collect()
+                        )
+                    ]
+                ),
+                op("EMC", vec![]),
+                op(
+                    "d0",
+                    vec![Object::Boolean(true), Object::Boolean(false), Object::Null]
+                ),
+                op("T*", vec![]),
+                op("Q", vec![]),
+            ]
+        );
+        assert_eq!(parse(b"").unwrap(), []);
+        assert_eq!(parse(b"  % just a comment").unwrap(), []);
+        // Trailing operands are dropped
+        assert_eq!(parse(b"q 1 2").unwrap(), [op("q", vec![])]);
+    }
+
+    fn image(entries: &[(&[u8], Object)], data: &[u8]) -> Operation {
+        let dict = entries
+            .iter()
+            .map(|(key, val)| (Name::from(*key), val.clone()))
+            .collect();
+        op(
+            "BI",
+            vec![Object::Stream(Stream {
+                dict,
+                data: data.to_vec(),
+            })],
+        )
+    }
+
+    #[test]
+    fn inline_images() {
+        // Unfiltered, so the length is known even though the data contains ` EI `
+        let input = b"q BI /W 4 /H 2 /BPC 8 /CS /G ID \x00 EI \xff\x01\x02EI Q";
+        let entries: [(&[u8], Object); 4] = [
+            (b"W", int(4)),
+            (b"H", int(2)),
+            (b"BPC", int(8)),
+            (b"CS", name(b"G")),
+        ];
+        assert_eq!(
+            parse(input).unwrap(),
+            [
+                op("q", vec![]),
+                image(&entries, b"\x00 EI \xff\x01\x02"),
+                op("Q", vec![])
+            ]
+        );
+
+        // An explicit length
+        let input = b"BI/W 1/H 1/F/AHx/L 5 ID\nEI EI\nEI\n";
+        assert_eq!(
+            parse(input).unwrap(),
+            [image(
+                &[
+                    (b"W", int(1)),
+                    (b"H", int(1)),
+                    (b"F", name(b"AHx")),
+                    (b"L", int(5))
+                ],
+                b"EI EI"
+            )]
+        );
+
+        // Filtered data is searched for an `EI` followed by content, skipping one followed by
+        // binary data
+        let input = b"BI /W 1 /H 1 /F /Fl /DP <</Predictor 1>> ID x EI \x9c\x00 EI Q";
+        let entries: [(&[u8], Object); 4] = [
+            (b"W", int(1)),
+            (b"H", int(1)),
+            (b"F", name(b"Fl")),
+            (
+                b"DP",
+                Object::Dictionary([(Name::from(b"Predictor"), int(1))].into_iter().collect()),
+            ),
+        ];
+        assert_eq!(
+            parse(input).unwrap(),
+            [image(&entries, b"x EI \x9c\x00"), op("Q", vec![])]
+        );
+
+        // An image mask, with the data directly after `ID`'s whitespace and running to the end
+        let input = b"BI /IM true /W 9 /H 1 ID \xff\x80EI";
+        assert_eq!(
+            parse(input).unwrap(),
+            [image(
+                &[
+                    (b"IM", Object::Boolean(true)),
+                    (b"W", int(9)),
+                    (b"H", int(1))
+                ],
+                b"\xff\x80"
+            )]
+        );
+
+        // A wrong length falls back on searching
+        let input = b"BI /W 100 /H 1 /BPC 8 /CS /RGB ID abc EI Q";
+        assert_eq!(parse(input).unwrap().len(), 2);
+
+        let err = parse(b"BI /W 1 /H 1 /F /DCT ID \xff\xd8").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Parse(ParseError {
+                kind: ParseErrorKind::UnexpectedEof,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let kind = |input: &[u8]| match parse(input) {
+            Err(Error::Parse(e)) => (e.kind, e.offset),
+            res => panic!("expected a parse error, got {res:?}"),
+        };
+        assert_eq!(kind(b"q [1 2 cm] Q"), (ParseErrorKind::UnexpectedToken, 7));
+        assert_eq!(kind(b"q ] Q"), (ParseErrorKind::UnexpectedToken, 2));
+        assert_eq!(
+            kind(b"<</A 1 2>> BDC"),
+            (ParseErrorKind::UnexpectedToken, 7)
+        );
+        assert_eq!(kind(b"1 0 R"), (ParseErrorKind::UnexpectedToken, 4));
+        assert_eq!(kind(b"[1 2"), (ParseErrorKind::UnexpectedEof, 4));
+        assert_eq!(kind(b"<4G> Tj"), (ParseErrorKind::UnexpectedChar, 2));
+        assert_eq!(kind(b"/A#G gs"), (ParseErrorKind::InvalidName, 0));
+        assert_eq!(kind(b"1 --2 m"), (ParseErrorKind::InvalidNumber, 2));
+
+        // Rather than overflowing the stack
+        let deep = [b"[".repeat(100_000), b"] sh".to_vec()].concat();
+        assert_eq!(kind(&deep), (ParseErrorKind::TooDeep, MAX_NESTING));
+        let deep = [b"<</A ".repeat(100_000), b">> sh".to_vec()].concat();
+        assert_eq!(kind(&deep), (ParseErrorKind::TooDeep, MAX_NESTING * 5));
+        let nested = [
+            b"[".repeat(MAX_NESTING),
+            b"]".repeat(MAX_NESTING),
+            b" sh".to_vec(),
+        ];
+        assert!(parse(&nested.concat()).is_ok());
+
+        // Operations before the error are still produced, and nothing after it
+        let mut parser = ContentParser::new(b"q ] Q");
+        assert_eq!(parser.next(), Some(Ok(op("q", vec![]))));
+        assert!(matches!(parser.next(), Some(Err(_))));
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn lenient() {
+        let mut parser = ContentParser::new_with(b"1 --2 m", ParseMode::Lenient);
+        assert_eq!(parser.next(), Some(Ok(op("m", vec![int(1), int(-2)]))));
+        assert_eq!(parser.next(), None);
+        let warnings = parser.into_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, WarningKind::DoubleSign);
+    }
+}
@@ -0,0 +1,1482 @@
+//! Executing content streams against the graphics state. See PDF Spec sections 8.4 and 9.3
+
+use std::collections::HashMap;
+
+use super::{ContentError, Op, OpParser, TextItem};
+use crate::Document;
+use crate::ast::object::{Dictionary, Name, Number, Object, Reference, Stream};
+use crate::error::{Error, ParseMode, Result};
+use crate::filter::decode_stream;
+use crate::geometry::{Matrix, Rectangle};
+use crate::page::Page;
+
+/// The width of glyphs in fonts that don't give one, in glyph space
+static DEFAULT_COMPOSITE_WIDTH: f64 = 1000.0;
+
+/// How deeply Form XObjects can draw each other
+static MAX_FORM_DEPTH: usize = 32;
+
+/// How many Form XObjects a content stream can draw, counting those drawn by other forms, so that
+/// forms that each draw the next more than once can't take exponential time
+static MAX_FORM_RUNS: usize = 10_000;
+
+/// How the inside of a path is decided, for filling and clipping. See PDF Spec section 8.5.3.3
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+/// How a path is painted: stroked, filled, or both
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Paint {
+    pub stroke: bool,
+    pub fill: Option<FillRule>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    /// Two control points and the end point
+    CurveTo([f64; 6]),
+    ClosePath,
+}
+
+/// A path in device space. `v`, `y` and `re` are expanded into the other segments.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    pub segments: Vec<PathSegment>,
+}
+
+/// A path that everything drawn is clipped to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clip {
+    pub path: Path,
+    pub rule: FillRule,
+}
+
+/// A colour space. See PDF Spec section 8.6
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColorSpace {
+    DeviceGray,
+    DeviceRgb,
+    DeviceCmyk,
+    Pattern,
+    /// Any other colour space, e.g. `[/ICCBased 5 0 R]`, as given in the resource dictionary
+    Other(Object),
+}
+
+impl ColorSpace {
+    /// Reads a colour space from its name or array
+    fn from_object(obj: &Object) -> ColorSpace {
+        let family = match obj {
+            Object::Array(array) => array.first().and_then(Object::as_name),
+            obj => obj.as_name(),
+        };
+        match family.map(Name::as_bytes) {
+            Some(b"DeviceGray" | b"G") => ColorSpace::DeviceGray,
+            Some(b"DeviceRGB" | b"RGB") => ColorSpace::DeviceRgb,
+            Some(b"DeviceCMYK" | b"CMYK") => ColorSpace::DeviceCmyk,
+            Some(b"Pattern") => ColorSpace::Pattern,
+            _ => ColorSpace::Other(obj.clone()),
+        }
+    }
+
+    /// The colour a colour space starts with when it's selected. See PDF Spec section 8.6.8
+    fn initial_color(&self, doc: &Document) -> Result<Color> {
+        let components = match self {
+            ColorSpace::DeviceGray => vec![0.0],
+            ColorSpace::DeviceRgb => vec![0.0; 3],
+            ColorSpace::DeviceCmyk => vec![0.0, 0.0, 0.0, 1.0],
+            ColorSpace::Pattern => Vec::new(),
+            ColorSpace::Other(Object::Array(array)) => {
+                let family = array.first().and_then(Object::as_name);
+                match family.map(Name::as_bytes) {
+                    Some(b"CalGray" | b"Indexed") => vec![0.0],
+                    Some(b"CalRGB" | b"Lab") => vec![0.0; 3],
+                    Some(b"Separation") => vec![1.0],
+                    Some(b"DeviceN") => {
+                        let names = doc.resolve(array.get(1).unwrap_or(&Object::Null))?;
+                        vec![1.0; names.as_array().map_or(1, <[_]>::len)]
+                    }
+                    Some(b"ICCBased") => {
+                        let profile = doc.resolve(array.get(1).unwrap_or(&Object::Null))?;
+                        let n = profile
+                            .as_stream()
+                            .and_then(|profile| profile.dict.get(b"N"))
+                            .and_then(Object::as_integer);
+                        vec![0.0; n.unwrap_or(0).clamp(0, 32) as usize]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            ColorSpace::Other(_) => Vec::new(),
+        };
+        Ok(Color {
+            components,
+            pattern: None,
+        })
+    }
+}
+
+/// A colour in the current colour space
+#[derive(Clone, Debug, PartialEq)]
+pub struct Color {
+    pub components: Vec<f64>,
+    /// The name of the `Pattern` resource, in pattern colour spaces
+    pub pattern: Option<Name>,
+}
+
+/// The dash pattern for stroking lines. See PDF Spec section 8.4.3.6
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dash {
+    pub array: Vec<f64>,
+    pub phase: f64,
+}
+
+/// The metrics of a font needed to position its glyphs. See PDF Spec sections 9.6 and 9.7
+///
+/// Composite (Type 0) fonts are assumed to use two-byte codes, as with the `Identity-H` encoding,
+/// and only horizontal writing is supported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Font {
+    pub dict: Dictionary,
+    composite: bool,
+    widths: HashMap<u32, f64>,
+    /// The width of glyphs not in `widths`
+    default_width: f64,
+    /// Maps glyph space to text space
+    matrix: Matrix,
+}
+
+impl Font {
+    /// Reads the metrics from a font dictionary. Missing or malformed widths are taken as the
+    /// default width.
+    pub fn load(doc: &Document, dict: Dictionary) -> Result<Self> {
+        let mut widths = HashMap::new();
+        let resolve = |key: &[u8], dict: &Dictionary| match dict.get(key) {
+            Some(obj) => doc.resolve(obj),
+            None => Ok(Object::Null),
+        };
+        let number = |obj: &Object| -> Result<Option<f64>> {
+            Ok(doc.resolve(obj)?.as_number().map(Number::as_f64))
+        };
+
+        let subtype = dict.get(b"Subtype").and_then(Object::as_name);
+        let composite = subtype.is_some_and(|subtype| subtype.as_bytes() == b"Type0");
+        let default_width = if composite {
+            let descendants = resolve(b"DescendantFonts", &dict)?;
+            let descendant = match descendants.as_array().and_then(<[_]>::first) {
+                Some(descendant) => doc.resolve(descendant)?,
+                None => Object::Null,
+            };
+            let descendant = descendant.as_dictionary().cloned().unwrap_or_default();
+            // Either `c [w1 w2 ...]`, giving the widths of consecutive codes from `c`, or
+            // `c_first c_last w`. See PDF Spec section 9.7.4.3
+            let w = resolve(b"W", &descendant)?;
+            let mut w = w.as_array().unwrap_or_default().iter();
+            while let (Some(first), Some(next)) = (w.next(), w.next()) {
+                let Some(first) = doc.resolve(first)?.as_integer() else {
+                    break;
+                };
+                let Ok(first) = u32::try_from(first) else {
+                    break;
+                };
+                match doc.resolve(next)? {
+                    Object::Array(array) => {
+                        for (code, width) in (first..=u32::MAX).zip(&array) {
+                            if let Some(width) = number(width)? {
+                                widths.insert(code, width);
+                            }
+                        }
+                    }
+                    last => {
+                        let (Some(last), Some(width)) = (last.as_integer(), w.next()) else {
+                            break;
+                        };
+                        let Some(width) = number(width)? else {
+                            break;
+                        };
+                        // Guard against huge ranges
+                        let last = u32::try_from(last.max(0)).unwrap_or(u32::MAX);
+                        for code in first..=last.min(first.saturating_add(0xFFFF)) {
+                            widths.insert(code, width);
+                        }
+                    }
+                }
+            }
+            number(&resolve(b"DW", &descendant)?)?.unwrap_or(DEFAULT_COMPOSITE_WIDTH)
+        } else {
+            let first = resolve(b"FirstChar", &dict)?.as_integer().unwrap_or(0);
+            if let Ok(first) = u32::try_from(first) {
+                let font_widths = resolve(b"Widths", &dict)?;
+                for (code, width) in
+                    (first..=u32::MAX).zip(font_widths.as_array().unwrap_or_default())
+                {
+                    if let Some(width) = number(width)? {
+                        widths.insert(code, width);
+                    }
+                }
+            }
+            let descriptor = resolve(b"FontDescriptor", &dict)?;
+            match descriptor.as_dictionary() {
+                Some(descriptor) => number(&resolve(b"MissingWidth", descriptor)?)?.unwrap_or(0.0),
+                None => 0.0,
+            }
+        };
+
+        // Only Type 3 fonts have their own glyph space. See PDF Spec section 9.6.5
+        let matrix = match resolve(b"FontMatrix", &dict)?.as_array() {
+            Some(array) if subtype.is_some_and(|subtype| subtype.as_bytes() == b"Type3") => {
+                Matrix::from_array(array)
+            }
+            _ => None,
+        };
+        Ok(Font {
+            dict,
+            composite,
+            widths,
+            default_width,
+            matrix: matrix.unwrap_or(Matrix::scale(0.001, 0.001)),
+        })
+    }
+
+    /// Splits the bytes of a shown string into character codes
+    pub fn codes<'a>(&self, bytes: &'a [u8]) -> impl Iterator<Item = u32> + 'a {
+        let size = if self.composite { 2 } else { 1 };
+        bytes
+            .chunks(size)
+            .map(|code| code.iter().fold(0, |acc, &b| (acc << 8) | b as u32))
+    }
+
+    /// The horizontal displacement of the glyph for `code`, in text space for a font size of 1
+    pub fn width(&self, code: u32) -> f64 {
+        let width = self.widths.get(&code).copied();
+        self.matrix
+            .transform_vector(width.unwrap_or(self.default_width), 0.0)
+            .0
+    }
+}
+
+impl Default for Font {
+    /// A font without any glyph widths
+    fn default() -> Self {
+        Font {
+            dict: Dictionary::new(),
+            composite: false,
+            widths: HashMap::new(),
+            default_width: 0.0,
+            matrix: Matrix::scale(0.001, 0.001),
+        }
+    }
+}
+
+/// The text state parameters of the graphics state. See PDF Spec section 9.3
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextState {
+    pub char_spacing: f64,
+    pub word_spacing: f64,
+    /// `Tz` as a fraction rather than a percentage
+    pub horizontal_scaling: f64,
+    pub leading: f64,
+    pub font: Option<Font>,
+    pub font_size: f64,
+    pub render_mode: i64,
+    pub rise: f64,
+}
+
+impl Default for TextState {
+    fn default() -> Self {
+        TextState {
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            font: None,
+            font_size: 0.0,
+            render_mode: 0,
+            rise: 0.0,
+        }
+    }
+}
+
+/// The graphics state, saved by `q` and restored by `Q`. See PDF Spec section 8.4
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphicsState {
+    /// The current transformation matrix, from user space to device space
+    pub ctm: Matrix,
+    /// The paths, in device space, that everything drawn is clipped to. Nothing is clipped if
+    /// there are none.
+    pub clip: Vec<Clip>,
+    pub stroke_color_space: ColorSpace,
+    pub fill_color_space: ColorSpace,
+    pub stroke_color: Color,
+    pub fill_color: Color,
+    /// In user space
+    pub line_width: f64,
+    pub line_cap: i64,
+    pub line_join: i64,
+    pub miter_limit: f64,
+    pub dash: Dash,
+    pub rendering_intent: Name,
+    pub flatness: f64,
+    /// `/CA` from an `ExtGState`
+    pub stroke_alpha: f64,
+    /// `/ca` from an `ExtGState`
+    pub fill_alpha: f64,
+    pub blend_mode: Name,
+    /// `/SMask` from an `ExtGState`, unless it was `/None`
+    pub soft_mask: Option<Object>,
+    pub text: TextState,
+}
+
+impl GraphicsState {
+    /// The state at the start of a page, whose user space is mapped to device space by `ctm`.
+    /// See PDF Spec tables 51 and 52
+    pub fn new(ctm: Matrix) -> Self {
+        let black = Color {
+            components: vec![0.0],
+            pattern: None,
+        };
+        GraphicsState {
+            ctm,
+            clip: Vec::new(),
+            stroke_color_space: ColorSpace::DeviceGray,
+            fill_color_space: ColorSpace::DeviceGray,
+            stroke_color: black.clone(),
+            fill_color: black,
+            line_width: 1.0,
+            line_cap: 0,
+            line_join: 0,
+            miter_limit: 10.0,
+            dash: Dash::default(),
+            rendering_intent: Name::from(b"RelativeColorimetric"),
+            flatness: 1.0,
+            stroke_alpha: 1.0,
+            fill_alpha: 1.0,
+            blend_mode: Name::from(b"Normal"),
+            soft_mask: None,
+            text: TextState::default(),
+        }
+    }
+}
+
+impl Default for GraphicsState {
+    fn default() -> Self {
+        Self::new(Matrix::identity())
+    }
+}
+
+/// A glyph shown by a text-showing operator
+#[derive(Clone, Debug, PartialEq)]
+pub struct Glyph {
+    /// The character code: one byte for simple fonts and two for composite ones
+    pub code: u32,
+    /// The text rendering matrix, which maps text space for a font size of 1 to device space,
+    /// with the glyph's origin at `(0, 0)`. See PDF Spec section 9.4.4
+    pub matrix: Matrix,
+    /// The glyph's width in the same space, so it ends at `matrix.transform_point(width, 0.0)`
+    pub width: f64,
+}
+
+/// Receives what a content stream draws from an [`Interpreter`]. Coordinates are in device
+/// space, and `state` is the graphics state at the time.
+pub trait Visitor {
+    /// A path was stroked or filled (or both). Paths that are only used for clipping aren't
+    /// passed here; they're added to `state.clip` for what's drawn after.
+    fn path(&mut self, _path: &Path, _paint: Paint, _state: &GraphicsState) {}
+
+    /// A text-showing operator showed these glyphs
+    fn text(&mut self, _glyphs: &[Glyph], _state: &GraphicsState) {}
+
+    /// An image XObject or inline image was painted into the unit square of user space, which
+    /// `state.ctm` maps to device space. The data is still encoded, and an inline image keeps
+    /// the abbreviations in its dictionary.
+    fn image(&mut self, _image: &Stream, _state: &GraphicsState) {}
+
+    /// `sh` painted a shading, given as its dictionary or stream
+    fn shading(&mut self, _shading: &Object, _state: &GraphicsState) {}
+
+    /// `MP` or `DP`, with the property list of `DP`
+    fn marked_content_point(&mut self, _tag: &Name, _properties: Option<&Dictionary>) {}
+
+    /// `BMC` or `BDC`, with the property list of `BDC`
+    fn begin_marked_content(&mut self, _tag: &Name, _properties: Option<&Dictionary>) {}
+
+    /// `EMC`
+    fn end_marked_content(&mut self) {}
+}
+
+/// Executes content streams, keeping track of the graphics state and telling a [`Visitor`] what
+/// was drawn. Form XObjects are drawn as part of the content that paints them.
+///
+/// In strict mode, missing resources, unbalanced `Q`s, forms that draw themselves and operators
+/// with invalid operands are errors. In lenient mode they're ignored.
+pub struct Interpreter<'doc, V> {
+    doc: &'doc Document,
+    visitor: V,
+    state: GraphicsState,
+    stack: Vec<GraphicsState>,
+    /// The depth of `stack` when the current content stream started, which `Q` can't go below
+    floor: usize,
+    /// The path being constructed, in device space
+    path: Vec<PathSegment>,
+    current_point: Option<(f64, f64)>,
+    subpath_start: Option<(f64, f64)>,
+    /// Set by `W` or `W*`, and applied once the path is painted
+    pending_clip: Option<FillRule>,
+    text_matrix: Matrix,
+    text_line_matrix: Matrix,
+    /// The Form XObjects being drawn
+    forms: Vec<Reference>,
+    form_depth: usize,
+    /// How many forms the current page or content stream has drawn
+    form_runs: usize,
+}
+
+impl<'doc, V: Visitor> Interpreter<'doc, V> {
+    /// An interpreter whose user space is mapped to device space by `ctm`, such as
+    /// [`Page::display_matrix`]
+    pub fn new(doc: &'doc Document, ctm: Matrix, visitor: V) -> Self {
+        Self {
+            doc,
+            visitor,
+            state: GraphicsState::new(ctm),
+            stack: Vec::new(),
+            floor: 0,
+            path: Vec::new(),
+            current_point: None,
+            subpath_start: None,
+            pending_clip: None,
+            text_matrix: Matrix::identity(),
+            text_line_matrix: Matrix::identity(),
+            forms: Vec::new(),
+            form_depth: 0,
+            form_runs: 0,
+        }
+    }
+
+    pub fn state(&self) -> &GraphicsState {
+        &self.state
+    }
+
+    pub fn visitor(&mut self) -> &mut V {
+        &mut self.visitor
+    }
+
+    pub fn into_visitor(self) -> V {
+        self.visitor
+    }
+
+    /// Executes the contents of `page` with its resources
+    pub fn run_page(&mut self, page: &Page) -> Result<()> {
+        let content = page.contents(self.doc)?;
+        let resources = page.resources().cloned().unwrap_or_default();
+        self.run(&content, &resources)
+    }
+
+    /// Executes (decoded) content stream data, looking up named resources in `resources`
+    pub fn run(&mut self, content: &[u8], resources: &Dictionary) -> Result<()> {
+        if self.form_depth == 0 {
+            self.form_runs = 0;
+        }
+        let floor = std::mem::replace(&mut self.floor, self.stack.len());
+        let result = self.execute_all(content, resources);
+        self.floor = floor;
+        result
+    }
+
+    fn execute_all(&mut self, content: &[u8], resources: &Dictionary) -> Result<()> {
+        for op in OpParser::new_with(content, self.doc.mode()) {
+            match op {
+                Ok(op) => self.execute(op, resources)?,
+                Err(Error::Content(e)) => self.recover(e)?,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails in strict mode, and carries on in lenient mode
+    fn recover(&self, error: ContentError) -> Result<()> {
+        match self.doc.mode() {
+            ParseMode::Strict => Err(error.into()),
+            ParseMode::Lenient => Ok(()),
+        }
+    }
+
+    fn execute(&mut self, op: Op, resources: &Dictionary) -> Result<()> {
+        let state = &mut self.state;
+        match op {
+            Op::SetLineWidth(width) => state.line_width = width.as_f64(),
+            Op::SetLineCap(cap) => state.line_cap = cap,
+            Op::SetLineJoin(join) => state.line_join = join,
+            Op::SetMiterLimit(limit) => state.miter_limit = limit.as_f64(),
+            Op::SetDash(array, phase) => {
+                state.dash = Dash {
+                    array: array.into_iter().map(Number::as_f64).collect(),
+                    phase: phase.as_f64(),
+                }
+            }
+            Op::SetRenderingIntent(intent) => state.rendering_intent = intent,
+            Op::SetFlatness(flatness) => state.flatness = flatness.as_f64(),
+            Op::SetExtGState(name) => {
+                let dict = match self.resource(resources, b"ExtGState", &name)? {
+                    Some(dict) => self.doc.resolve(&dict)?,
+                    None => Object::Null,
+                };
+                match dict.as_dictionary() {
+                    Some(dict) => self.ext_gstate(dict)?,
+                    None => self.recover(ContentError::MissingResource(name))?,
+                }
+            }
+
+            Op::Save => self.stack.push(self.state.clone()),
+            Op::Restore => {
+                if self.stack.len() > self.floor
+                    && let Some(state) = self.stack.pop()
+                {
+                    self.state = state;
+                } else {
+                    self.recover(ContentError::UnbalancedRestore)?;
+                }
+            }
+            Op::Transform(m) => state.ctm = matrix(m).then(&state.ctm),
+
+            Op::MoveTo(x, y) => {
+                let point = self.point(x.as_f64(), y.as_f64());
+                self.path.push(PathSegment::MoveTo(point.0, point.1));
+                self.current_point = Some(point);
+                self.subpath_start = Some(point);
+            }
+            Op::LineTo(x, y) => self.line_to(self.point(x.as_f64(), y.as_f64())),
+            Op::CurveTo([x1, y1, x2, y2, x3, y3]) => {
+                let points =
+                    [(x1, y1), (x2, y2), (x3, y3)].map(|(x, y)| self.point(x.as_f64(), y.as_f64()));
+                self.curve_to(points);
+            }
+            Op::CurveToV([x2, y2, x3, y3]) => {
+                let [p2, p3] =
+                    [(x2, y2), (x3, y3)].map(|(x, y)| self.point(x.as_f64(), y.as_f64()));
+                self.curve_to([self.current_point.unwrap_or(p2), p2, p3]);
+            }
+            Op::CurveToY([x1, y1, x3, y3]) => {
+                let [p1, p3] =
+                    [(x1, y1), (x3, y3)].map(|(x, y)| self.point(x.as_f64(), y.as_f64()));
+                self.curve_to([p1, p3, p3]);
+            }
+            Op::ClosePath => self.close_path(),
+            Op::Rectangle([x, y, width, height]) => {
+                let [x, y, width, height] = [x, y, width, height].map(Number::as_f64);
+                self.rectangle(x, y, width, height);
+            }
+
+            Op::Stroke => self.paint(false, true, None),
+            Op::CloseStroke => self.paint(true, true, None),
+            Op::Fill => self.paint(false, false, Some(FillRule::NonZero)),
+            Op::FillEvenOdd => self.paint(false, false, Some(FillRule::EvenOdd)),
+            Op::FillStroke => self.paint(false, true, Some(FillRule::NonZero)),
+            Op::FillStrokeEvenOdd => self.paint(false, true, Some(FillRule::EvenOdd)),
+            Op::CloseFillStroke => self.paint(true, true, Some(FillRule::NonZero)),
+            Op::CloseFillStrokeEvenOdd => self.paint(true, true, Some(FillRule::EvenOdd)),
+            Op::EndPath => self.paint(false, false, None),
+            Op::Clip => self.pending_clip = Some(FillRule::NonZero),
+            Op::ClipEvenOdd => self.pending_clip = Some(FillRule::EvenOdd),
+
+            Op::BeginText => {
+                self.text_matrix = Matrix::identity();
+                self.text_line_matrix = Matrix::identity();
+            }
+            Op::EndText => {}
+            Op::SetCharSpacing(spacing) => state.text.char_spacing = spacing.as_f64(),
+            Op::SetWordSpacing(spacing) => state.text.word_spacing = spacing.as_f64(),
+            Op::SetHorizontalScaling(scale) => {
+                state.text.horizontal_scaling = scale.as_f64() / 100.0
+            }
+            Op::SetLeading(leading) => state.text.leading = leading.as_f64(),
+            Op::SetFont(name, size) => {
+                self.state.text.font_size = size.as_f64();
+                let font = match self.resource(resources, b"Font", &name)? {
+                    Some(font) => self.doc.resolve(&font)?,
+                    None => Object::Null,
+                };
+                let font = match font {
+                    Object::Dictionary(font) => Some(Font::load(self.doc, font)?),
+                    _ => None,
+                };
+                self.state.text.font = font;
+                if self.state.text.font.is_none() {
+                    self.recover(ContentError::MissingResource(name))?;
+                }
+            }
+            Op::SetTextRenderingMode(mode) => state.text.render_mode = mode,
+            Op::SetTextRise(rise) => state.text.rise = rise.as_f64(),
+            Op::MoveText(x, y) => self.move_text(x.as_f64(), y.as_f64()),
+            Op::MoveTextSetLeading(x, y) => {
+                state.text.leading = -y.as_f64();
+                self.move_text(x.as_f64(), y.as_f64());
+            }
+            Op::SetTextMatrix(m) => {
+                self.text_matrix = matrix(m);
+                self.text_line_matrix = self.text_matrix;
+            }
+            Op::NextLine => self.move_text(0.0, -self.state.text.leading),
+            Op::ShowText(text) => self.show_text(&[TextItem::Text(text)]),
+            Op::ShowTextAdjusted(items) => self.show_text(&items),
+            Op::NextLineShowText(text) => {
+                self.move_text(0.0, -self.state.text.leading);
+                self.show_text(&[TextItem::Text(text)]);
+            }
+            Op::NextLineShowTextSpaced(word_spacing, char_spacing, text) => {
+                state.text.word_spacing = word_spacing.as_f64();
+                state.text.char_spacing = char_spacing.as_f64();
+                self.move_text(0.0, -self.state.text.leading);
+                self.show_text(&[TextItem::Text(text)]);
+            }
+            Op::SetCharWidth(..) | Op::SetCacheDevice(_) => {}
+
+            Op::SetStrokeColorSpace(name) => {
+                if let Some(space) = self.color_space(&name, resources)? {
+                    self.state.stroke_color = space.initial_color(self.doc)?;
+                    self.state.stroke_color_space = space;
+                }
+            }
+            Op::SetFillColorSpace(name) => {
+                if let Some(space) = self.color_space(&name, resources)? {
+                    self.state.fill_color = space.initial_color(self.doc)?;
+                    self.state.fill_color_space = space;
+                }
+            }
+            Op::SetStrokeColor(components) => state.stroke_color = color(&components, None),
+            Op::SetStrokeColorN(components, pattern) => {
+                state.stroke_color = color(&components, pattern)
+            }
+            Op::SetFillColor(components) => state.fill_color = color(&components, None),
+            Op::SetFillColorN(components, pattern) => {
+                state.fill_color = color(&components, pattern)
+            }
+            Op::SetStrokeGray(gray) => {
+                state.stroke_color_space = ColorSpace::DeviceGray;
+                state.stroke_color = color(&[gray], None);
+            }
+            Op::SetFillGray(gray) => {
+                state.fill_color_space = ColorSpace::DeviceGray;
+                state.fill_color = color(&[gray], None);
+            }
+            Op::SetStrokeRgb(rgb) => {
+                state.stroke_color_space = ColorSpace::DeviceRgb;
+                state.stroke_color = color(&rgb, None);
+            }
+            Op::SetFillRgb(rgb) => {
+                state.fill_color_space = ColorSpace::DeviceRgb;
+                state.fill_color = color(&rgb, None);
+            }
+            Op::SetStrokeCmyk(cmyk) => {
+                state.stroke_color_space = ColorSpace::DeviceCmyk;
+                state.stroke_color = color(&cmyk, None);
+            }
+            Op::SetFillCmyk(cmyk) => {
+                state.fill_color_space = ColorSpace::DeviceCmyk;
+                state.fill_color = color(&cmyk, None);
+            }
+
+            Op::PaintShading(name) => match self.resource(resources, b"Shading", &name)? {
+                Some(shading) => {
+                    let shading = self.doc.resolve(&shading)?;
+                    self.visitor.shading(&shading, &self.state);
+                }
+                None => self.recover(ContentError::MissingResource(name))?,
+            },
+            Op::InlineImage(image) => self.visitor.image(&image, &self.state),
+            Op::PaintXObject(name) => {
+                let xobject = self.resource(resources, b"XObject", &name)?;
+                let reference = xobject.as_ref().and_then(Object::as_reference);
+                let xobject = match xobject {
+                    Some(xobject) => self.doc.resolve(&xobject)?,
+                    None => Object::Null,
+                };
+                let Object::Stream(xobject) = xobject else {
+                    return self.recover(ContentError::MissingResource(name));
+                };
+                let subtype = xobject.dict.get(b"Subtype").and_then(Object::as_name);
+                match subtype.map(Name::as_bytes) {
+                    Some(b"Image") => self.visitor.image(&xobject, &self.state),
+                    Some(b"Form") => self.form(reference, &xobject, resources)?,
+                    _ => {}
+                }
+            }
+
+            Op::MarkedContentPoint(tag) => self.visitor.marked_content_point(&tag, None),
+            Op::MarkedContentPointProperties(tag, properties) => {
+                let properties = self.properties(properties, resources)?;
+                self.visitor.marked_content_point(&tag, properties.as_ref());
+            }
+            Op::BeginMarkedContent(tag) => self.visitor.begin_marked_content(&tag, None),
+            Op::BeginMarkedContentProperties(tag, properties) => {
+                let properties = self.properties(properties, resources)?;
+                self.visitor.begin_marked_content(&tag, properties.as_ref());
+            }
+            Op::EndMarkedContent => self.visitor.end_marked_content(),
+
+            Op::BeginCompatibility | Op::EndCompatibility | Op::Unknown(_) => {}
+        }
+        Ok(())
+    }
+
+    /// The (unresolved) resource called `name` in the `category` subdictionary
+    fn resource(
+        &self,
+        resources: &Dictionary,
+        category: &[u8],
+        name: &Name,
+    ) -> Result<Option<Object>> {
+        let Some(category) = resources.get(category) else {
+            return Ok(None);
+        };
+        let category = self.doc.resolve(category)?;
+        let resource = category
+            .as_dictionary()
+            .and_then(|category| category.get(name.as_bytes()));
+        match resource {
+            Some(resource) => Ok(Some(resource.clone())),
+            None => Ok(None),
+        }
+    }
+
+    /// Applies the entries of a graphics state parameter dictionary. See PDF Spec section 8.4.5
+    fn ext_gstate(&mut self, dict: &Dictionary) -> Result<()> {
+        for (key, value) in dict.iter() {
+            let value = self.doc.resolve(value)?;
+            let number = value.as_number().map(Number::as_f64);
+            let state = &mut self.state;
+            match (key.as_bytes(), number) {
+                (b"LW", Some(width)) => state.line_width = width,
+                (b"LC", Some(cap)) => state.line_cap = cap as i64,
+                (b"LJ", Some(join)) => state.line_join = join as i64,
+                (b"ML", Some(limit)) => state.miter_limit = limit,
+                (b"FL", Some(flatness)) => state.flatness = flatness,
+                (b"CA", Some(alpha)) => state.stroke_alpha = alpha,
+                (b"ca", Some(alpha)) => state.fill_alpha = alpha,
+                (b"D", _) => {
+                    if let Some([Object::Array(array), phase]) = value.as_array()
+                        && let Some(array) = array
+                            .iter()
+                            .map(|n| n.as_number().map(Number::as_f64))
+                            .collect::<Option<_>>()
+                        && let Some(phase) = phase.as_number()
+                    {
+                        state.dash = Dash {
+                            array,
+                            phase: phase.as_f64(),
+                        };
+                    }
+                }
+                (b"RI", _) => {
+                    if let Object::Name(intent) = value {
+                        state.rendering_intent = intent;
+                    }
+                }
+                // A blend mode, or an array of them to use the first supported one from
+                (b"BM", _) => {
+                    let mode = match &value {
+                        Object::Array(modes) => modes.first().and_then(Object::as_name),
+                        mode => mode.as_name(),
+                    };
+                    if let Some(mode) = mode {
+                        state.blend_mode = mode.clone();
+                    }
+                }
+                (b"SMask", _) => {
+                    state.soft_mask = match value {
+                        Object::Name(none) if none == b"None"[..] => None,
+                        mask => Some(mask),
+                    }
+                }
+                (b"Font", _) => {
+                    if let Some([font, size]) = value.as_array()
+                        && let Object::Dictionary(font) = self.doc.resolve(font)?
+                        && let Some(size) = size.as_number()
+                    {
+                        self.state.text.font = Some(Font::load(self.doc, font)?);
+                        self.state.text.font_size = size.as_f64();
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks up a colour space by name: a device colour space, `Pattern`, or a `ColorSpace`
+    /// resource
+    fn color_space(&self, name: &Name, resources: &Dictionary) -> Result<Option<ColorSpace>> {
+        let space = ColorSpace::from_object(&Object::Name(name.clone()));
+        if !matches!(space, ColorSpace::Other(_)) {
+            return Ok(Some(space));
+        }
+        match self.resource(resources, b"ColorSpace", name)? {
+            Some(space) => Ok(Some(ColorSpace::from_object(&self.doc.resolve(&space)?))),
+            None => {
+                self.recover(ContentError::MissingResource(name.clone()))?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// The property list of `DP` or `BDC`, given directly or as a `Properties` resource
+    fn properties(&self, properties: Object, resources: &Dictionary) -> Result<Option<Dictionary>> {
+        let properties = match properties {
+            Object::Name(name) => match self.resource(resources, b"Properties", &name)? {
+                Some(properties) => self.doc.resolve(&properties)?,
+                None => {
+                    self.recover(ContentError::MissingResource(name))?;
+                    return Ok(None);
+                }
+            },
+            properties => properties,
+        };
+        Ok(properties.as_dictionary().cloned())
+    }
+
+    /// Draws a Form XObject, with its own resources if it has them. See PDF Spec section 8.10
+    fn form(
+        &mut self,
+        reference: Option<Reference>,
+        form: &Stream,
+        resources: &Dictionary,
+    ) -> Result<()> {
+        if let Some(reference) = reference
+            && self.forms.contains(&reference)
+        {
+            return self.recover(ContentError::FormCycle(reference));
+        }
+        if self.form_depth >= MAX_FORM_DEPTH {
+            return self.recover(ContentError::FormsTooDeep);
+        }
+        if self.form_runs >= MAX_FORM_RUNS {
+            return self.recover(ContentError::TooManyForms);
+        }
+        self.form_runs += 1;
+        let resolve = |key: &[u8]| match form.dict.get(key) {
+            Some(obj) => self.doc.resolve(obj),
+            None => Ok(Object::Null),
+        };
+        let content = decode_stream(form)?;
+        let form_matrix = resolve(b"Matrix")?
+            .as_array()
+            .and_then(Matrix::from_array)
+            .unwrap_or_default();
+        let bbox = resolve(b"BBox")?.as_array().and_then(Rectangle::from_array);
+        let form_resources = match resolve(b"Resources")? {
+            Object::Dictionary(form_resources) => form_resources,
+            _ => resources.clone(),
+        };
+
+        let saved = self.state.clone();
+        let depth = self.stack.len();
+        self.state.ctm = form_matrix.then(&self.state.ctm);
+        if let Some(bbox) = bbox {
+            let path = std::mem::take(&mut self.path);
+            let (x0, y0) = (bbox.x0.as_f64(), bbox.y0.as_f64());
+            self.rectangle(x0, y0, bbox.width(), bbox.height());
+            let bbox = Path {
+                segments: std::mem::replace(&mut self.path, path),
+            };
+            self.state.clip.push(Clip {
+                path: bbox,
+                rule: FillRule::NonZero,
+            });
+        }
+        self.forms.extend(reference);
+        self.form_depth += 1;
+        let result = self.run(&content, &form_resources);
+        self.form_depth -= 1;
+        if reference.is_some() {
+            self.forms.pop();
+        }
+        // The form's `q`s and `Q`s may not balance
+        self.stack.truncate(depth);
+        self.state = saved;
+        result
+    }
+
+    /// Transforms a point in user space to device space
+    fn point(&self, x: f64, y: f64) -> (f64, f64) {
+        self.state.ctm.transform_point(x, y)
+    }
+
+    fn line_to(&mut self, point: (f64, f64)) {
+        self.path.push(PathSegment::LineTo(point.0, point.1));
+        self.current_point = Some(point);
+    }
+
+    fn curve_to(&mut self, [p1, p2, p3]: [(f64, f64); 3]) {
+        self.path
+            .push(PathSegment::CurveTo([p1.0, p1.1, p2.0, p2.1, p3.0, p3.1]));
+        self.current_point = Some(p3);
+    }
+
+    fn close_path(&mut self) {
+        if self.current_point.is_some() && self.path.last() != Some(&PathSegment::ClosePath) {
+            self.path.push(PathSegment::ClosePath);
+            self.current_point = self.subpath_start;
+        }
+    }
+
+    /// Appends a rectangle in user space as a closed subpath
+    fn rectangle(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        let start = self.point(x, y);
+        self.path.push(PathSegment::MoveTo(start.0, start.1));
+        self.current_point = Some(start);
+        self.subpath_start = Some(start);
+        self.line_to(self.point(x + width, y));
+        self.line_to(self.point(x + width, y + height));
+        self.line_to(self.point(x, y + height));
+        self.close_path();
+    }
+
+    /// Ends the current path, painting it and then clipping to it if `W` or `W*` came before
+    fn paint(&mut self, close: bool, stroke: bool, fill: Option<FillRule>) {
+        if close {
+            self.close_path();
+        }
+        let path = Path {
+            segments: std::mem::take(&mut self.path),
+        };
+        if stroke || fill.is_some() {
+            self.visitor
+                .path(&path, Paint { stroke, fill }, &self.state);
+        }
+        if let Some(rule) = self.pending_clip.take() {
+            self.state.clip.push(Clip { path, rule });
+        }
+        self.current_point = None;
+        self.subpath_start = None;
+    }
+
+    /// Starts a new line, offset from the start of the current one. See PDF Spec section 9.4.2
+    fn move_text(&mut self, x: f64, y: f64) {
+        self.text_line_matrix = Matrix::translate(x, y).then(&self.text_line_matrix);
+        self.text_matrix = self.text_line_matrix;
+    }
+
+    /// Shows strings, moving the text matrix past each glyph. See PDF Spec section 9.4.4
+    fn show_text(&mut self, items: &[TextItem]) {
+        let text = &self.state.text;
+        let default_font = Font::default();
+        let font = text.font.as_ref().unwrap_or(&default_font);
+        let size = text.font_size;
+        let scale = text.horizontal_scaling;
+        let glyph_matrix = Matrix::new(size * scale, 0.0, 0.0, size, 0.0, text.rise);
+
+        let mut glyphs = Vec::new();
+        for item in items {
+            match item {
+                TextItem::Text(string) => {
+                    for code in font.codes(string.as_bytes()) {
+                        let width = font.width(code);
+                        glyphs.push(Glyph {
+                            code,
+                            matrix: glyph_matrix.then(&self.text_matrix).then(&self.state.ctm),
+                            width,
+                        });
+                        // Word spacing only applies to the single-byte code 32
+                        let word_spacing = match code == 32 && !font.composite {
+                            true => text.word_spacing,
+                            false => 0.0,
+                        };
+                        let advance = (width * size + text.char_spacing + word_spacing) * scale;
+                        self.text_matrix = Matrix::translate(advance, 0.0).then(&self.text_matrix);
+                    }
+                }
+                TextItem::Adjust(adjust) => {
+                    let advance = -adjust.as_f64() / 1000.0 * size * scale;
+                    self.text_matrix = Matrix::translate(advance, 0.0).then(&self.text_matrix);
+                }
+            }
+        }
+        self.visitor.text(&glyphs, &self.state);
+    }
+}
+
+fn matrix(m: [Number; 6]) -> Matrix {
+    let [a, b, c, d, e, f] = m.map(Number::as_f64);
+    Matrix::new(a, b, c, d, e, f)
+}
+
+fn color(components: &[Number], pattern: Option<Name>) -> Color {
+    Color {
+        components: components.iter().copied().map(Number::as_f64).collect(),
+        pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Version;
+    use crate::filter::{Flate, encode_stream};
+    use crate::writer::Writer;
+
+    fn reference(number: u32) -> Object {
+        Object::Reference(Reference {
+            number,
+            generation: 0,
+        })
+    }
+
+    fn name(name: &[u8]) -> Object {
+        Object::Name(Name::from(name))
+    }
+
+    fn int(val: i64) -> Object {
+        Object::Number(Number::Integer(val))
+    }
+
+    fn array(vals: &[i64]) -> Object {
+        Object::Array(vals.iter().map(|val| int(*val)).collect())
+    }
+
+    fn dictionary(entries: &[(&[u8], Object)]) -> Dictionary {
+        entries
+            .iter()
+            .map(|(key, val)| (Name::from(*key), val.clone()))
+            .collect()
+    }
+
+    fn dict(entries: &[(&[u8], Object)]) -> Object {
+        Object::Dictionary(dictionary(entries))
+    }
+
+    fn stream(entries: &[(&[u8], Object)], data: &[u8]) -> Object {
+        let mut stream = encode_stream(data, &[&Flate::default()]);
+        for (key, val) in entries {
+            stream.dict.insert(Name::from(*key), val.clone());
+        }
+        Object::Stream(stream)
+    }
+
+    /// Writes a document with a single page, object 3, whose resources are `resources` and whose
+    /// content is object 4. The rest of the objects are numbered from 5.
+    fn open(resources: Object, content: &[u8], objects: Vec<Object>, mode: ParseMode) -> Document {
+        let mut writer = Writer::new(Version { major: 1, minor: 7 });
+        let root = writer.add(dict(&[
+            (b"Type", name(b"Catalog")),
+            (b"Pages", reference(2)),
+        ]));
+        writer.add(dict(&[
+            (b"Type", name(b"Pages")),
+            (b"Kids", Object::Array(vec![reference(3)])),
+        ]));
+        writer.add(dict(&[
+            (b"Type", name(b"Page")),
+            (b"Resources", resources),
+            (b"Contents", reference(4)),
+        ]));
+        writer.add(stream(&[], content));
+        for obj in objects {
+            writer.add(obj);
+        }
+        writer
+            .trailer_mut()
+            .insert(Name::from(b"Root"), Object::Reference(root));
+        Document::open_with(writer.finish().unwrap(), mode).unwrap()
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Path(Path, Paint, Box<GraphicsState>),
+        Text(Vec<Glyph>),
+        Image(Option<Object>, Matrix, usize),
+        Begin(Name, Option<Dictionary>),
+        End,
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<Event>);
+
+    impl Visitor for Recorder {
+        fn path(&mut self, path: &Path, paint: Paint, state: &GraphicsState) {
+            self.0
+                .push(Event::Path(path.clone(), paint, Box::new(state.clone())));
+        }
+
+        fn text(&mut self, glyphs: &[Glyph], _state: &GraphicsState) {
+            self.0.push(Event::Text(glyphs.to_vec()));
+        }
+
+        fn image(&mut self, image: &Stream, state: &GraphicsState) {
+            let subtype = image.dict.get(b"Subtype").cloned();
+            self.0
+                .push(Event::Image(subtype, state.ctm, state.clip.len()));
+        }
+
+        fn begin_marked_content(&mut self, tag: &Name, properties: Option<&Dictionary>) {
+            self.0.push(Event::Begin(tag.clone(), properties.cloned()));
+        }
+
+        fn end_marked_content(&mut self) {
+            self.0.push(Event::End);
+        }
+    }
+
+    fn run(doc: &Document) -> Result<Vec<Event>> {
+        let pages = doc.pages()?;
+        let mut interpreter = Interpreter::new(doc, Matrix::identity(), Recorder::default());
+        interpreter.run_page(pages.get(0).unwrap())?;
+        Ok(interpreter.into_visitor().0)
+    }
+
+    fn rectangle(x0: f64, y0: f64, x1: f64, y1: f64) -> Path {
+        Path {
+            segments: vec![
+                PathSegment::MoveTo(x0, y0),
+                PathSegment::LineTo(x1, y0),
+                PathSegment::LineTo(x1, y1),
+                PathSegment::LineTo(x0, y1),
+                PathSegment::ClosePath,
+            ],
+        }
+    }
+
+    #[test]
+    fn graphics_state() {
+        let resources = dict(&[
+            (
+                b"ExtGState",
+                dict(&[(
+                    b"GS1",
+                    dict(&[
+                        (b"LW", int(3)),
+                        (b"CA", Object::Number(Number::Real(0.5))),
+                        (b"D", Object::Array(vec![array(&[2, 1]), int(0)])),
+                        (
+                            b"BM",
+                            Object::Array(vec![name(b"Multiply"), name(b"Normal")]),
+                        ),
+                    ]),
+                )]),
+            ),
+            (
+                b"ColorSpace",
+                dict(&[(b"CS1", Object::Array(vec![name(b"ICCBased"), reference(5)]))]),
+            ),
+        ]);
+        let content = b"q 1 0 0 1 100 200 cm /GS1 gs 0 0 m 10 0 l 10 10 5 10 v S Q \
+            0 0 10 10 re W n /CS1 cs 1 0 0 RG 0 0 1 1 re b*";
+        let doc = open(
+            resources,
+            content,
+            vec![stream(&[(b"N", int(3))], b"")],
+            ParseMode::Strict,
+        );
+        let events = run(&doc).unwrap();
+
+        let mut state = GraphicsState {
+            ctm: Matrix::translate(100.0, 200.0),
+            line_width: 3.0,
+            stroke_alpha: 0.5,
+            dash: Dash {
+                array: vec![2.0, 1.0],
+                phase: 0.0,
+            },
+            blend_mode: Name::from(b"Multiply"),
+            ..GraphicsState::default()
+        };
+        let stroked = Path {
+            segments: vec![
+                PathSegment::MoveTo(100.0, 200.0),
+                PathSegment::LineTo(110.0, 200.0),
+                PathSegment::CurveTo([110.0, 200.0, 110.0, 210.0, 105.0, 210.0]),
+            ],
+        };
+        let stroke = Paint {
+            stroke: true,
+            fill: None,
+        };
+        assert_eq!(
+            events[0],
+            Event::Path(stroked, stroke, Box::new(state.clone()))
+        );
+
+        // Restored by `Q`, then clipped and recoloured
+        state = GraphicsState::default();
+        state.clip.push(Clip {
+            path: rectangle(0.0, 0.0, 10.0, 10.0),
+            rule: FillRule::NonZero,
+        });
+        state.fill_color_space =
+            ColorSpace::Other(Object::Array(vec![name(b"ICCBased"), reference(5)]));
+        state.fill_color.components = vec![0.0; 3];
+        state.stroke_color_space = ColorSpace::DeviceRgb;
+        state.stroke_color.components = vec![1.0, 0.0, 0.0];
+        let paint = Paint {
+            stroke: true,
+            fill: Some(FillRule::EvenOdd),
+        };
+        assert_eq!(
+            events[1],
+            Event::Path(rectangle(0.0, 0.0, 1.0, 1.0), paint, Box::new(state))
+        );
+        assert_eq!(events.len(), 2);
+    }
+
+    #[test]
+    fn text() {
+        let resources = dict(&[(
+            b"Font",
+            dict(&[
+                (
+                    b"F1",
+                    dict(&[
+                        (b"Subtype", name(b"TrueType")),
+                        (b"FirstChar", int(65)),
+                        (b"Widths", array(&[500, 600])),
+                    ]),
+                ),
+                (b"F2", reference(5)),
+            ]),
+        )]);
+        let content = b"BT /F1 10 Tf 200 Tz 1 Tc 5 5 Td [(AB) -1000 (A)] TJ \
+            /F2 1 Tf 100 Tz 0 Tc 12 TL (\x00\x01\x00\x02) ' ET";
+        let doc = open(
+            resources,
+            content,
+            vec![
+                dict(&[
+                    (b"Subtype", name(b"Type0")),
+                    (b"DescendantFonts", Object::Array(vec![reference(6)])),
+                ]),
+                dict(&[
+                    (b"DW", int(800)),
+                    (b"W", Object::Array(vec![int(1), array(&[250])])),
+                ]),
+            ],
+            ParseMode::Strict,
+        );
+        let events = run(&doc).unwrap();
+        let glyph = |code, e, f, a, d, width| Glyph {
+            code,
+            matrix: Matrix::new(a, 0.0, 0.0, d, e, f),
+            width,
+        };
+        assert_eq!(
+            events,
+            [
+                // Each glyph moves on by `(width × size + Tc) × Th`, and the adjustment by
+                // `1000 / 1000 × size × Th`
+                Event::Text(vec![
+                    glyph(65, 5.0, 5.0, 20.0, 10.0, 0.5),
+                    glyph(66, 17.0, 5.0, 20.0, 10.0, 0.6),
+                    glyph(65, 51.0, 5.0, 20.0, 10.0, 0.5),
+                ]),
+                // The next line starts back at x = 5
+                Event::Text(vec![
+                    glyph(1, 5.0, -7.0, 1.0, 1.0, 0.25),
+                    glyph(2, 5.25, -7.0, 1.0, 1.0, 0.8),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn font_widths_out_of_range() {
+        let doc = open(dict(&[]), b"", vec![], ParseMode::Strict);
+        let simple = |first: i64| {
+            let font = dictionary(&[(b"FirstChar", int(first)), (b"Widths", array(&[500, 600]))]);
+            Font::load(&doc, font).unwrap()
+        };
+        assert_eq!(simple(i64::MAX).width(0), 0.0);
+        assert_eq!(simple(-1).width(0), 0.0);
+        let font = simple(u32::MAX as i64);
+        assert_eq!(font.width(u32::MAX), 0.5);
+        assert_eq!(font.width(0), 0.0);
+
+        let composite = |w: Vec<Object>| {
+            let font = dictionary(&[
+                (b"Subtype", name(b"Type0")),
+                (
+                    b"DescendantFonts",
+                    Object::Array(vec![dict(&[(b"W", Object::Array(w))])]),
+                ),
+            ]);
+            Font::load(&doc, font).unwrap()
+        };
+        let font = composite(vec![int(i64::MAX), array(&[250])]);
+        assert_eq!(font.width(0), 1.0);
+        let font = composite(vec![int(i64::MAX - 1), int(i64::MAX), int(250)]);
+        assert_eq!(font.width(0), 1.0);
+        let font = composite(vec![int(u32::MAX as i64 - 1), array(&[250, 300, 350])]);
+        assert_eq!(font.width(u32::MAX - 1), 0.25);
+        assert_eq!(font.width(u32::MAX), 0.3);
+        // Codes past `u32::MAX` don't wrap around to 0
+        let font = composite(vec![int(u32::MAX as i64 - 1), int(1 << 33), int(250)]);
+        assert_eq!(font.width(u32::MAX), 0.25);
+        assert_eq!(font.width(0), 1.0);
+
+        // As found through a page
+        let resources = dict(&[(
+            b"Font",
+            dict(&[(
+                b"F1",
+                dict(&[(b"FirstChar", int(i64::MAX)), (b"Widths", array(&[500]))]),
+            )]),
+        )]);
+        let doc = open(
+            resources,
+            b"BT /F1 1 Tf (a) Tj ET",
+            vec![],
+            ParseMode::Strict,
+        );
+        assert_eq!(run(&doc).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn forms_and_images() {
+        let resources = dict(&[
+            (
+                b"XObject",
+                dict(&[(b"Fm1", reference(5)), (b"Im1", reference(6))]),
+            ),
+            (b"Properties", dict(&[(b"P1", dict(&[(b"MCID", int(0))]))])),
+        ]);
+        let content = b"/OC /P1 BDC /Fm1 Do EMC /Im1 Do \
+            BI /W 1 /H 1 /CS /G /BPC 8 ID \x80 EI";
+        let doc = open(
+            resources,
+            content,
+            vec![
+                // The form's content is clipped to its bounding box and uses the page's resources
+                stream(
+                    &[
+                        (b"Subtype", name(b"Form")),
+                        (b"Matrix", array(&[2, 0, 0, 2, 0, 0])),
+                        (b"BBox", array(&[0, 0, 10, 10])),
+                    ],
+                    b"q 0 0 5 5 re f /Im1 Do",
+                ),
+                stream(&[(b"Subtype", name(b"Image"))], b"\x00"),
+            ],
+            ParseMode::Strict,
+        );
+        let events = run(&doc).unwrap();
+
+        let mut state = GraphicsState::new(Matrix::scale(2.0, 2.0));
+        state.clip.push(Clip {
+            path: rectangle(0.0, 0.0, 20.0, 20.0),
+            rule: FillRule::NonZero,
+        });
+        let fill = Paint {
+            stroke: false,
+            fill: Some(FillRule::NonZero),
+        };
+        assert_eq!(
+            events,
+            [
+                Event::Begin(Name::from(b"OC"), Some(dictionary(&[(b"MCID", int(0))]))),
+                Event::Path(rectangle(0.0, 0.0, 10.0, 10.0), fill, Box::new(state)),
+                Event::Image(Some(name(b"Image")), Matrix::scale(2.0, 2.0), 1),
+                Event::End,
+                // The form's state doesn't leak out, despite its unbalanced `q`
+                Event::Image(Some(name(b"Image")), Matrix::identity(), 0),
+                Event::Image(None, Matrix::identity(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn form_limits() {
+        // A chain of `len` forms from object 5, where each draws the next `times` times and fills
+        // a square
+        let chain = |len: u32, times: usize, mode| {
+            let forms = (0..len)
+                .map(|i| {
+                    let content = [b"/F Do ".repeat(times), b"0 0 1 1 re f".to_vec()].concat();
+                    let next = dict(&[(b"XObject", dict(&[(b"F", reference(6 + i))]))]);
+                    let content = if i + 1 < len {
+                        &content[..]
+                    } else {
+                        b"0 0 1 1 re f"
+                    };
+                    stream(
+                        &[(b"Subtype", name(b"Form")), (b"Resources", next)],
+                        content,
+                    )
+                })
+                .collect();
+            let resources = dict(&[(b"XObject", dict(&[(b"F", reference(5))]))]);
+            open(resources, b"/F Do", forms, mode)
+        };
+
+        // Drawn 2^20 times without a budget
+        let doc = chain(20, 2, ParseMode::Strict);
+        assert_eq!(run(&doc), Err(Error::Content(ContentError::TooManyForms)));
+        let doc = chain(20, 2, ParseMode::Lenient);
+        assert_eq!(run(&doc).unwrap().len(), MAX_FORM_RUNS);
+
+        let doc = chain(40, 1, ParseMode::Strict);
+        assert_eq!(run(&doc), Err(Error::Content(ContentError::FormsTooDeep)));
+        let doc = chain(40, 1, ParseMode::Lenient);
+        assert_eq!(run(&doc).unwrap().len(), MAX_FORM_DEPTH);
+    }
+
+    #[test]
+    fn errors() {
+        let missing = |name: &[u8]| {
+            Err(Error::Content(ContentError::MissingResource(Name::from(
+                name,
+            ))))
+        };
+        let resources = || {
+            dict(&[(
+                b"XObject",
+                dict(&[(b"Fm1", reference(5)), (b"Fm2", reference(6))]),
+            )])
+        };
+        let form = |content: &[u8]| stream(&[(b"Subtype", name(b"Form"))], content);
+        let page = |content: &[u8], mode| {
+            open(
+                resources(),
+                content,
+                vec![form(b"/Fm2 Do"), form(b"/Fm1 Do")],
+                mode,
+            )
+        };
+
+        let doc = page(b"/Im9 Do", ParseMode::Strict);
+        assert_eq!(run(&doc), missing(b"Im9"));
+        let doc = page(b"/GS9 gs", ParseMode::Strict);
+        assert_eq!(run(&doc), missing(b"GS9"));
+        let doc = page(b"q Q Q", ParseMode::Strict);
+        assert_eq!(
+            run(&doc),
+            Err(Error::Content(ContentError::UnbalancedRestore))
+        );
+        let doc = page(b"/Fm1 Do", ParseMode::Strict);
+        assert_eq!(
+            run(&doc),
+            Err(Error::Content(ContentError::FormCycle(Reference {
+                number: 5,
+                generation: 0
+            })))
+        );
+        let doc = page(b"1 2 cm", ParseMode::Strict);
+        assert_eq!(
+            run(&doc),
+            Err(Error::Content(ContentError::InvalidOperands(
+                b"cm".to_vec()
+            )))
+        );
+
+        // Lenient mode carries on past all of them
+        let doc = page(
+            b"/Im9 Do /GS9 gs Q /Fm1 Do 1 2 cm /F9 1 Tf (a) Tj 0 0 1 1 re S",
+            ParseMode::Lenient,
+        );
+        let events = run(&doc).unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], Event::Text(glyphs) if glyphs.len() == 1));
+        assert!(matches!(&events[1], Event::Path(..)));
+    }
+}
@@ -0,0 +1,727 @@
+//! Typed content stream operators. See PDF Spec Annex A, table A.1
+
+use super::{ContentError, ContentParser, Operation};
+use crate::ast::object::{Name, Number, Object, PdfString, Stream};
+use crate::error::{ParseMode, Result};
+
+/// Every operator in PDF Spec Annex A. `ID` and `EI` only appear as part of an inline image, which
+/// is a single [`Op::InlineImage`].
+static OPERATORS: [&[u8]; 73] = [
+    b"b", b"B", b"b*", b"B*", b"BDC", b"BI", b"BMC", b"BT", b"BX", b"c", b"cm", b"CS", b"cs", b"d",
+    b"d0", b"d1", b"Do", b"DP", b"EI", b"EMC", b"ET", b"EX", b"f", b"F", b"f*", b"G", b"g", b"gs",
+    b"h", b"i", b"ID", b"j", b"J", b"K", b"k", b"l", b"m", b"M", b"MP", b"n", b"q", b"Q", b"re",
+    b"RG", b"rg", b"ri", b"s", b"S", b"SC", b"sc", b"SCN", b"scn", b"sh", b"T*", b"Tc", b"Td",
+    b"TD", b"Tf", b"Tj", b"TJ", b"TL", b"Tm", b"Tr", b"Ts", b"Tw", b"Tz", b"v", b"w", b"W", b"W*",
+    b"y", b"'", b"\"",
+];
+
+/// An element of the array shown by `TJ`
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextItem {
+    Text(PdfString),
+    /// An adjustment to the position of the next glyph, in thousandths of a unit of text space.
+    /// Positive numbers move it left (for horizontal writing).
+    Adjust(Number),
+}
+
+/// A content stream operator with its operands
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    // General graphics state. See PDF Spec section 8.4.4
+    /// `w`
+    SetLineWidth(Number),
+    /// `J`
+    SetLineCap(i64),
+    /// `j`
+    SetLineJoin(i64),
+    /// `M`
+    SetMiterLimit(Number),
+    /// `d`: the dash array and phase
+    SetDash(Vec<Number>, Number),
+    /// `ri`
+    SetRenderingIntent(Name),
+    /// `i`
+    SetFlatness(Number),
+    /// `gs`: the name of an `ExtGState` resource
+    SetExtGState(Name),
+
+    // Special graphics state
+    /// `q`
+    Save,
+    /// `Q`
+    Restore,
+    /// `cm`: the matrix to concatenate with the current transformation matrix
+    Transform([Number; 6]),
+
+    // Path construction. See PDF Spec section 8.5.2
+    /// `m`
+    MoveTo(Number, Number),
+    /// `l`
+    LineTo(Number, Number),
+    /// `c`: two control points and the end point
+    CurveTo([Number; 6]),
+    /// `v`: the second control point and the end point. The first is the current point.
+    CurveToV([Number; 4]),
+    /// `y`: the first control point and the end point. The second is the end point.
+    CurveToY([Number; 4]),
+    /// `h`
+    ClosePath,
+    /// `re`: x, y, width and height
+    Rectangle([Number; 4]),
+
+    // Path painting. See PDF Spec section 8.5.3
+    /// `S`
+    Stroke,
+    /// `s`
+    CloseStroke,
+    /// `f`, or the obsolete `F`
+    Fill,
+    /// `f*`
+    FillEvenOdd,
+    /// `B`
+    FillStroke,
+    /// `B*`
+    FillStrokeEvenOdd,
+    /// `b`
+    CloseFillStroke,
+    /// `b*`
+    CloseFillStrokeEvenOdd,
+    /// `n`
+    EndPath,
+
+    // Clipping paths. See PDF Spec section 8.5.4
+    /// `W`
+    Clip,
+    /// `W*`
+    ClipEvenOdd,
+
+    // Text objects and state. See PDF Spec sections 9.3 and 9.4
+    /// `BT`
+    BeginText,
+    /// `ET`
+    EndText,
+    /// `Tc`
+    SetCharSpacing(Number),
+    /// `Tw`
+    SetWordSpacing(Number),
+    /// `Tz`: a percentage
+    SetHorizontalScaling(Number),
+    /// `TL`
+    SetLeading(Number),
+    /// `Tf`: the name of a `Font` resource, and the size
+    SetFont(Name, Number),
+    /// `Tr`
+    SetTextRenderingMode(i64),
+    /// `Ts`
+    SetTextRise(Number),
+
+    // Text positioning
+    /// `Td`
+    MoveText(Number, Number),
+    /// `TD`, which also sets the leading to the negated y offset
+    MoveTextSetLeading(Number, Number),
+    /// `Tm`
+    SetTextMatrix([Number; 6]),
+    /// `T*`
+    NextLine,
+
+    // Text showing
+    /// `Tj`
+    ShowText(PdfString),
+    /// `TJ`
+    ShowTextAdjusted(Vec<TextItem>),
+    /// `'`
+    NextLineShowText(PdfString),
+    /// `"`: the word spacing, character spacing and text
+    NextLineShowTextSpaced(Number, Number, PdfString),
+
+    // Type 3 fonts. See PDF Spec section 9.6.5
+    /// `d0`: the glyph's width
+    SetCharWidth(Number, Number),
+    /// `d1`: the glyph's width and bounding box
+    SetCacheDevice([Number; 6]),
+
+    // Colour. See PDF Spec section 8.6.8
+    /// `CS`: the name of a device colour space or a `ColorSpace` resource
+    SetStrokeColorSpace(Name),
+    /// `cs`
+    SetFillColorSpace(Name),
+    /// `SC`
+    SetStrokeColor(Vec<Number>),
+    /// `SCN`: the components, and the name of a `Pattern` resource for pattern colour spaces
+    SetStrokeColorN(Vec<Number>, Option<Name>),
+    /// `sc`
+    SetFillColor(Vec<Number>),
+    /// `scn`
+    SetFillColorN(Vec<Number>, Option<Name>),
+    /// `G`
+    SetStrokeGray(Number),
+    /// `g`
+    SetFillGray(Number),
+    /// `RG`
+    SetStrokeRgb([Number; 3]),
+    /// `rg`
+    SetFillRgb([Number; 3]),
+    /// `K`
+    SetStrokeCmyk([Number; 4]),
+    /// `k`
+    SetFillCmyk([Number; 4]),
+
+    /// `sh`: the name of a `Shading` resource
+    PaintShading(Name),
+    /// `BI ... ID ... EI`, as parsed by [`ContentParser`]
+    InlineImage(Stream),
+    /// `Do`: the name of an `XObject` resource
+    PaintXObject(Name),
+
+    // Marked content. See PDF Spec section 14.6
+    /// `MP`: the tag
+    MarkedContentPoint(Name),
+    /// `DP`: the tag, and a property list or the name of a `Properties` resource
+    MarkedContentPointProperties(Name, Object),
+    /// `BMC`
+    BeginMarkedContent(Name),
+    /// `BDC`
+    BeginMarkedContentProperties(Name, Object),
+    /// `EMC`
+    EndMarkedContent,
+
+    // Compatibility. See PDF Spec section 8.2
+    /// `BX`
+    BeginCompatibility,
+    /// `EX`
+    EndCompatibility,
+    /// An operator that isn't in PDF Spec Annex A, which is allowed inside a `BX`/`EX` section
+    Unknown(Operation),
+}
+
+impl Op {
+    /// The operator, e.g. `b"cm"` for [`Op::Transform`]. An inline image is `BI`.
+    pub fn operator(&self) -> &[u8] {
+        match self {
+            Op::SetLineWidth(_) => b"w",
+            Op::SetLineCap(_) => b"J",
+            Op::SetLineJoin(_) => b"j",
+            Op::SetMiterLimit(_) => b"M",
+            Op::SetDash(..) => b"d",
+            Op::SetRenderingIntent(_) => b"ri",
+            Op::SetFlatness(_) => b"i",
+            Op::SetExtGState(_) => b"gs",
+            Op::Save => b"q",
+            Op::Restore => b"Q",
+            Op::Transform(_) => b"cm",
+            Op::MoveTo(..) => b"m",
+            Op::LineTo(..) => b"l",
+            Op::CurveTo(_) => b"c",
+            Op::CurveToV(_) => b"v",
+            Op::CurveToY(_) => b"y",
+            Op::ClosePath => b"h",
+            Op::Rectangle(_) => b"re",
+            Op::Stroke => b"S",
+            Op::CloseStroke => b"s",
+            Op::Fill => b"f",
+            Op::FillEvenOdd => b"f*",
+            Op::FillStroke => b"B",
+            Op::FillStrokeEvenOdd => b"B*",
+            Op::CloseFillStroke => b"b",
+            Op::CloseFillStrokeEvenOdd => b"b*",
+            Op::EndPath => b"n",
+            Op::Clip => b"W",
+            Op::ClipEvenOdd => b"W*",
+            Op::BeginText => b"BT",
+            Op::EndText => b"ET",
+            Op::SetCharSpacing(_) => b"Tc",
+            Op::SetWordSpacing(_) => b"Tw",
+            Op::SetHorizontalScaling(_) => b"Tz",
+            Op::SetLeading(_) => b"TL",
+            Op::SetFont(..) => b"Tf",
+            Op::SetTextRenderingMode(_) => b"Tr",
+            Op::SetTextRise(_) => b"Ts",
+            Op::MoveText(..) => b"Td",
+            Op::MoveTextSetLeading(..) => b"TD",
+            Op::SetTextMatrix(_) => b"Tm",
+            Op::NextLine => b"T*",
+            Op::ShowText(_) => b"Tj",
+            Op::ShowTextAdjusted(_) => b"TJ",
+            Op::NextLineShowText(_) => b"'",
+            Op::NextLineShowTextSpaced(..) => b"\"",
+            Op::SetCharWidth(..) => b"d0",
+            Op::SetCacheDevice(_) => b"d1",
+            Op::SetStrokeColorSpace(_) => b"CS",
+            Op::SetFillColorSpace(_) => b"cs",
+            Op::SetStrokeColor(_) => b"SC",
+            Op::SetStrokeColorN(..) => b"SCN",
+            Op::SetFillColor(_) => b"sc",
+            Op::SetFillColorN(..) => b"scn",
+            Op::SetStrokeGray(_) => b"G",
+            Op::SetFillGray(_) => b"g",
+            Op::SetStrokeRgb(_) => b"RG",
+            Op::SetFillRgb(_) => b"rg",
+            Op::SetStrokeCmyk(_) => b"K",
+            Op::SetFillCmyk(_) => b"k",
+            Op::PaintShading(_) => b"sh",
+            Op::InlineImage(_) => b"BI",
+            Op::PaintXObject(_) => b"Do",
+            Op::MarkedContentPoint(_) => b"MP",
+            Op::MarkedContentPointProperties(..) => b"DP",
+            Op::BeginMarkedContent(_) => b"BMC",
+            Op::BeginMarkedContentProperties(..) => b"BDC",
+            Op::EndMarkedContent => b"EMC",
+            Op::BeginCompatibility => b"BX",
+            Op::EndCompatibility => b"EX",
+            Op::Unknown(operation) => &operation.operator,
+        }
+    }
+}
+
+impl TryFrom<Operation> for Op {
+    type Error = ContentError;
+
+    /// Checks the operands of `operation`. Unknown operators are an error; [`OpParser`] allows
+    /// them in compatibility sections.
+    fn try_from(operation: Operation) -> std::result::Result<Self, ContentError> {
+        let Operation { operator, operands } = operation;
+        if !OPERATORS.contains(&&operator[..]) {
+            return Err(ContentError::UnknownOperator(operator));
+        }
+        convert(&operator, operands).ok_or(ContentError::InvalidOperands(operator))
+    }
+}
+
+fn convert(operator: &[u8], operands: Vec<Object>) -> Option<Op> {
+    use Object as O;
+
+    let num = |obj: &Object| obj.as_number();
+    let int = |obj: &Object| obj.as_integer();
+    let name = |obj: &Object| obj.as_name().cloned();
+    let string = |obj: &Object| obj.as_string().cloned();
+    // A property list is given directly or as the name of a resource
+    let properties = |obj: &Object| match obj {
+        O::Name(_) | O::Dictionary(_) => Some(obj.clone()),
+        _ => None,
+    };
+    // The components of a colour, optionally followed by a pattern name
+    let color = |operands: &[Object], pattern: bool| {
+        let (components, name) = match operands.split_last() {
+            Some((O::Name(name), components)) if pattern => (components, Some(name.clone())),
+            _ => (operands, None),
+        };
+        let components = components.iter().map(num).collect::<Option<Vec<_>>>()?;
+        Some((components, name))
+    };
+
+    Some(match (operator, &operands[..]) {
+        (b"w", [width]) => Op::SetLineWidth(num(width)?),
+        (b"J", [cap]) => Op::SetLineCap(int(cap)?),
+        (b"j", [join]) => Op::SetLineJoin(int(join)?),
+        (b"M", [limit]) => Op::SetMiterLimit(num(limit)?),
+        (b"d", [O::Array(array), phase]) => {
+            Op::SetDash(array.iter().map(num).collect::<Option<_>>()?, num(phase)?)
+        }
+        (b"ri", [intent]) => Op::SetRenderingIntent(name(intent)?),
+        (b"i", [flatness]) => Op::SetFlatness(num(flatness)?),
+        (b"gs", [dict]) => Op::SetExtGState(name(dict)?),
+        (b"q", []) => Op::Save,
+        (b"Q", []) => Op::Restore,
+        (b"cm", operands) => Op::Transform(numbers(operands)?),
+
+        (b"m", [x, y]) => Op::MoveTo(num(x)?, num(y)?),
+        (b"l", [x, y]) => Op::LineTo(num(x)?, num(y)?),
+        (b"c", operands) => Op::CurveTo(numbers(operands)?),
+        (b"v", operands) => Op::CurveToV(numbers(operands)?),
+        (b"y", operands) => Op::CurveToY(numbers(operands)?),
+        (b"h", []) => Op::ClosePath,
+        (b"re", operands) => Op::Rectangle(numbers(operands)?),
+
+        (b"S", []) => Op::Stroke,
+        (b"s", []) => Op::CloseStroke,
+        (b"f" | b"F", []) => Op::Fill,
+        (b"f*", []) => Op::FillEvenOdd,
+        (b"B", []) => Op::FillStroke,
+        (b"B*", []) => Op::FillStrokeEvenOdd,
+        (b"b", []) => Op::CloseFillStroke,
+        (b"b*", []) => Op::CloseFillStrokeEvenOdd,
+        (b"n", []) => Op::EndPath,
+        (b"W", []) => Op::Clip,
+        (b"W*", []) => Op::ClipEvenOdd,
+
+        (b"BT", []) => Op::BeginText,
+        (b"ET", []) => Op::EndText,
+        (b"Tc", [spacing]) => Op::SetCharSpacing(num(spacing)?),
+        (b"Tw", [spacing]) => Op::SetWordSpacing(num(spacing)?),
+        (b"Tz", [scale]) => Op::SetHorizontalScaling(num(scale)?),
+        (b"TL", [leading]) => Op::SetLeading(num(leading)?),
+        (b"Tf", [font, size]) => Op::SetFont(name(font)?, num(size)?),
+        (b"Tr", [mode]) => Op::SetTextRenderingMode(int(mode)?),
+        (b"Ts", [rise]) => Op::SetTextRise(num(rise)?),
+        (b"Td", [x, y]) => Op::MoveText(num(x)?, num(y)?),
+        (b"TD", [x, y]) => Op::MoveTextSetLeading(num(x)?, num(y)?),
+        (b"Tm", operands) => Op::SetTextMatrix(numbers(operands)?),
+        (b"T*", []) => Op::NextLine,
+        (b"Tj", [text]) => Op::ShowText(string(text)?),
+        (b"TJ", [O::Array(items)]) => Op::ShowTextAdjusted(
+            items
+                .iter()
+                .map(|item| match item {
+                    O::String(text) => Some(TextItem::Text(text.clone())),
+                    O::Number(adjust) => Some(TextItem::Adjust(*adjust)),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?,
+        ),
+        (b"'", [text]) => Op::NextLineShowText(string(text)?),
+        (b"\"", [word, char, text]) => {
+            Op::NextLineShowTextSpaced(num(word)?, num(char)?, string(text)?)
+        }
+
+        (b"d0", [x, y]) => Op::SetCharWidth(num(x)?, num(y)?),
+        (b"d1", operands) => Op::SetCacheDevice(numbers(operands)?),
+
+        (b"CS", [space]) => Op::SetStrokeColorSpace(name(space)?),
+        (b"cs", [space]) => Op::SetFillColorSpace(name(space)?),
+        (b"SC", operands) if !operands.is_empty() => Op::SetStrokeColor(color(operands, false)?.0),
+        (b"sc", operands) if !operands.is_empty() => Op::SetFillColor(color(operands, false)?.0),
+        (b"SCN", operands) if !operands.is_empty() => {
+            let (components, pattern) = color(operands, true)?;
+            Op::SetStrokeColorN(components, pattern)
+        }
+        (b"scn", operands) if !operands.is_empty() => {
+            let (components, pattern) = color(operands, true)?;
+            Op::SetFillColorN(components, pattern)
+        }
+        (b"G", [gray]) => Op::SetStrokeGray(num(gray)?),
+        (b"g", [gray]) => Op::SetFillGray(num(gray)?),
+        (b"RG", operands) => Op::SetStrokeRgb(numbers(operands)?),
+        (b"rg", operands) => Op::SetFillRgb(numbers(operands)?),
+        (b"K", operands) => Op::SetStrokeCmyk(numbers(operands)?),
+        (b"k", operands) => Op::SetFillCmyk(numbers(operands)?),
+
+        (b"sh", [shading]) => Op::PaintShading(name(shading)?),
+        (b"BI", [O::Stream(image)]) => Op::InlineImage(image.clone()),
+        (b"Do", [xobject]) => Op::PaintXObject(name(xobject)?),
+
+        (b"MP", [tag]) => Op::MarkedContentPoint(name(tag)?),
+        (b"DP", [tag, props]) => Op::MarkedContentPointProperties(name(tag)?, properties(props)?),
+        (b"BMC", [tag]) => Op::BeginMarkedContent(name(tag)?),
+        (b"BDC", [tag, props]) => Op::BeginMarkedContentProperties(name(tag)?, properties(props)?),
+        (b"EMC", []) => Op::EndMarkedContent,
+        (b"BX", []) => Op::BeginCompatibility,
+        (b"EX", []) => Op::EndCompatibility,
+        _ => return None,
+    })
+}
+
+/// Exactly `N` numbers
+fn numbers<const N: usize>(operands: &[Object]) -> Option<[Number; N]> {
+    let numbers: Vec<Number> = operands
+        .iter()
+        .map(Object::as_number)
+        .collect::<Option<_>>()?;
+    numbers.try_into().ok()
+}
+
+impl From<Op> for Operation {
+    fn from(op: Op) -> Self {
+        let num = Object::Number;
+        let nums = |numbers: &[Number]| numbers.iter().copied().map(Object::Number).collect();
+        let name = Object::Name;
+        let string = Object::String;
+        let color = |components: Vec<Number>, pattern: Option<Name>| {
+            let mut operands: Vec<Object> = nums(&components);
+            operands.extend(pattern.map(Object::Name));
+            operands
+        };
+
+        let operator = op.operator().to_vec();
+        let operands = match op {
+            Op::Unknown(operation) => return operation,
+            Op::SetLineWidth(n)
+            | Op::SetMiterLimit(n)
+            | Op::SetFlatness(n)
+            | Op::SetCharSpacing(n)
+            | Op::SetWordSpacing(n)
+            | Op::SetHorizontalScaling(n)
+            | Op::SetLeading(n)
+            | Op::SetTextRise(n)
+            | Op::SetStrokeGray(n)
+            | Op::SetFillGray(n) => vec![num(n)],
+            Op::SetLineCap(i) | Op::SetLineJoin(i) | Op::SetTextRenderingMode(i) => {
+                vec![num(Number::Integer(i))]
+            }
+            Op::SetDash(array, phase) => vec![Object::Array(nums(&array)), num(phase)],
+            Op::SetRenderingIntent(n)
+            | Op::SetExtGState(n)
+            | Op::SetStrokeColorSpace(n)
+            | Op::SetFillColorSpace(n)
+            | Op::PaintShading(n)
+            | Op::PaintXObject(n)
+            | Op::MarkedContentPoint(n)
+            | Op::BeginMarkedContent(n) => vec![name(n)],
+            Op::Transform(m) | Op::CurveTo(m) | Op::SetTextMatrix(m) | Op::SetCacheDevice(m) => {
+                nums(&m)
+            }
+            Op::MoveTo(x, y)
+            | Op::LineTo(x, y)
+            | Op::MoveText(x, y)
+            | Op::MoveTextSetLeading(x, y)
+            | Op::SetCharWidth(x, y) => vec![num(x), num(y)],
+            Op::CurveToV(n)
+            | Op::CurveToY(n)
+            | Op::Rectangle(n)
+            | Op::SetStrokeCmyk(n)
+            | Op::SetFillCmyk(n) => nums(&n),
+            Op::SetStrokeRgb(n) | Op::SetFillRgb(n) => nums(&n),
+            Op::SetFont(font, size) => vec![name(font), num(size)],
+            Op::ShowText(text) | Op::NextLineShowText(text) => vec![string(text)],
+            Op::ShowTextAdjusted(items) => vec![Object::Array(
+                items
+                    .into_iter()
+                    .map(|item| match item {
+                        TextItem::Text(text) => string(text),
+                        TextItem::Adjust(adjust) => num(adjust),
+                    })
+                    .collect(),
+            )],
+            Op::NextLineShowTextSpaced(word, char, text) => {
+                vec![num(word), num(char), string(text)]
+            }
+            Op::SetStrokeColor(components) | Op::SetFillColor(components) => nums(&components),
+            Op::SetStrokeColorN(components, pattern) | Op::SetFillColorN(components, pattern) => {
+                color(components, pattern)
+            }
+            Op::InlineImage(image) => vec![Object::Stream(image)],
+            Op::MarkedContentPointProperties(tag, props)
+            | Op::BeginMarkedContentProperties(tag, props) => vec![name(tag), props],
+            Op::Save
+            | Op::Restore
+            | Op::ClosePath
+            | Op::Stroke
+            | Op::CloseStroke
+            | Op::Fill
+            | Op::FillEvenOdd
+            | Op::FillStroke
+            | Op::FillStrokeEvenOdd
+            | Op::CloseFillStroke
+            | Op::CloseFillStrokeEvenOdd
+            | Op::EndPath
+            | Op::Clip
+            | Op::ClipEvenOdd
+            | Op::BeginText
+            | Op::EndText
+            | Op::NextLine
+            | Op::EndMarkedContent
+            | Op::BeginCompatibility
+            | Op::EndCompatibility => Vec::new(),
+        };
+        Operation { operator, operands }
+    }
+}
+
+/// Parses content stream data into typed operators. Unknown operators are an error, except
+/// inside `BX`/`EX` compatibility sections, where they're kept as [`Op::Unknown`].
+///
+/// An operation with invalid operands gives an error, and parsing continues with the next one.
+/// Syntax errors end parsing, as with [`ContentParser`].
+pub struct OpParser<'input> {
+    parser: ContentParser<'input>,
+    /// How many `BX` sections deep we are
+    compatibility: usize,
+}
+
+impl<'input> OpParser<'input> {
+    /// Parses `input` strictly
+    pub fn new(input: &'input [u8]) -> Self {
+        Self::new_with(input, ParseMode::Strict)
+    }
+
+    /// Parses `input` according to `mode`
+    pub fn new_with(input: &'input [u8], mode: ParseMode) -> Self {
+        Self {
+            parser: ContentParser::new_with(input, mode),
+            compatibility: 0,
+        }
+    }
+}
+
+impl Iterator for OpParser<'_> {
+    type Item = Result<Op>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let operation = match self.parser.next()? {
+            Ok(operation) => operation,
+            Err(e) => return Some(Err(e)),
+        };
+        let is_known = OPERATORS.contains(&&operation.operator[..]);
+        if !is_known && self.compatibility > 0 {
+            return Some(Ok(Op::Unknown(operation)));
+        }
+        let op = match Op::try_from(operation) {
+            Ok(op) => op,
+            Err(e) => return Some(Err(e.into())),
+        };
+        match op {
+            Op::BeginCompatibility => self.compatibility += 1,
+            Op::EndCompatibility => self.compatibility = self.compatibility.saturating_sub(1),
+            _ => {}
+        }
+        Some(Ok(op))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::object::Dictionary;
+    use crate::error::Error;
+
+    fn parse(input: &[u8]) -> Vec<Result<Op>> {
+        OpParser::new(input).collect()
+    }
+
+    fn int(val: i64) -> Number {
+        Number::Integer(val)
+    }
+
+    fn name(name: &[u8]) -> Name {
+        Name::from(name)
+    }
+
+    fn literal(text: &[u8]) -> PdfString {
+        PdfString::Literal(text.to_vec())
+    }
+
+    #[test]
+    fn operators() {
+        let input = b"q 2 J 0.5 w [3 1] 0 d /GS1 gs 1 0 0 1 10 20 cm \
+            10 20 m 30 40 l 1 2 3 4 5 6 c 0 0 10 10 re h W* n \
+            /Pattern cs 0.1 0.2 /P1 scn /P2 scn 1 0 0 RG 0 0 0 1 k 0.5 SC \
+            BT /F1 12 Tf [(A) -120 <42>] TJ (x) ' 1 2 (y) \" 0 -14 TD T* ET \
+            /OC /L1 BDC /Im1 Do EMC /Tag <</K 1>> DP /Sh sh F Q";
+        let ops: Vec<Op> = parse(input).into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            ops,
+            [
+                Op::Save,
+                Op::SetLineCap(2),
+                Op::SetLineWidth(Number::Real(0.5)),
+                Op::SetDash(vec![int(3), int(1)], int(0)),
+                Op::SetExtGState(name(b"GS1")),
+                Op::Transform([1, 0, 0, 1, 10, 20].map(int)),
+                Op::MoveTo(int(10), int(20)),
+                Op::LineTo(int(30), int(40)),
+                Op::CurveTo([1, 2, 3, 4, 5, 6].map(int)),
+                Op::Rectangle([0, 0, 10, 10].map(int)),
+                Op::ClosePath,
+                Op::ClipEvenOdd,
+                Op::EndPath,
+                Op::SetFillColorSpace(name(b"Pattern")),
+                Op::SetFillColorN(
+                    vec![Number::Real(0.1), Number::Real(0.2)],
+                    Some(name(b"P1"))
+                ),
+                Op::SetFillColorN(vec![], Some(name(b"P2"))),
+                Op::SetStrokeRgb([1, 0, 0].map(int)),
+                Op::SetFillCmyk([0, 0, 0, 1].map(int)),
+                Op::SetStrokeColor(vec![Number::Real(0.5)]),
+                Op::BeginText,
+                Op::SetFont(name(b"F1"), int(12)),
+                Op::ShowTextAdjusted(vec![
+                    TextItem::Text(literal(b"A")),
+                    TextItem::Adjust(int(-120)),
+                    TextItem::Text(PdfString::Hex(b"B".to_vec())),
+                ]),
+                Op::NextLineShowText(literal(b"x")),
+                Op::NextLineShowTextSpaced(int(1), int(2), literal(b"y")),
+                Op::MoveTextSetLeading(int(0), int(-14)),
+                Op::NextLine,
+                Op::EndText,
+                Op::BeginMarkedContentProperties(name(b"OC"), Object::Name(name(b"L1"))),
+                Op::PaintXObject(name(b"Im1")),
+                Op::EndMarkedContent,
+                Op::MarkedContentPointProperties(
+                    name(b"Tag"),
+                    Object::Dictionary(Dictionary::from_iter([(
+                        name(b"K"),
+                        Object::Number(int(1))
+                    )]))
+                ),
+                Op::PaintShading(name(b"Sh")),
+                Op::Fill,
+                Op::Restore,
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = b"q 1 0 0 1 0 0 cm 0 g 1 1 m 2 2 3 3 v 4 4 5 5 y S 2 Tr 100 Tz 5 Ts \
+            BI /W 1 /H 1 /CS /G /BPC 8 ID \x80 EI 3 0 d0 0 0 0 0 1 1 d1 /Perceptual ri 1 i \
+            BX 1 2 foo EX Q";
+        let operations: Vec<Operation> = ContentParser::new(input).collect::<Result<_>>().unwrap();
+        let ops: Vec<Op> = OpParser::new(input).collect::<Result<_>>().unwrap();
+        assert_eq!(ops.len(), operations.len());
+        for (op, operation) in ops.into_iter().zip(operations) {
+            assert_eq!(op.operator(), &operation.operator[..]);
+            assert_eq!(Operation::from(op.clone()), operation);
+            if op.operator() != b"foo" {
+                assert_eq!(Op::try_from(operation), Ok(op));
+            }
+        }
+        // `F` is read as `f`
+        assert_eq!(
+            Operation::from(Op::try_from(Operation::new(b"F", vec![])).unwrap()),
+            Operation::new(b"f", vec![])
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let invalid = |operator: &[u8]| {
+            Err(Error::Content(ContentError::InvalidOperands(
+                operator.to_vec(),
+            )))
+        };
+        let unknown = |operator: &[u8]| {
+            Err(Error::Content(ContentError::UnknownOperator(
+                operator.to_vec(),
+            )))
+        };
+        assert_eq!(parse(b"1 2 cm"), [invalid(b"cm")]);
+        assert_eq!(parse(b"(a) w"), [invalid(b"w")]);
+        assert_eq!(parse(b"1.5 J"), [invalid(b"J")]);
+        assert_eq!(parse(b"q"), [Ok(Op::Save)]);
+        assert_eq!(parse(b"1 q"), [invalid(b"q")]);
+        assert_eq!(parse(b"[1 /A] TJ"), [invalid(b"TJ")]);
+        assert_eq!(parse(b"sc"), [invalid(b"sc")]);
+        assert_eq!(parse(b"1 /P SC"), [invalid(b"SC")]);
+        assert_eq!(parse(b"/A 1 BDC"), [invalid(b"BDC")]);
+        assert_eq!(parse(b"ID"), [invalid(b"ID")]);
+
+        // Unknown operators are only allowed in compatibility sections, which can nest. Parsing
+        // carries on after errors.
+        assert_eq!(
+            parse(b"foo BX bar BX baz EX qux EX quux q"),
+            [
+                unknown(b"foo"),
+                Ok(Op::BeginCompatibility),
+                Ok(Op::Unknown(Operation::new(b"bar", vec![]))),
+                Ok(Op::BeginCompatibility),
+                Ok(Op::Unknown(Operation::new(b"baz", vec![]))),
+                Ok(Op::EndCompatibility),
+                Ok(Op::Unknown(Operation::new(b"qux", vec![]))),
+                Ok(Op::EndCompatibility),
+                unknown(b"quux"),
+                Ok(Op::Save),
+            ]
+        );
+        // Known operators are still checked in compatibility sections
+        assert_eq!(
+            parse(b"BX 1 Q EX"),
+            [
+                Ok(Op::BeginCompatibility),
+                invalid(b"Q"),
+                Ok(Op::EndCompatibility)
+            ]
+        );
+    }
+}
@@ -0,0 +1,1103 @@
+//! Decryption and encryption of documents with the standard security handler. See PDF Spec
+//! section 7.6
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use aes::cipher::block_padding::{NoPadding, Pkcs7};
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use md5::{Digest, Md5};
+
+use crate::ast::object::{Dictionary, Name, Number, Object, PdfString, Reference, Stream};
+use crate::filter::stream_filters;
+
+mod aes256;
+mod permissions;
+mod rc4;
+
+pub use permissions::Permissions;
+use rc4::rc4;
+
+/// The bytes passwords are padded to 32 bytes with. See PDF Spec section 7.6.4.3
+static PASSWORD_PADDING: [u8; 32] = [
+    0x28, 0xbf, 0x4e, 0x5e, 0x4e, 0x75, 0x8a, 0x41, 0x64, 0x00, 0x4e, 0x56, 0xff, 0xfa, 0x01, 0x08,
+    0x2e, 0x2e, 0x00, 0xb6, 0xd0, 0x68, 0x3e, 0x80, 0x2f, 0x0c, 0xa9, 0xfe, 0x64, 0x53, 0x69, 0x7a,
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CryptError {
+    /// A security handler other than `/Standard`
+    UnsupportedHandler(Vec<u8>),
+    /// An algorithm (`/V`) and revision (`/R`) of the standard security handler we don't
+    /// implement
+    UnsupportedRevision { version: i64, revision: i64 },
+    /// A crypt filter method (`/CFM`) we don't implement, or a crypt filter that isn't defined
+    UnsupportedMethod(Vec<u8>),
+    /// An entry in the encryption dictionary that's missing or has an invalid value
+    InvalidEntry(Vec<u8>),
+    /// The password is neither the user nor the owner password
+    IncorrectPassword,
+    /// The encrypted data of the referenced object is malformed
+    InvalidData(Reference),
+}
+
+impl Display for CryptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CryptError::UnsupportedHandler(name) => write!(
+                f,
+                "Unsupported security handler /{}",
+                String::from_utf8_lossy(name)
+            ),
+            CryptError::UnsupportedRevision { version, revision } => write!(
+                f,
+                "Unsupported encryption algorithm {version} revision {revision}"
+            ),
+            CryptError::UnsupportedMethod(name) => write!(
+                f,
+                "Unsupported crypt filter /{}",
+                String::from_utf8_lossy(name)
+            ),
+            CryptError::InvalidEntry(key) => write!(
+                f,
+                "Invalid /{} in encryption dictionary",
+                String::from_utf8_lossy(key)
+            ),
+            CryptError::IncorrectPassword => write!(f, "Incorrect password"),
+            CryptError::InvalidData(r) => write!(
+                f,
+                "Object {} {} couldn't be decrypted",
+                r.number, r.generation
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CryptError {}
+
+/// How strings or streams are encrypted, as given by a crypt filter's `/CFM`. See PDF Spec
+/// section 7.6.6
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CryptMethod {
+    /// `/None`: the data isn't encrypted
+    Identity,
+    /// `/V2`: RC4
+    Rc4,
+    /// `/AESV2`: AES-128 in CBC mode
+    Aes128,
+    /// `/AESV3`: AES-256 in CBC mode
+    Aes256,
+}
+
+/// Which password a document was opened with
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Password {
+    /// The owner password, which grants every permission
+    Owner,
+    /// The user password, which grants the document's [`Permissions`]
+    User,
+}
+
+/// The cipher to encrypt a new document with
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// RC4 with a 128-bit key, from PDF 1.4
+    Rc4,
+    /// AES-128, from PDF 1.6
+    Aes128,
+    /// AES-256, from PDF 2.0
+    Aes256,
+}
+
+/// How to encrypt a new document with the standard security handler
+#[derive(Clone, Debug, PartialEq)]
+pub struct Encryption {
+    pub algorithm: Algorithm,
+    /// The password needed to open the document, which may be empty
+    pub user_password: Vec<u8>,
+    /// The password that grants every permission. If it's empty, the user password is used.
+    pub owner_password: Vec<u8>,
+    /// What may be done with the user password
+    pub permissions: Permissions,
+    /// Whether XMP metadata streams are encrypted. Leaving them unencrypted lets tools that
+    /// don't know the password index the document.
+    pub encrypt_metadata: bool,
+}
+
+impl Encryption {
+    /// Encrypts with `algorithm` and the given passwords, granting every permission with the
+    /// user password and encrypting metadata
+    pub fn new(algorithm: Algorithm, user_password: &[u8], owner_password: &[u8]) -> Self {
+        Self {
+            algorithm,
+            user_password: user_password.to_vec(),
+            owner_password: owner_password.to_vec(),
+            permissions: Permissions::all(),
+            encrypt_metadata: true,
+        }
+    }
+}
+
+/// The standard security handler, which derives the key that objects are encrypted with from a
+/// password. See PDF Spec section 7.6.4
+#[derive(Clone, Debug, PartialEq)]
+pub struct StandardSecurity {
+    /// `/R`, the revision of the handler
+    pub revision: i64,
+    /// `/P`, what may be done with the user password
+    pub permissions: Permissions,
+    /// Which password the document was opened with
+    pub password: Password,
+    /// `/EncryptMetadata`: whether XMP metadata streams are encrypted
+    pub encrypt_metadata: bool,
+    /// How strings are encrypted (`/StrF`)
+    pub string_method: CryptMethod,
+    /// How streams are encrypted (`/StmF`)
+    pub stream_method: CryptMethod,
+    /// How embedded files are encrypted (`/EFF`)
+    pub embedded_file_method: CryptMethod,
+    /// The crypt filters in `/CF`, for streams that choose one with a `Crypt` filter
+    crypt_filters: HashMap<Vec<u8>, CryptMethod>,
+    /// The file encryption key
+    key: Vec<u8>,
+}
+
+impl StandardSecurity {
+    /// Reads the encryption dictionary `encrypt` and authenticates `password` against it, as
+    /// either the owner or the user password. `id` is the first element of the trailer's `/ID`.
+    pub fn new(encrypt: &Dictionary, id: &[u8], password: &[u8]) -> Result<Self, CryptError> {
+        let invalid = |key: &[u8]| CryptError::InvalidEntry(key.to_vec());
+        let filter = encrypt
+            .get(b"Filter")
+            .and_then(Object::as_name)
+            .ok_or_else(|| invalid(b"Filter"))?;
+        if filter.as_bytes() != b"Standard" {
+            return Err(CryptError::UnsupportedHandler(filter.as_bytes().to_vec()));
+        }
+
+        let int = |key: &[u8]| match encrypt.get(key) {
+            None => Ok(None),
+            Some(val) => val.as_integer().map(Some).ok_or_else(|| invalid(key)),
+        };
+        let version = int(b"V")?.unwrap_or(0);
+        let revision = int(b"R")?.ok_or_else(|| invalid(b"R"))?;
+        match (version, revision) {
+            (1 | 2, 2 | 3) | (4, 4) | (5, 5 | 6) => {}
+            _ => return Err(CryptError::UnsupportedRevision { version, revision }),
+        }
+
+        // Reads a string of at least `len` bytes, ignoring any more
+        let string = |key: &[u8], len: usize| {
+            encrypt
+                .get(key)
+                .and_then(Object::as_string)
+                .map(PdfString::as_bytes)
+                .filter(|val| val.len() >= len)
+                .map(|val| &val[..len])
+                .ok_or_else(|| invalid(key))
+        };
+        // Some producers write the flags as an unsigned number
+        let permissions = int(b"P")?.ok_or_else(|| invalid(b"P"))? as u32 as i32;
+        let encrypt_metadata = match encrypt.get(b"EncryptMetadata") {
+            None => true,
+            Some(val) => val.as_bool().ok_or_else(|| invalid(b"EncryptMetadata"))?,
+        };
+
+        let (key, password) = if revision >= 5 {
+            let params = aes256::KeyParams {
+                revision,
+                owner: string(b"O", 48)?,
+                user: string(b"U", 48)?,
+                owner_key: string(b"OE", 32)?,
+                user_key: string(b"UE", 32)?,
+            };
+            let (key, password) = authenticate(
+                || params.authenticate_owner(password),
+                || params.authenticate_user(password),
+            )?;
+            if !aes256::check_permissions(
+                &key,
+                string(b"Perms", 16)?,
+                permissions,
+                encrypt_metadata,
+            ) {
+                return Err(invalid(b"Perms"));
+            }
+            (key, password)
+        } else {
+            // The key length in bits, which revision 2 fixes at 40
+            let key_len = match (revision, int(b"Length")?) {
+                (2, _) => 40,
+                (_, None) if version == 4 => 128,
+                (_, None) => 40,
+                (_, Some(len)) if (40..=128).contains(&len) && len % 8 == 0 => len,
+                _ => return Err(invalid(b"Length")),
+            };
+            let params = KeyParams {
+                revision,
+                key_len: key_len as usize / 8,
+                owner: string(b"O", 32)?,
+                user: string(b"U", 32)?,
+                permissions,
+                id,
+                encrypt_metadata,
+            };
+            authenticate(
+                || params.authenticate_owner(password),
+                || params.authenticate_user(password),
+            )?
+        };
+
+        // Before crypt filters, everything was encrypted with RC4
+        let mut crypt_filters = HashMap::new();
+        let (string_method, stream_method, embedded_file_method) = if version >= 4 {
+            if let Some(filters) = encrypt.get(b"CF") {
+                let filters = filters.as_dictionary().ok_or_else(|| invalid(b"CF"))?;
+                for (name, filter) in filters.iter() {
+                    let filter = filter.as_dictionary().ok_or_else(|| invalid(b"CF"))?;
+                    crypt_filters.insert(name.as_bytes().to_vec(), crypt_method(filter)?);
+                }
+            }
+            let method = |key: &[u8]| match encrypt.get(key) {
+                None => Ok(None),
+                Some(Object::Name(name)) => lookup_filter(&crypt_filters, name).map(Some),
+                Some(_) => Err(invalid(key)),
+            };
+            let stream_method = method(b"StmF")?.unwrap_or(CryptMethod::Identity);
+            (
+                method(b"StrF")?.unwrap_or(CryptMethod::Identity),
+                stream_method,
+                method(b"EFF")?.unwrap_or(stream_method),
+            )
+        } else {
+            (CryptMethod::Rc4, CryptMethod::Rc4, CryptMethod::Rc4)
+        };
+
+        Ok(Self {
+            revision,
+            permissions: Permissions::from_bits(permissions, revision),
+            password,
+            encrypt_metadata,
+            string_method,
+            stream_method,
+            embedded_file_method,
+            crypt_filters,
+            key,
+        })
+    }
+
+    /// Sets up encryption for a new document, returning the handler to encrypt its objects with
+    /// and the encryption dictionary. `id` is the first element of the trailer's `/ID`, which
+    /// must be written as given. The handler is authenticated with the owner password.
+    pub fn create(encryption: &Encryption, id: &[u8]) -> (Self, Dictionary) {
+        let user_password = &encryption.user_password[..];
+        let owner_password = match &encryption.owner_password[..] {
+            [] => user_password,
+            password => password,
+        };
+        let permissions = encryption.permissions.bits();
+        let encrypt_metadata = encryption.encrypt_metadata;
+        // RC4 only needs crypt filters to leave metadata unencrypted
+        let (version, revision, method) = match encryption.algorithm {
+            Algorithm::Rc4 if encrypt_metadata => (2, 3, CryptMethod::Rc4),
+            Algorithm::Rc4 => (4, 4, CryptMethod::Rc4),
+            Algorithm::Aes128 => (4, 4, CryptMethod::Aes128),
+            Algorithm::Aes256 => (5, 6, CryptMethod::Aes256),
+        };
+
+        let mut dict = Dictionary::new();
+        let mut insert = |key: &[u8], val: Object| dict.insert(Name::from(key), val);
+        let string = |val: Vec<u8>| Object::String(PdfString::Hex(val));
+        let int = |val: i64| Object::Number(Number::Integer(val));
+        insert(b"Filter", Object::Name(Name::from(b"Standard")));
+        insert(b"V", int(version));
+        insert(b"R", int(revision));
+        insert(b"P", int(permissions as i64));
+        let key = if revision == 6 {
+            let key = random_bytes(32);
+            let [user, user_key, owner, owner_key] =
+                aes256::KeyParams::create(&key, user_password, owner_password);
+            insert(b"U", string(user));
+            insert(b"UE", string(user_key));
+            insert(b"O", string(owner));
+            insert(b"OE", string(owner_key));
+            let perms = aes256::create_permissions(&key, permissions, encrypt_metadata);
+            insert(b"Perms", string(perms));
+            key
+        } else {
+            // Algorithm 3: `/O` is the padded user password, encrypted with the owner password
+            let key_len = 16;
+            let owner_key = owner_password_key(revision, key_len, owner_password);
+            let owner = rc4_rounds(&owner_key, &pad_password(user_password), 0..=19);
+            let params = KeyParams {
+                revision,
+                key_len,
+                owner: &owner,
+                user: &[],
+                permissions,
+                id,
+                encrypt_metadata,
+            };
+            let key = params.file_key(user_password);
+            // The rest of `/U` is arbitrary padding
+            let mut user = params.user_hash(&key);
+            user.resize(32, 0);
+            insert(b"Length", int(key_len as i64 * 8));
+            insert(b"O", string(owner));
+            insert(b"U", string(user));
+            key
+        };
+
+        let mut crypt_filters = HashMap::new();
+        if version >= 4 {
+            let cfm: &[u8] = match method {
+                CryptMethod::Rc4 => b"V2",
+                CryptMethod::Aes128 => b"AESV2",
+                _ => b"AESV3",
+            };
+            let filter = Dictionary::from_iter([
+                (Name::from(b"CFM"), Object::Name(Name::from(cfm))),
+                (
+                    Name::from(b"AuthEvent"),
+                    Object::Name(Name::from(b"DocOpen")),
+                ),
+                (Name::from(b"Length"), int(key.len() as i64)),
+            ]);
+            let filters =
+                Dictionary::from_iter([(Name::from(b"StdCF"), Object::Dictionary(filter))]);
+            insert(b"CF", Object::Dictionary(filters));
+            insert(b"StmF", Object::Name(Name::from(b"StdCF")));
+            insert(b"StrF", Object::Name(Name::from(b"StdCF")));
+            if !encrypt_metadata {
+                insert(b"EncryptMetadata", Object::Boolean(false));
+            }
+            crypt_filters.insert(b"StdCF".to_vec(), method);
+        }
+
+        let security = Self {
+            revision,
+            permissions: encryption.permissions,
+            password: Password::Owner,
+            encrypt_metadata,
+            string_method: method,
+            stream_method: method,
+            embedded_file_method: method,
+            crypt_filters,
+            key,
+        };
+        (security, dict)
+    }
+
+    /// What may be done with the document as it was opened: everything with the owner password,
+    /// and only what `/P` allows with the user password
+    pub fn effective_permissions(&self) -> Permissions {
+        match self.password {
+            Password::Owner => Permissions::all(),
+            Password::User => self.permissions,
+        }
+    }
+
+    /// Decrypts every string and stream in `obj`, the indirect object `reference`
+    pub fn decrypt_object(&self, reference: Reference, obj: Object) -> Result<Object, CryptError> {
+        self.map_object(obj, &|method, data| self.decrypt(method, reference, data))
+    }
+
+    /// Encrypts every string and stream in `obj`, to be written as the indirect object
+    /// `reference`. Streams that are never encrypted, such as cross-reference streams, are left
+    /// alone.
+    pub fn encrypt_object(&self, reference: Reference, obj: Object) -> Result<Object, CryptError> {
+        self.map_object(obj, &|method, data| {
+            Ok(self.encrypt(method, reference, data))
+        })
+    }
+
+    /// Replaces the data of every string and stream in `obj` with the result of `f`, given how
+    /// the string or stream is encrypted
+    fn map_object(
+        &self,
+        obj: Object,
+        f: &impl Fn(CryptMethod, &[u8]) -> Result<Vec<u8>, CryptError>,
+    ) -> Result<Object, CryptError> {
+        Ok(match obj {
+            Object::String(PdfString::Literal(s)) => {
+                Object::String(PdfString::Literal(f(self.string_method, &s)?))
+            }
+            Object::String(PdfString::Hex(s)) => {
+                Object::String(PdfString::Hex(f(self.string_method, &s)?))
+            }
+            Object::Array(objects) => Object::Array(
+                objects
+                    .into_iter()
+                    .map(|obj| self.map_object(obj, f))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Object::Dictionary(dict) => Object::Dictionary(self.map_dictionary(dict, f)?),
+            Object::Stream(stream) => {
+                let data = f(self.stream_method_for(&stream.dict)?, &stream.data)?;
+                Object::Stream(Stream {
+                    dict: self.map_dictionary(stream.dict, f)?,
+                    data,
+                })
+            }
+            obj => obj,
+        })
+    }
+
+    fn map_dictionary(
+        &self,
+        dict: Dictionary,
+        f: &impl Fn(CryptMethod, &[u8]) -> Result<Vec<u8>, CryptError>,
+    ) -> Result<Dictionary, CryptError> {
+        // The signature in a signature dictionary is left unencrypted, so that it can be checked
+        // against the file's bytes
+        let is_signature = matches!(
+            dict.get(b"Type")
+                .and_then(Object::as_name)
+                .map(Name::as_bytes),
+            Some(b"Sig" | b"DocTimeStamp")
+        );
+        dict.into_iter()
+            .map(|(key, val)| {
+                if is_signature && key.as_bytes() == b"Contents" {
+                    return Ok((key, val));
+                }
+                Ok((key, self.map_object(val, f)?))
+            })
+            .collect()
+    }
+
+    /// Decrypts a string in the indirect object `reference`. This is the string's bytes after
+    /// escape sequences or hex digits have been decoded.
+    pub fn decrypt_string(&self, reference: Reference, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+        self.decrypt(self.string_method, reference, data)
+    }
+
+    /// Decrypts the data of `stream`, the indirect object `reference`
+    pub fn decrypt_stream(
+        &self,
+        reference: Reference,
+        stream: &Stream,
+    ) -> Result<Vec<u8>, CryptError> {
+        let method = self.stream_method_for(&stream.dict)?;
+        self.decrypt(method, reference, &stream.data)
+    }
+
+    /// How the stream with dictionary `dict` is encrypted
+    fn stream_method_for(&self, dict: &Dictionary) -> Result<CryptMethod, CryptError> {
+        let ty = dict.get(b"Type").and_then(Object::as_name);
+        match ty.map(Name::as_bytes) {
+            // Cross-reference streams have to be read before we can decrypt anything
+            Some(b"XRef") => return Ok(CryptMethod::Identity),
+            Some(b"Metadata") if !self.encrypt_metadata => return Ok(CryptMethod::Identity),
+            _ => {}
+        }
+        // A `Crypt` filter at the start of the chain picks a crypt filter by name. See PDF Spec
+        // section 7.4.10
+        if let Ok(filters) = stream_filters(dict)
+            && let Some((name, params)) = filters.first()
+            && name.as_bytes() == b"Crypt"
+        {
+            return match params.and_then(|params| params.get(b"Name")) {
+                None => Ok(CryptMethod::Identity),
+                Some(Object::Name(name)) => lookup_filter(&self.crypt_filters, name),
+                Some(_) => Err(CryptError::InvalidEntry(b"Name".to_vec())),
+            };
+        }
+        match ty.map(Name::as_bytes) {
+            Some(b"EmbeddedFile") => Ok(self.embedded_file_method),
+            _ => Ok(self.stream_method),
+        }
+    }
+
+    fn decrypt(
+        &self,
+        method: CryptMethod,
+        reference: Reference,
+        data: &[u8],
+    ) -> Result<Vec<u8>, CryptError> {
+        match method {
+            CryptMethod::Identity => Ok(data.to_vec()),
+            CryptMethod::Rc4 => Ok(rc4(&self.object_key(reference, method), data)),
+            CryptMethod::Aes128 => aes_decrypt(&self.object_key(reference, method), data)
+                .ok_or(CryptError::InvalidData(reference)),
+            // AES-256 uses the file key for everything
+            CryptMethod::Aes256 => {
+                aes_decrypt(&self.key, data).ok_or(CryptError::InvalidData(reference))
+            }
+        }
+    }
+
+    fn encrypt(&self, method: CryptMethod, reference: Reference, data: &[u8]) -> Vec<u8> {
+        match method {
+            CryptMethod::Identity => data.to_vec(),
+            CryptMethod::Rc4 => rc4(&self.object_key(reference, method), data),
+            CryptMethod::Aes128 => aes_encrypt(&self.object_key(reference, method), data),
+            CryptMethod::Aes256 => aes_encrypt(&self.key, data),
+        }
+    }
+
+    /// The key for the data of one object, which mixes in its object and generation numbers.
+    /// See PDF Spec section 7.6.3.3, algorithm 1
+    fn object_key(&self, reference: Reference, method: CryptMethod) -> Vec<u8> {
+        let mut hash = Md5::new();
+        hash.update(&self.key);
+        hash.update(&reference.number.to_le_bytes()[..3]);
+        hash.update(reference.generation.to_le_bytes());
+        if method == CryptMethod::Aes128 {
+            hash.update(b"sAlT");
+        }
+        let key_len = (self.key.len() + 5).min(16);
+        hash.finalize()[..key_len].to_vec()
+    }
+}
+
+/// The parts of the encryption dictionary the file key is derived from
+struct KeyParams<'a> {
+    revision: i64,
+    /// In bytes
+    key_len: usize,
+    /// `/O`, derived from the owner and user passwords
+    owner: &'a [u8],
+    /// `/U`, derived from the user password
+    user: &'a [u8],
+    permissions: i32,
+    id: &'a [u8],
+    encrypt_metadata: bool,
+}
+
+impl KeyParams<'_> {
+    /// Computes the file key from the user password. See PDF Spec section 7.6.4.3.2, algorithm 2
+    fn file_key(&self, password: &[u8]) -> Vec<u8> {
+        let mut hash = Md5::new();
+        hash.update(pad_password(password));
+        hash.update(self.owner);
+        hash.update(self.permissions.to_le_bytes());
+        hash.update(self.id);
+        if self.revision >= 4 && !self.encrypt_metadata {
+            hash.update([0xff; 4]);
+        }
+        let mut key = hash.finalize().to_vec();
+        if self.revision >= 3 {
+            for _ in 0..50 {
+                key = Md5::digest(&key[..self.key_len]).to_vec();
+            }
+        }
+        key.truncate(self.key_len);
+        key
+    }
+
+    /// Computes `/U` from the file key. From revision 3, only the first 16 bytes are significant,
+    /// and only they are returned. See PDF Spec section 7.6.4.4, algorithms 4 and 5
+    fn user_hash(&self, key: &[u8]) -> Vec<u8> {
+        if self.revision == 2 {
+            return rc4(key, &PASSWORD_PADDING);
+        }
+        let mut hash = Md5::new();
+        hash.update(PASSWORD_PADDING);
+        hash.update(self.id);
+        rc4_rounds(key, &hash.finalize(), 0..=19)
+    }
+
+    /// Returns the file key if `password` is the user password, which is checked by computing
+    /// `/U` from it. See PDF Spec section 7.6.4.4, algorithm 6
+    fn authenticate_user(&self, password: &[u8]) -> Option<Vec<u8>> {
+        let key = self.file_key(password);
+        let user = self.user_hash(&key);
+        (user == self.user[..user.len()]).then_some(key)
+    }
+
+    /// Returns the file key if `password` is the owner password, which is checked by using it to
+    /// recover the user password from `/O`. See PDF Spec section 7.6.4.4, algorithm 7
+    fn authenticate_owner(&self, password: &[u8]) -> Option<Vec<u8>> {
+        let key = owner_password_key(self.revision, self.key_len, password);
+        let user_password = if self.revision == 2 {
+            rc4(&key, self.owner)
+        } else {
+            rc4_rounds(&key, self.owner, (0..=19).rev())
+        };
+        self.authenticate_user(&user_password)
+    }
+}
+
+/// The RC4 key `/O` is encrypted with, derived from the owner password. See PDF Spec section
+/// 7.6.4.3.2, algorithm 3
+fn owner_password_key(revision: i64, key_len: usize, password: &[u8]) -> Vec<u8> {
+    let mut key = Md5::digest(pad_password(password)).to_vec();
+    if revision >= 3 {
+        for _ in 0..50 {
+            key = Md5::digest(&key[..key_len]).to_vec();
+        }
+    }
+    key.truncate(key_len);
+    key
+}
+
+/// Tries a password as the owner password, then as the user password, returning the file key and
+/// which password it was
+fn authenticate(
+    owner: impl FnOnce() -> Option<Vec<u8>>,
+    user: impl FnOnce() -> Option<Vec<u8>>,
+) -> Result<(Vec<u8>, Password), CryptError> {
+    if let Some(key) = owner() {
+        return Ok((key, Password::Owner));
+    }
+    user()
+        .map(|key| (key, Password::User))
+        .ok_or(CryptError::IncorrectPassword)
+}
+
+/// Pads or truncates a password to 32 bytes. See PDF Spec section 7.6.4.3.2
+fn pad_password(password: &[u8]) -> [u8; 32] {
+    let mut padded = PASSWORD_PADDING;
+    let len = password.len().min(32);
+    padded[..len].copy_from_slice(&password[..len]);
+    padded[len..].copy_from_slice(&PASSWORD_PADDING[..32 - len]);
+    padded
+}
+
+/// Encrypts `data` with RC4 once for each of `rounds`, using `key` with each byte XORed with the
+/// round number
+fn rc4_rounds(key: &[u8], data: &[u8], rounds: impl Iterator<Item = u8>) -> Vec<u8> {
+    rounds.fold(data.to_vec(), |data, round| {
+        let key: Vec<u8> = key.iter().map(|byte| byte ^ round).collect();
+        rc4(&key, &data)
+    })
+}
+
+/// Reads a crypt filter dictionary's method. See PDF Spec section 7.6.6, table 25
+fn crypt_method(filter: &Dictionary) -> Result<CryptMethod, CryptError> {
+    match filter.get(b"CFM") {
+        None => Ok(CryptMethod::Identity),
+        Some(Object::Name(name)) => match name.as_bytes() {
+            b"None" => Ok(CryptMethod::Identity),
+            b"V2" => Ok(CryptMethod::Rc4),
+            b"AESV2" => Ok(CryptMethod::Aes128),
+            b"AESV3" => Ok(CryptMethod::Aes256),
+            name => Err(CryptError::UnsupportedMethod(name.to_vec())),
+        },
+        Some(_) => Err(CryptError::InvalidEntry(b"CFM".to_vec())),
+    }
+}
+
+/// Finds the crypt filter called `name`. `Identity` is always defined.
+fn lookup_filter(
+    filters: &HashMap<Vec<u8>, CryptMethod>,
+    name: &Name,
+) -> Result<CryptMethod, CryptError> {
+    match name.as_bytes() {
+        b"Identity" => Ok(CryptMethod::Identity),
+        name => filters
+            .get(name)
+            .copied()
+            .ok_or_else(|| CryptError::UnsupportedMethod(name.to_vec())),
+    }
+}
+
+/// Decrypts AES-CBC data, which starts with a 16 byte initialization vector and is padded as in
+/// PKCS#7. The key length picks AES-128 or AES-256.
+fn aes_decrypt(key: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    // Some producers leave empty strings empty, rather than encrypting them
+    if data.is_empty() {
+        return Some(Vec::new());
+    }
+    if data.len() < 16 {
+        return None;
+    }
+    let (iv, data) = data.split_at(16);
+    let mut out = match key.len() {
+        16 => cbc::Decryptor::<aes::Aes128>::new_from_slices(key, iv)
+            .ok()?
+            .decrypt_padded_vec_mut::<NoPadding>(data),
+        _ => cbc::Decryptor::<aes::Aes256>::new_from_slices(key, iv)
+            .ok()?
+            .decrypt_padded_vec_mut::<NoPadding>(data),
+    }
+    .ok()?;
+    // Tolerate bad padding by leaving it in place
+    if let Some(&pad) = out.last()
+        && (1..=16).contains(&pad)
+        && out.len() >= pad as usize
+        && out[out.len() - pad as usize..]
+            .iter()
+            .all(|byte| *byte == pad)
+    {
+        out.truncate(out.len() - pad as usize);
+    }
+    Some(out)
+}
+
+/// Encrypts data with AES-CBC, starting it with a random initialization vector and padding it as
+/// in PKCS#7. The key length picks AES-128 or AES-256.
+fn aes_encrypt(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let iv = random_bytes(16);
+    let encrypted = match key.len() {
+        16 => cbc::Encryptor::<aes::Aes128>::new_from_slices(key, &iv)
+            .expect("the key and IV are 16 bytes")
+            .encrypt_padded_vec_mut::<Pkcs7>(data),
+        _ => cbc::Encryptor::<aes::Aes256>::new_from_slices(key, &iv)
+            .expect("the key is 32 bytes and the IV 16")
+            .encrypt_padded_vec_mut::<Pkcs7>(data),
+    };
+    [iv, encrypted].concat()
+}
+
+/// Generates keys, salts and initialization vectors
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    getrandom::fill(&mut bytes).expect("the system random number generator is unavailable");
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::object::Number;
+
+    // Test vectors computed independently from PDF Spec section 7.6, for the user password
+    // `user` and owner password `owner`
+    static ID: &str = "0123456789abcdeffedcba9876543210";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn reference(number: u32) -> Reference {
+        Reference {
+            number,
+            generation: 0,
+        }
+    }
+
+    fn encrypt_dict(entries: &[(&[u8], Object)]) -> Dictionary {
+        entries
+            .iter()
+            .map(|(k, v)| (Name::from(*k), v.clone()))
+            .chain([
+                (Name::from(b"Filter"), Object::Name(Name::from(b"Standard"))),
+                (Name::from(b"P"), Object::Number(Number::Integer(-3904))),
+            ])
+            .collect()
+    }
+
+    fn int(val: i64) -> Object {
+        Object::Number(Number::Integer(val))
+    }
+
+    fn string(s: &str) -> Object {
+        Object::String(PdfString::Hex(hex(s)))
+    }
+
+    fn aes_dict(encrypt_metadata: bool, user: &str) -> Dictionary {
+        let std_cf = Dictionary::from_iter([
+            (Name::from(b"CFM"), Object::Name(Name::from(b"AESV2"))),
+            (Name::from(b"Length"), int(16)),
+        ]);
+        encrypt_dict(&[
+            (b"V", int(4)),
+            (b"R", int(4)),
+            (b"Length", int(128)),
+            (
+                b"CF",
+                Object::Dictionary(Dictionary::from_iter([(
+                    Name::from(b"StdCF"),
+                    Object::Dictionary(std_cf),
+                )])),
+            ),
+            (b"StmF", Object::Name(Name::from(b"StdCF"))),
+            (b"StrF", Object::Name(Name::from(b"StdCF"))),
+            (b"EncryptMetadata", Object::Boolean(encrypt_metadata)),
+            (
+                b"O",
+                string("0ba3835f88f90388e74e54584125ce142be0de24c6b0d37746e075b891756671"),
+            ),
+            (b"U", string(user)),
+        ])
+    }
+
+    #[test]
+    fn rc4_40() {
+        let dict = encrypt_dict(&[
+            (b"V", int(1)),
+            (b"R", int(2)),
+            (
+                b"O",
+                string("94e8094419662a774442fb072e3d9f19e9d130ec09a4d0061e78fe920f7ab62f"),
+            ),
+            (
+                b"U",
+                string("00960a327fd80c93dd8fdefe3ab7fff0e2e8b39542a5cecc70f05f28755084db"),
+            ),
+        ]);
+        for (password, opened_with) in [(&b"user"[..], Password::User), (b"owner", Password::Owner)]
+        {
+            let security = StandardSecurity::new(&dict, &hex(ID), password).unwrap();
+            assert_eq!(security.key, hex("5d4446bedf"));
+            assert_eq!(security.permissions.bits(), -3904);
+            assert!(!security.permissions.print);
+            assert_eq!(security.password, opened_with);
+            assert_eq!(
+                security.effective_permissions().print,
+                opened_with == Password::Owner
+            );
+            assert_eq!(
+                security.decrypt_string(reference(5), &hex("2d265daadc979b720c46e7118f")),
+                Ok(b"Hello, world!".to_vec())
+            );
+        }
+        assert_eq!(
+            StandardSecurity::new(&dict, &hex(ID), b"wrong"),
+            Err(CryptError::IncorrectPassword)
+        );
+    }
+
+    #[test]
+    fn rc4_128() {
+        let dict = encrypt_dict(&[
+            (b"V", int(2)),
+            (b"R", int(3)),
+            (b"Length", int(128)),
+            (
+                b"O",
+                string("0ba3835f88f90388e74e54584125ce142be0de24c6b0d37746e075b891756671"),
+            ),
+            (
+                b"U",
+                string("c9bbe2a3956ec521581a87749e0bc91a00000000000000000000000000000000"),
+            ),
+        ]);
+        for password in [&b"user"[..], b"owner"] {
+            let security = StandardSecurity::new(&dict, &hex(ID), password).unwrap();
+            let stream = Stream {
+                dict: Dictionary::new(),
+                data: hex("b64b493b93f5f7ce65a9a6fc230bd4924991989dddc1acfc63dfed"),
+            };
+            assert_eq!(
+                security.decrypt_stream(reference(6), &stream),
+                Ok(b"BT /F1 12 Tf (secret) Tj ET".to_vec())
+            );
+            assert_eq!(
+                security.decrypt_string(reference(5), &hex("aabfe94aa1bfbcae62c2a35c53")),
+                Ok(b"Hello, world!".to_vec())
+            );
+        }
+        assert_eq!(
+            StandardSecurity::new(&dict, &hex(ID), b""),
+            Err(CryptError::IncorrectPassword)
+        );
+    }
+
+    #[test]
+    fn aes_128() {
+        let dict = aes_dict(
+            true,
+            "c9bbe2a3956ec521581a87749e0bc91a00000000000000000000000000000000",
+        );
+        let security = StandardSecurity::new(&dict, &hex(ID), b"owner").unwrap();
+        assert_eq!(security.string_method, CryptMethod::Aes128);
+        assert_eq!(security.stream_method, CryptMethod::Aes128);
+
+        let obj = Object::Array(vec![
+            string("000102030405060708090a0b0c0d0e0f4f6c9de819e2d00d4351c7fdebd106a0"),
+            Object::String(PdfString::Literal(vec![])),
+        ]);
+        assert_eq!(
+            security.decrypt_object(reference(5), obj),
+            Ok(Object::Array(vec![
+                Object::String(PdfString::Hex(b"Hello, world!".to_vec())),
+                Object::String(PdfString::Literal(vec![])),
+            ]))
+        );
+        let stream = Object::Stream(Stream {
+            dict: Dictionary::new(),
+            data: hex(concat!(
+                "000102030405060708090a0b0c0d0e0fb840b4128f692e8734c8f7ca3f9bb290",
+                "5c1b84b474c146bd6744d9338736f32c"
+            )),
+        });
+        assert_eq!(
+            security.decrypt_object(reference(6), stream),
+            Ok(Object::Stream(Stream {
+                dict: Dictionary::new(),
+                data: b"BT /F1 12 Tf (secret) Tj ET".to_vec(),
+            }))
+        );
+
+        // Not a whole number of blocks
+        assert_eq!(
+            security.decrypt_string(reference(5), &[0; 20]),
+            Err(CryptError::InvalidData(reference(5)))
+        );
+    }
+
+    #[test]
+    fn unencrypted_metadata() {
+        let dict = aes_dict(
+            false,
+            "026ff9eb254a9de965203e046649429200000000000000000000000000000000",
+        );
+        let security = StandardSecurity::new(&dict, &hex(ID), b"user").unwrap();
+        assert_eq!(security.key, hex("8daac4091c894a6df2237667d4935830"));
+        assert!(!security.encrypt_metadata);
+        assert_eq!(
+            security.decrypt_string(
+                reference(5),
+                &hex("000102030405060708090a0b0c0d0e0fcd26a05f93e2eb8ef78ac8a6eb292015")
+            ),
+            Ok(b"Hello, world!".to_vec())
+        );
+
+        // Metadata streams are left alone, and so are cross-reference streams
+        for ty in [&b"Metadata"[..], b"XRef"] {
+            let stream = Stream {
+                dict: Dictionary::from_iter([(Name::from(b"Type"), Object::Name(Name::from(ty)))]),
+                data: b"<x:xmpmeta/>".to_vec(),
+            };
+            assert_eq!(
+                security.decrypt_stream(reference(7), &stream),
+                Ok(b"<x:xmpmeta/>".to_vec())
+            );
+        }
+
+        // As are streams with the identity crypt filter
+        let stream = Stream {
+            dict: Dictionary::from_iter([(
+                Name::from(b"Filter"),
+                Object::Name(Name::from(b"Crypt")),
+            )]),
+            data: b"plain".to_vec(),
+        };
+        assert_eq!(
+            security.decrypt_stream(reference(7), &stream),
+            Ok(b"plain".to_vec())
+        );
+    }
+
+    fn aes_256_dict(revision: i64, values: [&str; 5]) -> Dictionary {
+        let std_cf = Dictionary::from_iter([
+            (Name::from(b"CFM"), Object::Name(Name::from(b"AESV3"))),
+            (Name::from(b"Length"), int(32)),
+        ]);
+        let [user, user_key, owner, owner_key, perms] = values;
+        encrypt_dict(&[
+            (b"V", int(5)),
+            (b"R", int(revision)),
+            (b"Length", int(256)),
+            (
+                b"CF",
+                Object::Dictionary(Dictionary::from_iter([(
+                    Name::from(b"StdCF"),
+                    Object::Dictionary(std_cf),
+                )])),
+            ),
+            (b"StmF", Object::Name(Name::from(b"StdCF"))),
+            (b"StrF", Object::Name(Name::from(b"StdCF"))),
+            (b"U", string(user)),
+            (b"UE", string(user_key)),
+            (b"O", string(owner)),
+            (b"OE", string(owner_key)),
+            (b"Perms", string(perms)),
+        ])
+    }
+
+    #[test]
+    fn aes_256() {
+        // User password `fi`, owner password `ownér`
+        let revision_5 = aes_256_dict(
+            5,
+            [
+                concat!(
+                    "cc8b7f0bb33c88f602134efd4861d432976d48f416c508d39cd23b3d288c63e6",
+                    "01020304050607081112131415161718"
+                ),
+                "b82901482c7f843bba79dcc67cf05b8186efaf4ca78929bc946ff753239726ee",
+                concat!(
+                    "5bef526f102e01487419a8e22e2985b118449c6d0ff34a5d08129b86f8c26364",
+                    "21222324252627283132333435363738"
+                ),
+                "f1dff043cde6a46a64cb4a9919ff5df2ffd7ec702f2ed56891447400f25932c9",
+                "dd1bf6c45aa0fb3bde0bfe62609ed382",
+            ],
+        );
+        let revision_6 = aes_256_dict(
+            6,
+            [
+                concat!(
+                    "140432f63b349f3791d699936778e2bbe27e039d9454262202c14797936d9692",
+                    "01020304050607081112131415161718"
+                ),
+                "32b961bd2d7f30242d78eed009ccb17d317124391233604d474d4b00b21cd0eb",
+                concat!(
+                    "0c6362cb6df05fbd99aefdcd9d2297a59ad71438811c0b71c1a1115c4604adaf",
+                    "21222324252627283132333435363738"
+                ),
+                "80d2e5490187012baa65d2a3b5fc1243b78fbc800ca9b2a8c9b8e15612664997",
+                "dd1bf6c45aa0fb3bde0bfe62609ed382",
+            ],
+        );
+        let file_key: Vec<u8> = (0x40..0x60).collect();
+        for dict in [&revision_5, &revision_6] {
+            // Passwords are normalized with SASLprep, so the ligature `ﬁ` is the same as `fi`,
+            // and a decomposed `é` is the same as a precomposed one
+            for password in ["fi", "\u{fb01}", "own\u{e9}r", "owne\u{301}r"] {
+                let security = StandardSecurity::new(dict, &[], password.as_bytes()).unwrap();
+                assert_eq!(security.key, file_key);
+                let opened_with = if password.starts_with("own") {
+                    Password::Owner
+                } else {
+                    Password::User
+                };
+                assert_eq!(security.password, opened_with);
+                assert_eq!(security.string_method, CryptMethod::Aes256);
+                // The key doesn't depend on the object
+                for number in [5, 6] {
+                    assert_eq!(
+                        security.decrypt_string(
+                            reference(number),
+                            &hex(concat!(
+                                "000102030405060708090a0b0c0d0e0f",
+                                "62b8a6dac985d3a1098044073e30532a"
+                            ))
+                        ),
+                        Ok(b"Hello, world!".to_vec())
+                    );
+                }
+            }
+            assert_eq!(
+                StandardSecurity::new(dict, &[], b"owner"),
+                Err(CryptError::IncorrectPassword)
+            );
+
+            // `/Perms` must agree with `/P`
+            let mut tampered = dict.clone();
+            tampered.insert(Name::from(b"P"), int(-4));
+            assert_eq!(
+                StandardSecurity::new(&tampered, &[], b"fi"),
+                Err(CryptError::InvalidEntry(b"Perms".to_vec()))
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported() {
+        let dict = encrypt_dict(&[(b"V", int(3)), (b"R", int(3))]);
+        assert_eq!(
+            StandardSecurity::new(&dict, &[], b""),
+            Err(CryptError::UnsupportedRevision {
+                version: 3,
+                revision: 3
+            })
+        );
+        let mut dict = encrypt_dict(&[]);
+        dict.insert(
+            Name::from(b"Filter"),
+            Object::Name(Name::from(b"Adobe.PubSec")),
+        );
+        assert_eq!(
+            StandardSecurity::new(&dict, &[], b""),
+            Err(CryptError::UnsupportedHandler(b"Adobe.PubSec".to_vec()))
+        );
+    }
+}
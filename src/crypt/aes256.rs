@@ -0,0 +1,173 @@
+//! Password checking for AES-256 encryption, revisions 5 and 6 of the standard security handler.
+//! Unlike earlier revisions, the file key is random, and is stored in the encryption dictionary
+//! encrypted with a key derived from each password. See ISO 32000-2 section 7.6.4.3.3
+//!
+//! Revision 5 was never part of a standard, so documents are only created with revision 6.
+
+use aes::cipher::block_padding::NoPadding;
+use aes::cipher::{
+    BlockDecrypt, BlockDecryptMut, BlockEncrypt, BlockEncryptMut, KeyInit, KeyIvInit,
+};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use super::random_bytes;
+
+/// Passwords are truncated to this many bytes of UTF-8
+static MAX_PASSWORD_LEN: usize = 127;
+
+/// The parts of the encryption dictionary needed to check a password
+pub(super) struct KeyParams<'a> {
+    pub revision: i64,
+    /// `/O`: a hash of the owner password and `/U`, then the salts for checking the owner
+    /// password and for deriving the key to `/OE`
+    pub owner: &'a [u8],
+    /// `/U`: a hash of the user password, then the salts for checking it and for deriving the
+    /// key to `/UE`
+    pub user: &'a [u8],
+    /// `/OE`: the file key, encrypted with a key derived from the owner password
+    pub owner_key: &'a [u8],
+    /// `/UE`: the file key, encrypted with a key derived from the user password
+    pub user_key: &'a [u8],
+}
+
+impl KeyParams<'_> {
+    /// Creates `/U`, `/UE`, `/O` and `/OE`, in that order, for the file key `key`, with revision
+    /// 6. See ISO 32000-2 section 7.6.4.4, algorithms 8 and 9
+    pub fn create(key: &[u8], user_password: &[u8], owner_password: &[u8]) -> [Vec<u8>; 4] {
+        let (user, user_key) = create_hash(&prepare_password(user_password), &[], key);
+        let (owner, owner_key) = create_hash(&prepare_password(owner_password), &user, key);
+        [user, user_key, owner, owner_key]
+    }
+
+    /// Returns the file key if `password` is the owner password. See ISO 32000-2 section
+    /// 7.6.4.4.10, algorithm 12
+    pub fn authenticate_owner(&self, password: &[u8]) -> Option<Vec<u8>> {
+        let password = prepare_password(password);
+        let user = &self.user[..48];
+        let (validation_salt, key_salt) = (&self.owner[32..40], &self.owner[40..48]);
+        if hash(self.revision, &password, validation_salt, user) != self.owner[..32] {
+            return None;
+        }
+        let key = hash(self.revision, &password, key_salt, user);
+        unwrap_key(&key, self.owner_key)
+    }
+
+    /// Returns the file key if `password` is the user password. See ISO 32000-2 section
+    /// 7.6.4.4.9, algorithm 11
+    pub fn authenticate_user(&self, password: &[u8]) -> Option<Vec<u8>> {
+        let password = prepare_password(password);
+        let (validation_salt, key_salt) = (&self.user[32..40], &self.user[40..48]);
+        if hash(self.revision, &password, validation_salt, &[]) != self.user[..32] {
+            return None;
+        }
+        let key = hash(self.revision, &password, key_salt, &[]);
+        unwrap_key(&key, self.user_key)
+    }
+}
+
+/// Normalizes a password with SASLprep (RFC 4013), so that equivalent Unicode strings give the
+/// same bytes. Passwords that aren't valid UTF-8, or that SASLprep rejects, are used as they are.
+fn prepare_password(password: &[u8]) -> Vec<u8> {
+    let mut password = std::str::from_utf8(password)
+        .ok()
+        .and_then(|password| stringprep::saslprep(password).ok())
+        .map_or_else(
+            || password.to_vec(),
+            |password| password.as_bytes().to_vec(),
+        );
+    password.truncate(MAX_PASSWORD_LEN);
+    password
+}
+
+/// Hashes a password with a salt and, for the owner password, `/U`. Revision 5 uses a single
+/// SHA-256, and revision 6 a deliberately slow iterated hash. See ISO 32000-2 section
+/// 7.6.4.3.4, algorithm 2.B
+fn hash(revision: i64, password: &[u8], salt: &[u8], user: &[u8]) -> Vec<u8> {
+    let mut key = Sha256::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(user)
+        .finalize()
+        .to_vec();
+    if revision == 5 {
+        return key;
+    }
+
+    let mut round = 0;
+    loop {
+        let block: Vec<u8> = [password, &key, user].concat();
+        let data = block.repeat(64);
+        let encrypted = cbc::Encryptor::<aes::Aes128>::new_from_slices(&key[..16], &key[16..32])
+            .expect("the key and IV are 16 bytes")
+            .encrypt_padded_vec_mut::<NoPadding>(&data);
+        // The first 16 bytes as a big-endian number modulo 3, which is the same as the sum of
+        // the bytes modulo 3, since 256 % 3 == 1
+        key = match encrypted[..16].iter().map(|byte| *byte as u32).sum::<u32>() % 3 {
+            0 => Sha256::digest(&encrypted).to_vec(),
+            1 => Sha384::digest(&encrypted).to_vec(),
+            _ => Sha512::digest(&encrypted).to_vec(),
+        };
+        round += 1;
+        if round >= 64 && *encrypted.last().unwrap() as u32 <= round - 32 {
+            break;
+        }
+    }
+    key.truncate(32);
+    key
+}
+
+/// Hashes a password with new random salts, giving `/U` or `/O`, and encrypts the file key with
+/// a key derived from it, giving `/UE` or `/OE`
+fn create_hash(password: &[u8], user: &[u8], key: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let salts = random_bytes(16);
+    let (validation_salt, key_salt) = salts.split_at(8);
+    let hashed = [&hash(6, password, validation_salt, user)[..], &salts].concat();
+    let wrapped = cbc::Encryptor::<aes::Aes256>::new_from_slices(
+        &hash(6, password, key_salt, user),
+        &[0; 16],
+    )
+    .expect("the key is 32 bytes and the IV 16")
+    .encrypt_padded_vec_mut::<NoPadding>(key);
+    (hashed, wrapped)
+}
+
+/// Decrypts `/OE` or `/UE` to get the file key
+fn unwrap_key(key: &[u8], wrapped: &[u8]) -> Option<Vec<u8>> {
+    cbc::Decryptor::<aes::Aes256>::new_from_slices(key, &[0; 16])
+        .ok()?
+        .decrypt_padded_vec_mut::<NoPadding>(wrapped)
+        .ok()
+}
+
+/// Checks `/Perms`, a copy of the permissions and `/EncryptMetadata` encrypted with the file key,
+/// against the unencrypted values. If they don't match, they've been tampered with. See ISO
+/// 32000-2 section 7.6.4.4.12, algorithm 13
+pub(super) fn check_permissions(
+    key: &[u8],
+    perms: &[u8],
+    permissions: i32,
+    encrypt_metadata: bool,
+) -> bool {
+    let Ok(cipher) = aes::Aes256::new_from_slice(key) else {
+        return false;
+    };
+    let mut block = aes::Block::clone_from_slice(&perms[..16]);
+    cipher.decrypt_block(&mut block);
+    block[..4] == permissions.to_le_bytes()
+        && block[8] == if encrypt_metadata { b'T' } else { b'F' }
+        && &block[9..12] == b"adb"
+}
+
+/// Creates `/Perms` for the permission flags `permissions`. See ISO 32000-2 section 7.6.4.4,
+/// algorithm 10
+pub(super) fn create_permissions(key: &[u8], permissions: i32, encrypt_metadata: bool) -> Vec<u8> {
+    let cipher = aes::Aes256::new_from_slice(key).expect("the file key is 32 bytes");
+    let mut block = aes::Block::default();
+    block[..4].copy_from_slice(&permissions.to_le_bytes());
+    block[4..8].fill(0xff);
+    block[8] = if encrypt_metadata { b'T' } else { b'F' };
+    block[9..12].copy_from_slice(b"adb");
+    block[12..].copy_from_slice(&random_bytes(4));
+    cipher.encrypt_block(&mut block);
+    block.to_vec()
+}
@@ -0,0 +1,121 @@
+//! The user access permissions of an encrypted document. See PDF Spec section 7.6.4.2
+
+/// What a user who opened the document with the user password may do, from the `/P` flags.
+/// Anyone with the owner password may do everything.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Permissions {
+    /// Print the document, at low resolution unless `print_high_quality` is also set (bit 3)
+    pub print: bool,
+    /// Change the contents, other than by the operations covered by `annotate`, `fill_forms` and
+    /// `assemble` (bit 4)
+    pub modify: bool,
+    /// Copy or otherwise extract text and graphics (bit 5)
+    pub copy: bool,
+    /// Add or change annotations, and fill in form fields (bit 6)
+    pub annotate: bool,
+    /// Fill in existing form fields, even if `annotate` is unset (bit 9)
+    pub fill_forms: bool,
+    /// Extract text and graphics for accessibility (bit 10)
+    pub accessibility: bool,
+    /// Insert, rotate or delete pages, and create bookmarks or thumbnails, even if `modify` is
+    /// unset (bit 11)
+    pub assemble: bool,
+    /// Print at full quality, rather than a degraded version (bit 12)
+    pub print_high_quality: bool,
+}
+
+impl Permissions {
+    /// Every permission, as given by the owner password or an unencrypted document
+    pub fn all() -> Self {
+        Self::from_bits(-1, 3)
+    }
+
+    /// Reads the `/P` flags of a document encrypted with revision `revision` of the standard
+    /// security handler. Revision 2 predates bits 9 to 12, which take their values from the
+    /// older bits they were split from.
+    pub fn from_bits(bits: i32, revision: i64) -> Self {
+        let bit = |n: u32| bits & (1 << (n - 1)) != 0;
+        let (print, modify, copy, annotate) = (bit(3), bit(4), bit(5), bit(6));
+        if revision == 2 {
+            return Self {
+                print,
+                modify,
+                copy,
+                annotate,
+                fill_forms: annotate,
+                accessibility: copy,
+                assemble: modify,
+                print_high_quality: print,
+            };
+        }
+        Self {
+            print,
+            modify,
+            copy,
+            annotate,
+            fill_forms: bit(9),
+            accessibility: bit(10),
+            assemble: bit(11),
+            print_high_quality: bit(12),
+        }
+    }
+
+    /// The `/P` flags for these permissions. The reserved bits are set as the spec requires.
+    pub fn bits(&self) -> i32 {
+        let flags = [
+            (3, self.print),
+            (4, self.modify),
+            (5, self.copy),
+            (6, self.annotate),
+            (9, self.fill_forms),
+            (10, self.accessibility),
+            (11, self.assemble),
+            (12, self.print_high_quality),
+        ];
+        // Bits 1 and 2 must be 0, and the other unused bits 1
+        let reserved = !0b1111_0011_1111u32 as i32;
+        flags
+            .into_iter()
+            .filter(|(_, set)| *set)
+            .fold(reserved, |bits, (n, _)| bits | (1 << (n - 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bits() {
+        // Everything but assembling
+        let permissions = Permissions::from_bits(-1028, 3);
+        assert_eq!(
+            permissions,
+            Permissions {
+                assemble: false,
+                ..Permissions::all()
+            }
+        );
+        assert_eq!(permissions.bits(), -1028);
+
+        // Only printing, at low quality
+        let permissions = Permissions::from_bits(-3900, 4);
+        assert!(permissions.print);
+        assert!(!permissions.print_high_quality);
+        assert!(!permissions.copy && !permissions.accessibility);
+        assert_eq!(permissions.bits(), -3900);
+
+        assert_eq!(Permissions::from_bits(-3904, 3).bits(), -3904);
+        assert_eq!(Permissions::all().bits(), -4);
+    }
+
+    #[test]
+    fn revision_2() {
+        // Copying implies accessibility, and printing is at full quality
+        let permissions = Permissions::from_bits(-3884, 2);
+        assert!(permissions.print && permissions.print_high_quality);
+        assert!(permissions.copy && permissions.accessibility);
+        assert!(!permissions.modify && !permissions.assemble);
+        assert!(!permissions.annotate && !permissions.fill_forms);
+    }
+}
@@ -0,0 +1,604 @@
+//! A PDF document, which gives random access to the objects in a file. See PDF Spec section 7.5
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use crate::ast::object::{Dictionary, Object, Reference};
+use crate::crypt::{CryptError, Permissions, StandardSecurity};
+use crate::error::{Error, ParseMode, Result, Warning};
+use crate::lexer::find_subslice;
+use crate::page::Pages;
+use crate::parser_helper::ParseContext;
+use crate::xref::{
+    ObjectStream, RepairReport, XrefChain, XrefEntry, XrefError, parse_indirect_object, read_xref,
+};
+
+/// The PDF version from a file's `%PDF-x.y` header. See PDF Spec section 7.5.2
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+// How many bytes at the start of the file to look for the header in. Some producers write junk
+// before it.
+static HEADER_SEARCH_LEN: usize = 1024;
+
+// How many references we'll follow to resolve a single object before assuming there's a loop
+static MAX_REFERENCE_DEPTH: usize = 32;
+
+pub struct Document {
+    data: Vec<u8>,
+    version: Version,
+    xref: XrefChain,
+    repairs: RepairReport,
+    mode: ParseMode,
+    /// Deviations from the spec accepted while reading objects in lenient mode
+    warnings: RefCell<Vec<Warning>>,
+    /// Object streams we've already decoded, by object number
+    object_streams: RefCell<HashMap<u32, Rc<ObjectStream>>>,
+    /// How objects are decrypted, if the document is encrypted
+    security: Option<StandardSecurity>,
+    /// The encryption dictionary, if it's an indirect object. It isn't itself encrypted.
+    encrypt: Option<Reference>,
+}
+
+impl Document {
+    /// Opens a document from the bytes of a PDF file. If the cross-reference data is damaged, it
+    /// is reconstructed; see [`Document::repairs`]. Objects are parsed strictly. An encrypted
+    /// document is opened with an empty password, which works when only the owner password is
+    /// set.
+    pub fn open(data: impl Into<Vec<u8>>) -> Result<Self> {
+        Self::open_with(data, ParseMode::Strict)
+    }
+
+    /// Like [`Document::open`], but parses objects according to `mode`. In lenient mode, the
+    /// deviations from the spec that were accepted are available from [`Document::warnings`].
+    pub fn open_with(data: impl Into<Vec<u8>>, mode: ParseMode) -> Result<Self> {
+        Self::open_with_password(data, mode, b"")
+    }
+
+    /// Like [`Document::open_with`], but decrypts an encrypted document with `password`, which
+    /// may be either the user or the owner password
+    pub fn open_with_password(
+        data: impl Into<Vec<u8>>,
+        mode: ParseMode,
+        password: &[u8],
+    ) -> Result<Self> {
+        let data = data.into();
+        let version = parse_header(&data).ok_or(Error::InvalidHeader)?;
+        let (xref, repairs) = read_xref(&data)?;
+        let mut doc = Self {
+            data,
+            version,
+            xref,
+            repairs,
+            mode,
+            warnings: RefCell::new(Vec::new()),
+            object_streams: RefCell::new(HashMap::new()),
+            security: None,
+            encrypt: None,
+        };
+
+        if let Some(encrypt) = doc.trailer().get(b"Encrypt").cloned() {
+            doc.encrypt = encrypt.as_reference();
+            let encrypt = doc.resolve(&encrypt)?;
+            let encrypt = encrypt
+                .as_dictionary()
+                .ok_or_else(|| CryptError::InvalidEntry(b"Encrypt".to_vec()))?;
+            // The first half of the file identifier is mixed into the key. It isn't encrypted,
+            // being in the trailer.
+            let id = doc
+                .trailer()
+                .get(b"ID")
+                .and_then(Object::as_array)
+                .and_then(|id| id.first())
+                .and_then(Object::as_string)
+                .map_or(&[][..], |id| id.as_bytes());
+            doc.security = Some(StandardSecurity::new(encrypt, id, password)?);
+        }
+        Ok(doc)
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// The trailer dictionary. For documents with incremental updates, this combines the trailers
+    /// of every revision.
+    pub fn trailer(&self) -> &Dictionary {
+        self.xref.trailer()
+    }
+
+    /// The document's cross-reference data, including each revision of the document
+    pub fn xref(&self) -> &XrefChain {
+        &self.xref
+    }
+
+    /// What had to be repaired to open the document
+    pub fn repairs(&self) -> &RepairReport {
+        &self.repairs
+    }
+
+    pub fn mode(&self) -> ParseMode {
+        self.mode
+    }
+
+    /// The deviations from the spec accepted so far while reading objects. Objects are read on
+    /// demand, so this grows as more of the document is read.
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.warnings.borrow().clone()
+    }
+
+    /// How the document is encrypted, or `None` if it isn't
+    pub fn security(&self) -> Option<&StandardSecurity> {
+        self.security.as_ref()
+    }
+
+    /// What may be done with the document: everything if it isn't encrypted, and otherwise what
+    /// the password it was opened with allows
+    pub fn permissions(&self) -> Permissions {
+        self.security
+            .as_ref()
+            .map_or_else(Permissions::all, StandardSecurity::effective_permissions)
+    }
+
+    /// The raw bytes of the file
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Reads the indirect object `reference` refers to
+    pub fn get(&self, reference: Reference) -> Result<Object> {
+        let ctx = ParseContext::new(self.mode);
+        let obj = self.get_with(reference, &ctx);
+        self.record_warnings(ctx, reference);
+        obj
+    }
+
+    fn get_with(&self, reference: Reference, ctx: &ParseContext) -> Result<Object> {
+        let not_found = || Error::ObjectNotFound(reference);
+        match *self
+            .xref
+            .table()
+            .get(reference.number)
+            .ok_or_else(not_found)?
+        {
+            XrefEntry::Free { .. } => Err(not_found()),
+            XrefEntry::InUse { offset, generation } => {
+                if generation != reference.generation {
+                    return Err(not_found());
+                }
+                let obj = parse_indirect_object(&self.data, offset, ctx)
+                    .map_err(|e| e.in_object(reference))?;
+                if obj.id != reference {
+                    return Err(Error::InvalidObject(reference));
+                }
+                self.decrypt(reference, obj.object)
+            }
+            XrefEntry::Compressed { stream, index } => {
+                if reference.generation != 0 {
+                    return Err(not_found());
+                }
+                let objstm = self.object_stream(stream)?;
+                let (num, obj) = objstm
+                    .parse_object(index as usize, ctx)
+                    .map_err(|e| match e {
+                        Error::Parse(e) => Error::Parse(e.in_object(reference)),
+                        e => e,
+                    })?;
+                if num != reference.number {
+                    return Err(Error::InvalidObject(reference));
+                }
+                Ok(obj)
+            }
+        }
+    }
+
+    /// Follows `obj` if it's a reference, until a direct object is reached. Per PDF Spec section
+    /// 7.3.10, a reference to an object that doesn't exist resolves to `null`.
+    pub fn resolve(&self, obj: &Object) -> Result<Object> {
+        let &Object::Reference(mut reference) = obj else {
+            return Ok(obj.clone());
+        };
+        let first = reference;
+        for _ in 0..MAX_REFERENCE_DEPTH {
+            match self.get(reference) {
+                Ok(Object::Reference(next)) => reference = next,
+                Ok(obj) => return Ok(obj),
+                Err(Error::ObjectNotFound(_)) => return Ok(Object::Null),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(Error::ReferenceLoop(first))
+    }
+
+    /// Reads the page tree, giving every page in order with its inherited attributes
+    pub fn pages(&self) -> Result<Pages> {
+        Pages::read(self)
+    }
+
+    /// Iterates over every object in use in the document, in order of object number
+    pub fn objects(&self) -> impl Iterator<Item = (Reference, Result<Object>)> + '_ {
+        self.xref.table().iter().filter_map(|(number, entry)| {
+            if let XrefEntry::Free { .. } = entry {
+                return None;
+            }
+            let reference = Reference {
+                number,
+                generation: entry.generation(),
+            };
+            Some((reference, self.get(reference)))
+        })
+    }
+
+    /// Decrypts the strings and streams of the indirect object `reference`, if the document is
+    /// encrypted. Objects in object streams are only encrypted as part of the object stream.
+    fn decrypt(&self, reference: Reference, obj: Object) -> Result<Object> {
+        match &self.security {
+            Some(security) if self.encrypt != Some(reference) => {
+                Ok(security.decrypt_object(reference, obj)?)
+            }
+            _ => Ok(obj),
+        }
+    }
+
+    fn record_warnings(&self, ctx: ParseContext, reference: Reference) {
+        self.warnings.borrow_mut().extend(
+            ctx.into_warnings()
+                .into_iter()
+                .map(|w| w.in_object(reference)),
+        );
+    }
+
+    /// Returns the decoded object stream with object number `number`
+    fn object_stream(&self, number: u32) -> Result<Rc<ObjectStream>> {
+        if let Some(objstm) = self.object_streams.borrow().get(&number) {
+            return Ok(objstm.clone());
+        }
+
+        let reference = Reference {
+            number,
+            generation: 0,
+        };
+        // Object streams can't themselves be compressed, so look them up directly
+        let offset = self
+            .xref
+            .table()
+            .offset_of(reference)
+            .ok_or(Error::ObjectNotFound(reference))?;
+        let ctx = ParseContext::new(self.mode);
+        let obj = parse_indirect_object(&self.data, offset, &ctx);
+        self.record_warnings(ctx, reference);
+        let obj = obj.map_err(|e| e.in_object(reference))?;
+        let obj = self.decrypt(reference, obj.object)?;
+        let stream = obj
+            .as_stream()
+            .ok_or(XrefError::InvalidObjectStream(number))?;
+        let objstm = Rc::new(ObjectStream::new(number, stream)?);
+        self.object_streams
+            .borrow_mut()
+            .insert(number, objstm.clone());
+        Ok(objstm)
+    }
+}
+
+/// Finds the `%PDF-x.y` header near the start of the file
+fn parse_header(data: &[u8]) -> Option<Version> {
+    let search = &data[..data.len().min(HEADER_SEARCH_LEN)];
+    let start = find_subslice(search, b"%PDF-")? + b"%PDF-".len();
+    match data.get(start..start + 3)? {
+        [major @ b'0'..=b'9', b'.', minor @ b'0'..=b'9'] => Some(Version {
+            major: major - b'0',
+            minor: minor - b'0',
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::object::{Number, PdfString};
+    use crate::error::{ParseErrorKind, WarningKind};
+    use crate::lexer::MAX_NESTING;
+
+    /// Builds a document from object bodies, numbered from 1, with a correct xref table
+    fn build(version: &str, objects: &[&str]) -> Vec<u8> {
+        build_with_trailer(version, objects, "")
+    }
+
+    /// Like `build`, with extra trailer entries
+    fn build_with_trailer(version: &str, objects: &[&str], trailer: &str) -> Vec<u8> {
+        let mut data = format!("%PDF-{version}\n").into_bytes();
+        let mut offsets = Vec::new();
+        for (i, obj) in objects.iter().enumerate() {
+            offsets.push(data.len());
+            data.extend_from_slice(format!("{} 0 obj\n{obj}\nendobj\n", i + 1).as_bytes());
+        }
+        let startxref = data.len();
+        data.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f\r\n", objects.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            data.extend_from_slice(format!("{offset:010} 00000 n\r\n").as_bytes());
+        }
+        data.extend_from_slice(
+            format!(
+                "trailer\n<</Size {}/Root 1 0 R{trailer}>>\nstartxref\n{startxref}\n%%EOF\n",
+                objects.len() + 1
+            )
+            .as_bytes(),
+        );
+        data
+    }
+
+    fn reference(number: u32) -> Reference {
+        Reference {
+            number,
+            generation: 0,
+        }
+    }
+
+    #[test]
+    fn open() {
+        let doc = Document::open(build(
+            "1.7",
+            &[
+                "<</Type/Catalog/Pages 2 0 R>>",
+                "<</Type/Pages/Kids[]/Count 0>>",
+            ],
+        ))
+        .unwrap();
+        assert_eq!(doc.version(), Version { major: 1, minor: 7 });
+        assert_eq!(doc.version().to_string(), "1.7");
+        assert!(doc.repairs().is_empty());
+        assert_eq!(
+            doc.trailer().get(b"Root"),
+            Some(&Object::Reference(reference(1)))
+        );
+
+        let root = doc.resolve(doc.trailer().get(b"Root").unwrap()).unwrap();
+        let pages = doc
+            .resolve(root.as_dictionary().unwrap().get(b"Pages").unwrap())
+            .unwrap();
+        assert_eq!(
+            pages.as_dictionary().unwrap().get(b"Count"),
+            Some(&Object::Number(Number::Integer(0)))
+        );
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert_eq!(
+            Document::open(b"hello".to_vec()).err(),
+            Some(Error::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn get() {
+        let doc = Document::open(build("1.4", &["<<>>", "(two)"])).unwrap();
+        assert_eq!(doc.security(), None);
+        assert_eq!(doc.permissions(), Permissions::all());
+        assert_eq!(
+            doc.get(reference(2)),
+            Ok(Object::String(PdfString::Literal(b"two".to_vec())))
+        );
+        assert_eq!(
+            doc.get(reference(3)),
+            Err(Error::ObjectNotFound(reference(3)))
+        );
+        assert_eq!(
+            doc.get(Reference {
+                number: 2,
+                generation: 1
+            }),
+            Err(Error::ObjectNotFound(Reference {
+                number: 2,
+                generation: 1
+            }))
+        );
+        // Object 0 is always free
+        assert_eq!(
+            doc.get(reference(0)),
+            Err(Error::ObjectNotFound(reference(0)))
+        );
+    }
+
+    #[test]
+    fn resolve() {
+        let doc = Document::open(build(
+            "1.4",
+            &["<<>>", "3 0 R", "42", "4 0 R", "5 0 R", "4 0 R"],
+        ))
+        .unwrap();
+        assert_eq!(
+            doc.resolve(&Object::Reference(reference(2))),
+            Ok(Object::Number(Number::Integer(42)))
+        );
+        assert_eq!(
+            doc.resolve(&Object::Boolean(true)),
+            Ok(Object::Boolean(true))
+        );
+        assert_eq!(
+            doc.resolve(&Object::Reference(reference(99))),
+            Ok(Object::Null)
+        );
+        assert_eq!(
+            doc.resolve(&Object::Reference(reference(4))),
+            Err(Error::ReferenceLoop(reference(4)))
+        );
+    }
+
+    #[test]
+    fn objects() {
+        let doc = Document::open(build("1.4", &["<<>>", "true", "null"])).unwrap();
+        let objects: Vec<_> = doc.objects().collect();
+        assert_eq!(
+            objects,
+            vec![
+                (reference(1), Ok(Object::Dictionary(Dictionary::new()))),
+                (reference(2), Ok(Object::Boolean(true))),
+                (reference(3), Ok(Object::Null)),
+            ]
+        );
+    }
+
+    #[test]
+    fn lenient() {
+        let data = build("1.4", &["<<>>", "[1 --2]"]);
+        let strict = Document::open(data.clone()).unwrap();
+        assert!(matches!(strict.get(reference(2)), Err(Error::Parse(_))));
+        assert!(strict.warnings().is_empty());
+
+        let doc = Document::open_with(data, ParseMode::Lenient).unwrap();
+        assert_eq!(doc.mode(), ParseMode::Lenient);
+        assert_eq!(
+            doc.get(reference(2)),
+            Ok(Object::Array(vec![
+                Object::Number(Number::Integer(1)),
+                Object::Number(Number::Integer(-2)),
+            ]))
+        );
+        let warnings = doc.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, WarningKind::DoubleSign);
+        assert_eq!(warnings[0].object, Some(reference(2)));
+        assert_eq!(
+            warnings[0].to_string(),
+            format!(
+                "Number with more than one sign at byte {} in object 2 0",
+                warnings[0].offset
+            )
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        // Too deep to drop without overflowing the stack, had it been parsed
+        let deep = format!("{}{}", "[".repeat(200_000), "]".repeat(200_000));
+        let nested = format!(
+            "{}{}",
+            "<</A [".repeat(MAX_NESTING / 2),
+            "]>>".repeat(MAX_NESTING / 2)
+        );
+        let data = build("1.4", &["<<>>", &deep, &nested]);
+        for mode in [ParseMode::Strict, ParseMode::Lenient] {
+            let doc = Document::open_with(data.clone(), mode).unwrap();
+            match doc.get(reference(2)) {
+                Err(Error::Parse(err)) => {
+                    assert_eq!(err.kind, ParseErrorKind::TooDeep);
+                    assert_eq!(err.bytes, b"[");
+                }
+                other => panic!("expected a parse error, got {other:?}"),
+            }
+            assert!(doc.get(reference(3)).is_ok());
+        }
+    }
+
+    #[test]
+    fn compressed_objects() {
+        let objstm_data = "2 0 3 6 (two) [3]";
+        let mut data = b"%PDF-1.5\n".to_vec();
+        let catalog = data.len();
+        data.extend_from_slice(b"1 0 obj\n<</Type/Catalog>>\nendobj\n");
+        let objstm = data.len();
+        data.extend_from_slice(
+            format!(
+                "4 0 obj\n<</Type/ObjStm/N 2/First 8/Length {}>>\nstream\n{objstm_data}\nendstream\nendobj\n",
+                objstm_data.len()
+            )
+            .as_bytes(),
+        );
+        let xref = data.len();
+        let rows = [
+            [0u8, 0, 0],
+            [1, catalog as u8, 0],
+            [2, 4, 0],
+            [2, 4, 1],
+            [1, objstm as u8, 0],
+        ]
+        .concat();
+        data.extend_from_slice(
+            format!(
+                "5 0 obj\n<</Type/XRef/Size 5/W[1 1 1]/Root 1 0 R/Length {}>>\nstream\n",
+                rows.len()
+            )
+            .as_bytes(),
+        );
+        data.extend_from_slice(&rows);
+        data.extend_from_slice(
+            format!("\nendstream\nendobj\nstartxref\n{xref}\n%%EOF\n").as_bytes(),
+        );
+
+        let doc = Document::open(data).unwrap();
+        assert!(doc.repairs().is_empty());
+        assert_eq!(
+            doc.get(reference(2)),
+            Ok(Object::String(PdfString::Literal(b"two".to_vec())))
+        );
+        assert_eq!(
+            doc.get(reference(3)),
+            Ok(Object::Array(vec![Object::Number(Number::Integer(3))]))
+        );
+        assert_eq!(doc.objects().count(), 4);
+    }
+
+    #[test]
+    fn encrypted() {
+        // RC4 with a 40-bit key, with user password `user` and owner password `owner`
+        let data = build_with_trailer(
+            "1.4",
+            &[
+                "<</Type/Catalog>>",
+                concat!(
+                    "<</Filter/Standard/V 1/R 2/P -3904",
+                    "/O<94e8094419662a774442fb072e3d9f19e9d130ec09a4d0061e78fe920f7ab62f>",
+                    "/U<00960a327fd80c93dd8fdefe3ab7fff0e2e8b39542a5cecc70f05f28755084db>>>"
+                ),
+                "null",
+                "null",
+                "<2d265daadc979b720c46e7118f>",
+            ],
+            "/Encrypt 2 0 R/ID[<0123456789abcdeffedcba9876543210><0123456789abcdeffedcba9876543210>]",
+        );
+        assert_eq!(
+            Document::open(data.clone()).err(),
+            Some(Error::Crypt(CryptError::IncorrectPassword))
+        );
+
+        for password in [&b"user"[..], b"owner"] {
+            let doc =
+                Document::open_with_password(data.clone(), ParseMode::Strict, password).unwrap();
+            assert_eq!(doc.security().unwrap().revision, 2);
+            // `/P` allows nothing, but the owner can do anything
+            assert_eq!(doc.permissions().copy, password == b"owner");
+            assert_eq!(
+                doc.get(reference(5)),
+                Ok(Object::String(PdfString::Hex(b"Hello, world!".to_vec())))
+            );
+            // The encryption dictionary itself isn't encrypted
+            let encrypt = doc.get(reference(2)).unwrap();
+            assert_eq!(
+                encrypt
+                    .as_dictionary()
+                    .unwrap()
+                    .get(b"O")
+                    .unwrap()
+                    .as_string()
+                    .unwrap()
+                    .as_bytes()[0],
+                0x94
+            );
+        }
+    }
+}
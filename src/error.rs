@@ -0,0 +1,328 @@
+//! The crate's error type
+
+use std::fmt::Display;
+
+use crate::ast::object::Reference;
+use crate::content::ContentError;
+use crate::crypt::CryptError;
+use crate::filter::FilterError;
+use crate::lexer::{PdfLexError, Tok};
+use crate::page::PageTreeError;
+use crate::xref::XrefError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The file doesn't start with a `%PDF-x.y` header
+    InvalidHeader,
+    /// The cross-reference data couldn't be read, even after trying to repair it
+    Xref(XrefError),
+    /// The referenced object isn't in the cross-reference table, or has been freed
+    ObjectNotFound(Reference),
+    /// The referenced object couldn't be parsed
+    InvalidObject(Reference),
+    /// Resolving a reference led back to the same reference
+    ReferenceLoop(Reference),
+    /// The object couldn't be parsed due to a syntax error
+    Parse(ParseError),
+    /// A stream's data couldn't be decoded
+    Filter(FilterError),
+    /// The document is encrypted, and couldn't be decrypted
+    Crypt(CryptError),
+    /// The page tree is malformed
+    PageTree(PageTreeError),
+    /// A content stream operator is unknown or has invalid operands
+    Content(ContentError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidHeader => write!(f, "Missing `%PDF-` header"),
+            Error::Xref(e) => e.fmt(f),
+            Error::ObjectNotFound(r) => {
+                write!(f, "Object {} {} not found", r.number, r.generation)
+            }
+            Error::InvalidObject(r) => {
+                write!(f, "Object {} {} is malformed", r.number, r.generation)
+            }
+            Error::ReferenceLoop(r) => write!(
+                f,
+                "Object {} {} is a reference to itself",
+                r.number, r.generation
+            ),
+            Error::Parse(e) => e.fmt(f),
+            Error::Filter(e) => e.fmt(f),
+            Error::Crypt(e) => e.fmt(f),
+            Error::PageTree(e) => e.fmt(f),
+            Error::Content(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<XrefError> for Error {
+    fn from(value: XrefError) -> Self {
+        Error::Xref(value)
+    }
+}
+
+impl From<FilterError> for Error {
+    fn from(value: FilterError) -> Self {
+        Error::Filter(value)
+    }
+}
+
+impl From<CryptError> for Error {
+    fn from(value: CryptError) -> Self {
+        Error::Crypt(value)
+    }
+}
+
+impl From<PageTreeError> for Error {
+    fn from(value: PageTreeError) -> Self {
+        Error::PageTree(value)
+    }
+}
+
+impl From<ContentError> for Error {
+    fn from(value: ContentError) -> Self {
+        Error::Content(value)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(value: ParseError) -> Self {
+        Error::Parse(value)
+    }
+}
+
+/// The error type produced by the generated parser
+pub(crate) type RawParseError<'input> =
+    lalrpop_util::ParseError<usize, Tok<'input>, PdfLexError<'input>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A character that can't start or continue any token
+    UnexpectedChar,
+    /// The input ended in the middle of a token
+    UnexpectedEof,
+    /// A token is much longer than any valid token
+    TokenTooLong,
+    /// A well-formed token was found somewhere it's not allowed
+    UnexpectedToken,
+    /// The object is missing tokens at its start, e.g. `0 obj` without an object number
+    MissingTokens,
+    /// There are tokens before the start of what is otherwise a complete object
+    ExtraToken,
+    InvalidObjectId,
+    InvalidHexString,
+    InvalidName,
+    InvalidNumber,
+    /// Arrays and dictionaries are nested more deeply than we'll parse
+    TooDeep,
+}
+
+/// A syntax error, located in terms of the file (or the decoded object stream) being parsed
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The byte offset of the offending token or character
+    pub offset: usize,
+    /// The offending token. Empty if the problem is missing tokens.
+    pub bytes: Vec<u8>,
+    /// Descriptions of the tokens that would have been valid at `offset`.
+    ///
+    /// The parser reads tokens last-to-first (see `pdf.lalrpop`), so these are the tokens that
+    /// could have appeared at `offset` given everything *after* it, which is how they should be
+    /// read when looking at the file.
+    pub expected: Vec<String>,
+    /// The indirect object being parsed, if known
+    pub object: Option<Reference>,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ParseErrorKind, offset: usize, bytes: &[u8]) -> Self {
+        Self {
+            kind,
+            offset,
+            bytes: bytes.to_vec(),
+            expected: Vec::new(),
+            object: None,
+        }
+    }
+
+    /// Converts an error from the lexer. `input` is what was being lexed.
+    pub(crate) fn from_lex(input: &[u8], err: PdfLexError) -> Self {
+        // Token slices always point into `input`, so we can recover their offset
+        let offset_of = |tok: &[u8]| {
+            (tok.as_ptr() as usize)
+                .checked_sub(input.as_ptr() as usize)
+                .filter(|offset| *offset <= input.len())
+                .unwrap_or(0)
+        };
+        let from_tok = |kind, tok: &[u8]| Self::new(kind, offset_of(tok), tok);
+
+        match err {
+            PdfLexError::UnexpectedChar(i) => Self::new(
+                ParseErrorKind::UnexpectedChar,
+                i,
+                input.get(i..i + 1).unwrap_or(&[]),
+            ),
+            PdfLexError::UnexpectedEOF => {
+                Self::new(ParseErrorKind::UnexpectedEof, input.len(), &[])
+            }
+            PdfLexError::TokenTooLong(tok) => from_tok(ParseErrorKind::TokenTooLong, tok),
+            PdfLexError::InvalidObjectId(tok) => from_tok(ParseErrorKind::InvalidObjectId, tok),
+            PdfLexError::InvalidHexString(tok) => from_tok(ParseErrorKind::InvalidHexString, tok),
+            PdfLexError::InvalidName(tok) => from_tok(ParseErrorKind::InvalidName, tok),
+            PdfLexError::InvalidNumber(tok) => from_tok(ParseErrorKind::InvalidNumber, tok),
+            PdfLexError::TooDeep(tok) => from_tok(ParseErrorKind::TooDeep, tok),
+        }
+    }
+
+    /// Converts an error from the parser. `input` is what was being parsed, and `start` is where
+    /// in `input` lexing began.
+    pub(crate) fn from_parse(input: &[u8], start: usize, err: RawParseError) -> Self {
+        use lalrpop_util::ParseError as E;
+
+        let token_bytes = |l: usize, r: usize| input.get(l..r).unwrap_or(&[]);
+        match err {
+            E::InvalidToken { location } => Self::new(
+                ParseErrorKind::UnexpectedChar,
+                location,
+                token_bytes(location, location + 1),
+            ),
+            // Running out of tokens means we've reached the *start* of the object
+            E::UnrecognizedEof { expected, .. } => Self {
+                expected: describe_expected(expected),
+                ..Self::new(ParseErrorKind::MissingTokens, start, &[])
+            },
+            E::UnrecognizedToken {
+                token: (l, _, r),
+                expected,
+            } => Self {
+                expected: describe_expected(expected),
+                ..Self::new(ParseErrorKind::UnexpectedToken, l, token_bytes(l, r))
+            },
+            E::ExtraToken { token: (l, _, r) } => {
+                Self::new(ParseErrorKind::ExtraToken, l, token_bytes(l, r))
+            }
+            E::User { error } => Self::from_lex(input, error),
+        }
+    }
+
+    /// Records that this error happened while parsing the object `reference`
+    pub(crate) fn in_object(mut self, reference: Reference) -> Self {
+        self.object = Some(reference);
+        self
+    }
+}
+
+/// Turns the terminal names from the grammar into something readable
+fn describe_expected(expected: Vec<String>) -> Vec<String> {
+    expected
+        .into_iter()
+        .map(|name| match name.as_str() {
+            "NameTok" => "name".to_string(),
+            "NumberTok" => "number".to_string(),
+            "RawStrContent" => "string content".to_string(),
+            "HexStrContent" => "hex string content".to_string(),
+            "StreamData" => "stream data".to_string(),
+            "UnknownTok" => "keyword".to_string(),
+            quoted => format!("`{}`", quoted.trim_matches('"')),
+        })
+        .collect()
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match self.kind {
+            ParseErrorKind::UnexpectedChar => "Unexpected character",
+            ParseErrorKind::UnexpectedEof => "Unexpected end of input",
+            ParseErrorKind::TokenTooLong => "Token too long",
+            ParseErrorKind::UnexpectedToken => "Unexpected token",
+            ParseErrorKind::MissingTokens => "Missing tokens",
+            ParseErrorKind::ExtraToken => "Extra token",
+            ParseErrorKind::InvalidObjectId => "Invalid object or generation number",
+            ParseErrorKind::InvalidHexString => "Invalid hex string",
+            ParseErrorKind::InvalidName => "Invalid name",
+            ParseErrorKind::InvalidNumber => "Invalid number",
+            ParseErrorKind::TooDeep => "Nesting too deep",
+        };
+        write!(f, "{what}")?;
+        if !self.bytes.is_empty() {
+            write!(f, " `{}`", String::from_utf8_lossy(&self.bytes))?;
+        }
+        write!(f, " at byte {}", self.offset)?;
+        if let Some(r) = self.object {
+            write!(f, " in object {} {}", r.number, r.generation)?;
+        }
+        if !self.expected.is_empty() {
+            write!(f, ", expected one of {}", self.expected.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// How strictly to follow the PDF spec when parsing objects
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Reject anything the spec doesn't allow
+    #[default]
+    Strict,
+    /// Accept the common deviations from the spec that Acrobat tolerates, recording each one as
+    /// a [`Warning`]
+    Lenient,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A number with more than one leading sign, e.g. `--5`
+    DoubleSign,
+    /// A number with junk after it, or no digits at all, e.g. `1.2.3` or `-`. The junk is
+    /// ignored, and a number without digits is read as zero.
+    MalformedNumber,
+    /// An integer too large to represent, which was read as a real instead
+    IntegerOverflow,
+    /// An indirect object that isn't closed by `endobj`
+    MissingEndobj,
+}
+
+/// A deviation from the spec that was accepted in [`ParseMode::Lenient`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// The byte offset of the offending token, or where the missing token should have been
+    pub offset: usize,
+    /// The indirect object being parsed, if known
+    pub object: Option<Reference>,
+}
+
+impl Warning {
+    /// Records that this warning happened while parsing the object `reference`
+    pub(crate) fn in_object(mut self, reference: Reference) -> Self {
+        self.object = Some(reference);
+        self
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match self.kind {
+            WarningKind::DoubleSign => "Number with more than one sign",
+            WarningKind::MalformedNumber => "Malformed number",
+            WarningKind::IntegerOverflow => "Integer too large, read as a real",
+            WarningKind::MissingEndobj => "Missing `endobj`",
+        };
+        write!(f, "{what} at byte {}", self.offset)?;
+        if let Some(r) = self.object {
+            write!(f, " in object {} {}", r.number, r.generation)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,521 @@
+//! Stream filters, which decode the data of streams. See PDF Spec section 7.4
+
+use std::fmt::Display;
+
+use crate::ast::object::{Dictionary, Name, Number, Object, Stream};
+
+mod ascii85;
+mod ascii_hex;
+mod ccitt;
+mod flate;
+mod image;
+mod lzw;
+mod predictor;
+mod run_length;
+
+pub use ascii_hex::AsciiHex;
+pub use ascii85::Ascii85;
+pub use ccitt::{CcittFax, CcittParams};
+pub use flate::Flate;
+pub use image::{EncodedImage, ImageFilter, decode_image_stream};
+pub use lzw::Lzw;
+pub use predictor::{PngFilter, Predictor, PredictorKind};
+pub use run_length::RunLength;
+
+/// The most data a filter will decode, so that a small stream can't make us allocate gigabytes
+pub(crate) static MAX_DECODED_LEN: usize = 1 << 28;
+
+/// A filter that can decode stream data
+pub trait Filter {
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, FilterError>;
+}
+
+/// A filter that can also encode stream data, for writing
+pub trait Encoder: Filter {
+    /// The name of the filter, for the stream's `/Filter` entry
+    fn name(&self) -> &'static [u8];
+
+    /// The stream's `/DecodeParms` entry for this filter, if it needs one to decode the data
+    fn params(&self) -> Option<Dictionary> {
+        None
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterError {
+    /// A `/Filter` we don't implement, or that isn't a name
+    UnsupportedFilter(Vec<u8>),
+    /// An entry in `/DecodeParms` with an invalid value
+    InvalidParameter(Vec<u8>),
+    /// The data couldn't be decoded by the named filter
+    InvalidData(Vec<u8>),
+    /// The named filter decodes the data to more than `MAX_DECODED_LEN` bytes
+    TooLarge(Vec<u8>),
+}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterError::UnsupportedFilter(name) => {
+                write!(f, "Unsupported filter /{}", String::from_utf8_lossy(name))
+            }
+            FilterError::InvalidParameter(key) => write!(
+                f,
+                "Invalid /{} in stream decode parameters",
+                String::from_utf8_lossy(key)
+            ),
+            FilterError::InvalidData(name) => {
+                write!(f, "Malformed /{} data", String::from_utf8_lossy(name))
+            }
+            FilterError::TooLarge(name) => {
+                write!(
+                    f,
+                    "Decoded /{} data is too large",
+                    String::from_utf8_lossy(name)
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// Creates the filter named `name`, configured by its decode parameters `params`. Both the full
+/// names and the abbreviations used in inline images (PDF Spec section 8.9.7) are accepted.
+pub fn new_filter(
+    name: &[u8],
+    params: Option<&Dictionary>,
+) -> Result<Box<dyn Filter>, FilterError> {
+    match name {
+        b"ASCIIHexDecode" | b"AHx" => Ok(Box::new(AsciiHex)),
+        b"ASCII85Decode" | b"A85" => Ok(Box::new(Ascii85)),
+        b"LZWDecode" | b"LZW" => Ok(Box::new(Lzw::new(params)?)),
+        b"FlateDecode" | b"Fl" => Ok(Box::new(Flate::new(params)?)),
+        b"RunLengthDecode" | b"RL" => Ok(Box::new(RunLength)),
+        b"CCITTFaxDecode" | b"CCF" => Ok(Box::new(CcittFax::new(params)?)),
+        // Decryption happens when the document reads the stream, so there's nothing left to do
+        b"Crypt" => Ok(Box::new(Decrypted)),
+        _ => Err(FilterError::UnsupportedFilter(name.to_vec())),
+    }
+}
+
+/// The `Crypt` filter, for data that has already been decrypted. See PDF Spec section 7.4.10
+struct Decrypted;
+
+impl Filter for Decrypted {
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        Ok(data.to_vec())
+    }
+}
+
+/// Returns the filters a stream's data is encoded with, in the order they should be applied to
+/// decode it, along with each one's decode parameters. See PDF Spec section 7.3.8.2
+pub fn stream_filters(dict: &Dictionary) -> Result<Vec<(&Name, Option<&Dictionary>)>, FilterError> {
+    let names: Vec<&Name> = match dict.get(b"Filter") {
+        None => Vec::new(),
+        Some(Object::Name(name)) => vec![name],
+        Some(Object::Array(filters)) => filters
+            .iter()
+            .map(|filter| {
+                filter
+                    .as_name()
+                    .ok_or_else(|| FilterError::UnsupportedFilter(Vec::new()))
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(FilterError::UnsupportedFilter(Vec::new())),
+    };
+
+    // A single filter has a single dictionary, and a chain has an array with an entry (possibly
+    // `null`) per filter
+    let invalid = || FilterError::InvalidParameter(b"DecodeParms".to_vec());
+    let params: Vec<Option<&Dictionary>> = match dict.get(b"DecodeParms") {
+        None => Vec::new(),
+        Some(Object::Dictionary(params)) => vec![Some(params)],
+        Some(Object::Array(params)) => params
+            .iter()
+            .map(|params| match params {
+                Object::Dictionary(params) => Ok(Some(params)),
+                Object::Null => Ok(None),
+                _ => Err(invalid()),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(invalid()),
+    };
+
+    Ok(names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (name, params.get(i).copied().flatten()))
+        .collect())
+}
+
+/// Returns the decoded data of `stream`, applying each filter in its `/Filter` entry in turn.
+/// Image data encoded with DCT, JPX or JBIG2 can't be decoded here; use [`decode_image_stream`] to
+/// get it in its encoded form instead.
+pub fn decode_stream(stream: &Stream) -> Result<Vec<u8>, FilterError> {
+    let mut data = stream.data.clone();
+    for (name, params) in stream_filters(&stream.dict)? {
+        data = new_filter(name.as_bytes(), params)?.decode(&data)?;
+    }
+    Ok(data)
+}
+
+/// Creates a stream holding `data` encoded with `filters`, with the `/Filter`, `/DecodeParms` and
+/// `/Length` entries needed to decode it. As with `/Filter`, the filters are listed in the order
+/// they should be applied to decode the data, so the last one is applied first here.
+pub fn encode_stream(data: &[u8], filters: &[&dyn Encoder]) -> Stream {
+    let mut encoded = data.to_vec();
+    for filter in filters.iter().rev() {
+        encoded = filter.encode(&encoded);
+    }
+
+    let mut dict = Dictionary::new();
+    let mut names: Vec<Object> = filters
+        .iter()
+        .map(|filter| Object::Name(Name::from(filter.name())))
+        .collect();
+    let mut params: Vec<Object> = filters
+        .iter()
+        .map(|filter| filter.params().map_or(Object::Null, Object::Dictionary))
+        .collect();
+    // A single filter is written directly rather than in an array. Inserting `null` parameters is
+    // a no-op.
+    if names.len() == 1 {
+        dict.insert(Name::from(b"Filter"), names.remove(0));
+        dict.insert(Name::from(b"DecodeParms"), params.remove(0));
+    } else if !names.is_empty() {
+        dict.insert(Name::from(b"Filter"), Object::Array(names));
+        if !params.iter().all(Object::is_null) {
+            dict.insert(Name::from(b"DecodeParms"), Object::Array(params));
+        }
+    }
+    dict.insert(
+        Name::from(b"Length"),
+        Object::Number(Number::Integer(encoded.len() as i64)),
+    );
+    Stream {
+        dict,
+        data: encoded,
+    }
+}
+
+/// Reads the integer parameter `key` from `params`, which must be within `range`, or `default` if
+/// it's absent
+fn int_param(
+    params: Option<&Dictionary>,
+    key: &[u8],
+    default: usize,
+    range: std::ops::RangeInclusive<usize>,
+) -> Result<usize, FilterError> {
+    let Some(val) = params.and_then(|params| params.get(key)) else {
+        return Ok(default);
+    };
+    val.as_integer()
+        .and_then(|val| usize::try_from(val).ok())
+        .filter(|val| range.contains(val))
+        .ok_or_else(|| FilterError::InvalidParameter(key.to_vec()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Compresses `data` with zlib, for tests of things built on Flate
+    pub(crate) fn zlib(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn stream(dict: &[(&[u8], Object)], data: Vec<u8>) -> Stream {
+        Stream {
+            dict: dict
+                .iter()
+                .map(|(k, v)| (Name::from(*k), v.clone()))
+                .collect(),
+            data,
+        }
+    }
+
+    #[test]
+    fn unfiltered() {
+        assert_eq!(
+            decode_stream(&stream(&[], b"abc".to_vec())),
+            Ok(b"abc".to_vec())
+        );
+        assert_eq!(
+            decode_stream(&stream(
+                &[(b"Filter", Object::Array(vec![]))],
+                b"abc".to_vec()
+            )),
+            Ok(b"abc".to_vec())
+        );
+    }
+
+    #[test]
+    fn flate() {
+        let filter = Object::Name(Name::from(b"FlateDecode"));
+        assert_eq!(
+            decode_stream(&stream(&[(b"Filter", filter.clone())], zlib(b"hello"))),
+            Ok(b"hello".to_vec())
+        );
+        assert_eq!(
+            decode_stream(&stream(
+                &[(b"Filter", Object::Array(vec![filter]))],
+                zlib(b"hello")
+            )),
+            Ok(b"hello".to_vec())
+        );
+    }
+
+    #[test]
+    fn decode_parms() {
+        // Rows of 2 bytes, each with the PNG Up predictor
+        let params = Dictionary::from_iter([
+            (
+                Name::from(b"Predictor"),
+                Object::Number(Number::Integer(12)),
+            ),
+            (Name::from(b"Columns"), Object::Number(Number::Integer(2))),
+        ]);
+        let data = zlib(&[2, 1, 2, 2, 1, 1]);
+        let filter = Object::Name(Name::from(b"FlateDecode"));
+        assert_eq!(
+            decode_stream(&stream(
+                &[
+                    (b"Filter", filter.clone()),
+                    (b"DecodeParms", Object::Dictionary(params.clone()))
+                ],
+                data.clone()
+            )),
+            Ok(vec![1, 2, 2, 3])
+        );
+        assert_eq!(
+            decode_stream(&stream(
+                &[
+                    (b"Filter", Object::Array(vec![filter])),
+                    (
+                        b"DecodeParms",
+                        Object::Array(vec![Object::Dictionary(params)])
+                    )
+                ],
+                data
+            )),
+            Ok(vec![1, 2, 2, 3])
+        );
+    }
+
+    #[test]
+    fn huge_predictor_rows() {
+        // Rejected before allocating a 256 GB row
+        let params = Dictionary::from_iter([
+            (
+                Name::from(b"Predictor"),
+                Object::Number(Number::Integer(12)),
+            ),
+            (Name::from(b"Colors"), Object::Number(Number::Integer(32))),
+            (
+                Name::from(b"BitsPerComponent"),
+                Object::Number(Number::Integer(16)),
+            ),
+            (
+                Name::from(b"Columns"),
+                Object::Number(Number::Integer(u32::MAX as i64)),
+            ),
+        ]);
+        assert_eq!(
+            decode_stream(&stream(
+                &[
+                    (b"Filter", Object::Name(Name::from(b"FlateDecode"))),
+                    (b"DecodeParms", Object::Dictionary(params))
+                ],
+                zlib(&[2, 0, 0, 0])
+            )),
+            Err(FilterError::InvalidParameter(b"Columns".to_vec()))
+        );
+    }
+
+    #[test]
+    fn empty_predictor_rows() {
+        let params = Dictionary::from_iter([
+            (
+                Name::from(b"Predictor"),
+                Object::Number(Number::Integer(12)),
+            ),
+            (Name::from(b"Columns"), Object::Number(Number::Integer(4))),
+        ]);
+        assert_eq!(
+            decode_stream(&stream(
+                &[
+                    (b"Filter", Object::Name(Name::from(b"FlateDecode"))),
+                    (b"DecodeParms", Object::Dictionary(params))
+                ],
+                zlib(&[])
+            )),
+            Ok(Vec::new())
+        );
+    }
+
+    #[test]
+    fn chain() {
+        let hex: Vec<u8> = zlib(&[2, 1, 2, 2, 1, 1])
+            .iter()
+            .flat_map(|b| format!("{b:02x}").into_bytes())
+            .chain(*b">")
+            .collect();
+        let filters = Object::Array(vec![
+            Object::Name(Name::from(b"ASCIIHexDecode")),
+            Object::Name(Name::from(b"FlateDecode")),
+        ]);
+        // Each filter has its own parameters, with `null` for none
+        let params = Object::Array(vec![
+            Object::Null,
+            Object::Dictionary(Dictionary::from_iter([
+                (
+                    Name::from(b"Predictor"),
+                    Object::Number(Number::Integer(12)),
+                ),
+                (Name::from(b"Columns"), Object::Number(Number::Integer(2))),
+            ])),
+        ]);
+        assert_eq!(
+            decode_stream(&stream(
+                &[(b"Filter", filters.clone()), (b"DecodeParms", params)],
+                hex.clone()
+            )),
+            Ok(vec![1, 2, 2, 3])
+        );
+        assert_eq!(
+            decode_stream(&stream(&[(b"Filter", filters)], hex)),
+            Ok(vec![2, 1, 2, 2, 1, 1])
+        );
+
+        // With the abbreviated names used in inline images
+        let filters = Object::Array(vec![
+            Object::Name(Name::from(b"A85")),
+            Object::Name(Name::from(b"RL")),
+        ]);
+        assert_eq!(
+            decode_stream(&stream(&[(b"Filter", filters)], b"r-3_~>".to_vec())),
+            Ok(b"xxxxx".to_vec())
+        );
+    }
+
+    #[test]
+    fn encode() {
+        let data = b"Hello, world! Hello, world! Hello, world!".to_vec();
+
+        let stream = encode_stream(&data, &[]);
+        assert_eq!(stream.data, data);
+        assert_eq!(stream.dict.get(b"Filter"), None);
+        assert_eq!(
+            stream.dict.get(b"Length"),
+            Some(&Object::Number(Number::Integer(data.len() as i64)))
+        );
+
+        let stream = encode_stream(&data, &[&Flate::default()]);
+        assert_eq!(
+            stream.dict.get(b"Filter"),
+            Some(&Object::Name(Name::from(b"FlateDecode")))
+        );
+        assert_eq!(stream.dict.get(b"DecodeParms"), None);
+        assert_eq!(decode_stream(&stream), Ok(data.clone()));
+
+        let predictor = Predictor {
+            kind: PredictorKind::Png { filter: None },
+            colors: 1,
+            bits_per_component: 8,
+            columns: 8,
+        };
+        let flate = Flate {
+            level: 9,
+            predictor: Some(predictor.clone()),
+        };
+        let stream = encode_stream(&data, &[&flate]);
+        assert_eq!(
+            stream.dict.get(b"DecodeParms"),
+            Some(&Object::Dictionary(predictor.params()))
+        );
+        assert_eq!(decode_stream(&stream), Ok(data.clone()));
+    }
+
+    #[test]
+    fn encode_chain() {
+        let data: Vec<u8> = (0..2000).map(|i| (i / 7 % 13) as u8).collect();
+        let lzw = Lzw {
+            early_change: false,
+            predictor: None,
+        };
+        let stream = encode_stream(
+            &data,
+            &[&AsciiHex, &Ascii85, &RunLength, &lzw, &Flate::default()],
+        );
+        assert_eq!(
+            stream.dict.get(b"Filter"),
+            Some(&Object::Array(
+                [
+                    &b"ASCIIHexDecode"[..],
+                    b"ASCII85Decode",
+                    b"RunLengthDecode",
+                    b"LZWDecode",
+                    b"FlateDecode",
+                ]
+                .into_iter()
+                .map(|name| Object::Name(Name::from(name)))
+                .collect()
+            ))
+        );
+        assert_eq!(
+            stream.dict.get(b"DecodeParms"),
+            Some(&Object::Array(vec![
+                Object::Null,
+                Object::Null,
+                Object::Null,
+                Object::Dictionary(lzw.params().unwrap()),
+                Object::Null,
+            ]))
+        );
+        // The outermost encoding is ASCII hex
+        assert!(
+            stream
+                .data
+                .iter()
+                .all(|c| c.is_ascii_hexdigit() || b"\n>".contains(c))
+        );
+        assert_eq!(decode_stream(&stream), Ok(data));
+    }
+
+    #[test]
+    fn invalid_decode_parms() {
+        assert_eq!(
+            decode_stream(&stream(
+                &[
+                    (b"Filter", Object::Name(Name::from(b"FlateDecode"))),
+                    (b"DecodeParms", Object::Array(vec![Object::Boolean(true)]))
+                ],
+                vec![]
+            )),
+            Err(FilterError::InvalidParameter(b"DecodeParms".to_vec()))
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(
+            decode_stream(&stream(
+                &[(b"Filter", Object::Name(Name::from(b"Foo")))],
+                vec![]
+            )),
+            Err(FilterError::UnsupportedFilter(b"Foo".to_vec()))
+        );
+        assert_eq!(
+            decode_stream(&stream(&[(b"Filter", Object::Boolean(true))], vec![])),
+            Err(FilterError::UnsupportedFilter(vec![]))
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! The `ASCII85Decode` filter. See PDF Spec section 7.4.3
+
+use super::{Encoder, Filter, FilterError};
+use crate::lexer::is_whitespace;
+
+/// Decodes base-85 data, where each group of 5 characters from `!` to `u` encodes 4 bytes, up to a
+/// `~>` end-of-data marker
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ascii85;
+
+impl Filter for Ascii85 {
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        let invalid = || FilterError::InvalidData(b"ASCII85Decode".to_vec());
+        // Some producers include the `<~` that starts ASCII base-85 in PostScript
+        let data = data.strip_prefix(b"<~").unwrap_or(data);
+
+        let mut out = Vec::with_capacity(data.len() / 5 * 4);
+        let mut group = [0u8; 5];
+        let mut len = 0;
+        for &c in data {
+            match c {
+                b'~' => break,
+                c if is_whitespace(c) => {}
+                // `z` is shorthand for `!!!!!`, but only in place of a whole group
+                b'z' if len == 0 => out.extend_from_slice(&[0; 4]),
+                b'!'..=b'u' => {
+                    group[len] = c - b'!';
+                    len += 1;
+                    if len == 5 {
+                        out.extend_from_slice(&group_value(&group).ok_or_else(invalid)?);
+                        len = 0;
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        // A final partial group of n characters is padded with `u`s, and gives n - 1 bytes
+        match len {
+            0 => {}
+            1 => return Err(invalid()),
+            _ => {
+                group[len..].fill(b'u' - b'!');
+                let bytes = group_value(&group).ok_or_else(invalid)?;
+                out.extend_from_slice(&bytes[..len - 1]);
+            }
+        }
+        Ok(out)
+    }
+}
+
+// How many characters to write per line, to keep lines well under the recommended 255
+static CHARS_PER_LINE: usize = 75;
+
+impl Encoder for Ascii85 {
+    fn name(&self) -> &'static [u8] {
+        b"ASCII85Decode"
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() / 4 * 5 + 8);
+        let mut line_len = 0;
+        let mut push = |out: &mut Vec<u8>, chars: &[u8]| {
+            for c in chars {
+                if line_len == CHARS_PER_LINE {
+                    out.push(b'\n');
+                    line_len = 0;
+                }
+                out.push(*c);
+                line_len += 1;
+            }
+        };
+        for chunk in data.chunks(4) {
+            let mut group = [0u8; 4];
+            group[..chunk.len()].copy_from_slice(chunk);
+            let mut val = u32::from_be_bytes(group);
+            if chunk.len() == 4 && val == 0 {
+                push(&mut out, b"z");
+                continue;
+            }
+            let mut digits = [0u8; 5];
+            for digit in digits.iter_mut().rev() {
+                *digit = (val % 85) as u8 + b'!';
+                val /= 85;
+            }
+            // A final partial group of n bytes is written as n + 1 characters
+            push(&mut out, &digits[..chunk.len() + 1]);
+        }
+        out.extend_from_slice(b"~>");
+        out
+    }
+}
+
+/// Converts 5 base-85 digits into 4 bytes, or `None` if they're more than 2^32 - 1
+fn group_value(group: &[u8; 5]) -> Option<[u8; 4]> {
+    let val = group.iter().try_fold(0u32, |acc, digit| {
+        acc.checked_mul(85)?.checked_add(*digit as u32)
+    })?;
+    Some(val.to_be_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        assert_eq!(Ascii85.decode(b"87cURDZ~>"), Ok(b"Hello".to_vec()));
+        assert_eq!(Ascii85.decode(b"<~87cUR\nDZ~>"), Ok(b"Hello".to_vec()));
+        assert_eq!(Ascii85.decode(b"z~>"), Ok(vec![0; 4]));
+        assert_eq!(Ascii85.decode(b"zz!!~>"), Ok(vec![0; 9]));
+        assert_eq!(Ascii85.decode(b"s8W-!~>"), Ok(vec![0xff; 4]));
+        assert_eq!(Ascii85.decode(b"~>"), Ok(vec![]));
+        // The end-of-data marker is sometimes missing
+        assert_eq!(Ascii85.decode(b"87cURDZ"), Ok(b"Hello".to_vec()));
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(Ascii85.encode(b"Hello"), b"87cURDZ~>".to_vec());
+        assert_eq!(Ascii85.encode(&[0; 9]), b"zz!!~>".to_vec());
+        assert_eq!(Ascii85.encode(b""), b"~>".to_vec());
+
+        let data: Vec<u8> = (0..1000).map(|i| (i * 7 % 256) as u8).collect();
+        let encoded = Ascii85.encode(&data);
+        assert!(
+            encoded
+                .split(|c| *c == b'\n')
+                .all(|line| line.len() <= 75 + 2)
+        );
+        assert_eq!(Ascii85.decode(&encoded), Ok(data));
+    }
+
+    #[test]
+    fn invalid() {
+        for data in [&b"87cURD~>"[..], b"s8W-\"~>", b"!!z!!~>", b"87{~>"] {
+            assert_eq!(
+                Ascii85.decode(data),
+                Err(FilterError::InvalidData(b"ASCII85Decode".to_vec())),
+            );
+        }
+    }
+}
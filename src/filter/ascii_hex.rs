@@ -0,0 +1,90 @@
+//! The `ASCIIHexDecode` filter. See PDF Spec section 7.4.2
+
+use super::{Encoder, Filter, FilterError};
+use crate::lexer::is_whitespace;
+use crate::parser_helper::char_to_val;
+
+/// Decodes pairs of hex digits, ignoring whitespace, up to a `>` end-of-data marker
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsciiHex;
+
+impl Filter for AsciiHex {
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        let invalid = || FilterError::InvalidData(b"ASCIIHexDecode".to_vec());
+        let mut out = Vec::with_capacity(data.len() / 2);
+        let mut high = None;
+        for &c in data {
+            if c == b'>' {
+                break;
+            }
+            if is_whitespace(c) {
+                continue;
+            }
+            let val = char_to_val(c).ok_or_else(invalid)?;
+            match high.take() {
+                None => high = Some(val),
+                Some(high) => out.push(high * 16 + val),
+            }
+        }
+        // An odd number of digits behaves as if followed by a 0
+        if let Some(high) = high {
+            out.push(high * 16);
+        }
+        Ok(out)
+    }
+}
+
+// How many bytes to encode per line, to keep lines well under the recommended 255 characters
+static BYTES_PER_LINE: usize = 32;
+
+impl Encoder for AsciiHex {
+    fn name(&self) -> &'static [u8] {
+        b"ASCIIHexDecode"
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() * 2 + data.len() / BYTES_PER_LINE + 1);
+        for (i, byte) in data.iter().enumerate() {
+            if i > 0 && i % BYTES_PER_LINE == 0 {
+                out.push(b'\n');
+            }
+            out.extend_from_slice(format!("{byte:02X}").as_bytes());
+        }
+        out.push(b'>');
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        assert_eq!(AsciiHex.decode(b"48656c6C6F>"), Ok(b"Hello".to_vec()));
+        assert_eq!(AsciiHex.decode(b" 48 65\r\n6c >junk"), Ok(b"Hel".to_vec()));
+        assert_eq!(AsciiHex.decode(b"4>"), Ok(vec![0x40]));
+        // The end-of-data marker is sometimes missing
+        assert_eq!(AsciiHex.decode(b"4865"), Ok(b"He".to_vec()));
+        assert_eq!(AsciiHex.decode(b">"), Ok(vec![]));
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(AsciiHex.encode(b"Hello"), b"48656C6C6F>".to_vec());
+        assert_eq!(AsciiHex.encode(b""), b">".to_vec());
+
+        let data: Vec<u8> = (0..=255).collect();
+        let encoded = AsciiHex.encode(&data);
+        assert!(encoded.split(|c| *c == b'\n').all(|line| line.len() <= 65));
+        assert_eq!(AsciiHex.decode(&encoded), Ok(data));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            AsciiHex.decode(b"4G>"),
+            Err(FilterError::InvalidData(b"ASCIIHexDecode".to_vec()))
+        );
+    }
+}
@@ -0,0 +1,807 @@
+//! The `CCITTFaxDecode` filter, for bilevel images compressed with the Group 3 or Group 4 fax
+//! encodings of ITU-T T.4 and T.6. See PDF Spec section 7.4.6
+
+use super::{Filter, FilterError, MAX_DECODED_LEN, int_param};
+use crate::ast::object::Dictionary;
+
+/// The decode parameters of a CCITT fax stream. See PDF Spec section 7.4.6, table 11
+#[derive(Clone, Debug, PartialEq)]
+pub struct CcittParams {
+    /// The encoding: negative for Group 4, zero for Group 3 one-dimensional, and positive for
+    /// Group 3 mixed one- and two-dimensional
+    pub k: i64,
+    /// Whether end-of-line markers are required
+    pub end_of_line: bool,
+    /// Whether each encoded line starts on a byte boundary
+    pub encoded_byte_align: bool,
+    /// Pixels per row
+    pub columns: usize,
+    /// The number of rows, or 0 if not known in advance
+    pub rows: usize,
+    /// Whether the data is terminated by an end-of-block marker
+    pub end_of_block: bool,
+    /// Whether 1 bits are black rather than white
+    pub black_is_1: bool,
+    /// How many damaged rows to tolerate before giving up
+    pub damaged_rows_before_error: usize,
+}
+
+impl CcittParams {
+    pub fn new(params: Option<&Dictionary>) -> Result<Self, FilterError> {
+        let flag = |key: &[u8], default: bool| match params.and_then(|p| p.get(key)) {
+            None => Ok(default),
+            Some(val) => val
+                .as_bool()
+                .ok_or_else(|| FilterError::InvalidParameter(key.to_vec())),
+        };
+        let k = match params.and_then(|p| p.get(b"K")) {
+            None => 0,
+            Some(k) => k
+                .as_integer()
+                .ok_or_else(|| FilterError::InvalidParameter(b"K".to_vec()))?,
+        };
+        Ok(Self {
+            k,
+            end_of_line: flag(b"EndOfLine", false)?,
+            encoded_byte_align: flag(b"EncodedByteAlign", false)?,
+            columns: int_param(params, b"Columns", 1728, 1..=u16::MAX as usize)?,
+            rows: int_param(params, b"Rows", 0, 0..=u32::MAX as usize)?,
+            end_of_block: flag(b"EndOfBlock", true)?,
+            black_is_1: flag(b"BlackIs1", false)?,
+            damaged_rows_before_error: int_param(
+                params,
+                b"DamagedRowsBeforeError",
+                0,
+                0..=u32::MAX as usize,
+            )?,
+        })
+    }
+}
+
+/// Decodes CCITT fax data into a bitmap, with one bit per pixel and each row padded to a whole
+/// byte
+#[derive(Clone, Debug, PartialEq)]
+pub struct CcittFax {
+    pub params: CcittParams,
+}
+
+impl CcittFax {
+    pub fn new(params: Option<&Dictionary>) -> Result<Self, FilterError> {
+        Ok(Self {
+            params: CcittParams::new(params)?,
+        })
+    }
+
+    /// Decodes `data`, as long as the bitmap is at most `limit` bytes. Each row can take as little
+    /// as a bit to encode, so without a limit a few kilobytes could decode to gigabytes.
+    fn decode_limited(&self, data: &[u8], limit: usize) -> Result<Vec<u8>, FilterError> {
+        let mut decoder = Decoder {
+            params: &self.params,
+            limit,
+            reader: BitReader::new(data),
+            modes: CodeTable::new(MODE_CODES),
+            white: CodeTable::new(WHITE_CODES.iter().chain(EXTENDED_MAKEUP_CODES)),
+            black: CodeTable::new(BLACK_CODES.iter().chain(EXTENDED_MAKEUP_CODES)),
+        };
+        decoder.decode()
+    }
+}
+
+/// The pixel colours, which alternate between runs
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    fn flip(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+/// The ways a line can be coded relative to the line above it. See T.4 section 4.2.1.3
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Pass,
+    Horizontal,
+    /// The next change is this many pixels right of the one above it
+    Vertical(isize),
+}
+
+// The 2D mode codes, as (mode, code), from T.4 table 4
+static MODE_CODES: &[(Mode, &str)] = &[
+    (Mode::Pass, "0001"),
+    (Mode::Horizontal, "001"),
+    (Mode::Vertical(0), "1"),
+    (Mode::Vertical(1), "011"),
+    (Mode::Vertical(2), "000011"),
+    (Mode::Vertical(3), "0000011"),
+    (Mode::Vertical(-1), "010"),
+    (Mode::Vertical(-2), "000010"),
+    (Mode::Vertical(-3), "0000010"),
+];
+
+// The run length codes for white runs, as (run length, code), from T.4 tables 2 and 3
+static WHITE_CODES: &[(u16, &str)] = &[
+    (0, "00110101"),
+    (1, "000111"),
+    (2, "0111"),
+    (3, "1000"),
+    (4, "1011"),
+    (5, "1100"),
+    (6, "1110"),
+    (7, "1111"),
+    (8, "10011"),
+    (9, "10100"),
+    (10, "00111"),
+    (11, "01000"),
+    (12, "001000"),
+    (13, "000011"),
+    (14, "110100"),
+    (15, "110101"),
+    (16, "101010"),
+    (17, "101011"),
+    (18, "0100111"),
+    (19, "0001100"),
+    (20, "0001000"),
+    (21, "0010111"),
+    (22, "0000011"),
+    (23, "0000100"),
+    (24, "0101000"),
+    (25, "0101011"),
+    (26, "0010011"),
+    (27, "0100100"),
+    (28, "0011000"),
+    (29, "00000010"),
+    (30, "00000011"),
+    (31, "00011010"),
+    (32, "00011011"),
+    (33, "00010010"),
+    (34, "00010011"),
+    (35, "00010100"),
+    (36, "00010101"),
+    (37, "00010110"),
+    (38, "00010111"),
+    (39, "00101000"),
+    (40, "00101001"),
+    (41, "00101010"),
+    (42, "00101011"),
+    (43, "00101100"),
+    (44, "00101101"),
+    (45, "00000100"),
+    (46, "00000101"),
+    (47, "00001010"),
+    (48, "00001011"),
+    (49, "01010010"),
+    (50, "01010011"),
+    (51, "01010100"),
+    (52, "01010101"),
+    (53, "00100100"),
+    (54, "00100101"),
+    (55, "01011000"),
+    (56, "01011001"),
+    (57, "01011010"),
+    (58, "01011011"),
+    (59, "01001010"),
+    (60, "01001011"),
+    (61, "00110010"),
+    (62, "00110011"),
+    (63, "00110100"),
+    (64, "11011"),
+    (128, "10010"),
+    (192, "010111"),
+    (256, "0110111"),
+    (320, "00110110"),
+    (384, "00110111"),
+    (448, "01100100"),
+    (512, "01100101"),
+    (576, "01101000"),
+    (640, "01100111"),
+    (704, "011001100"),
+    (768, "011001101"),
+    (832, "011010010"),
+    (896, "011010011"),
+    (960, "011010100"),
+    (1024, "011010101"),
+    (1088, "011010110"),
+    (1152, "011010111"),
+    (1216, "011011000"),
+    (1280, "011011001"),
+    (1344, "011011010"),
+    (1408, "011011011"),
+    (1472, "010011000"),
+    (1536, "010011001"),
+    (1600, "010011010"),
+    (1664, "011000"),
+    (1728, "010011011"),
+];
+
+// The run length codes for black runs, as (run length, code), from T.4 tables 2 and 3
+static BLACK_CODES: &[(u16, &str)] = &[
+    (0, "0000110111"),
+    (1, "010"),
+    (2, "11"),
+    (3, "10"),
+    (4, "011"),
+    (5, "0011"),
+    (6, "0010"),
+    (7, "00011"),
+    (8, "000101"),
+    (9, "000100"),
+    (10, "0000100"),
+    (11, "0000101"),
+    (12, "0000111"),
+    (13, "00000100"),
+    (14, "00000111"),
+    (15, "000011000"),
+    (16, "0000010111"),
+    (17, "0000011000"),
+    (18, "0000001000"),
+    (19, "00001100111"),
+    (20, "00001101000"),
+    (21, "00001101100"),
+    (22, "00000110111"),
+    (23, "00000101000"),
+    (24, "00000010111"),
+    (25, "00000011000"),
+    (26, "000011001010"),
+    (27, "000011001011"),
+    (28, "000011001100"),
+    (29, "000011001101"),
+    (30, "000001101000"),
+    (31, "000001101001"),
+    (32, "000001101010"),
+    (33, "000001101011"),
+    (34, "000011010010"),
+    (35, "000011010011"),
+    (36, "000011010100"),
+    (37, "000011010101"),
+    (38, "000011010110"),
+    (39, "000011010111"),
+    (40, "000001101100"),
+    (41, "000001101101"),
+    (42, "000011011010"),
+    (43, "000011011011"),
+    (44, "000001010100"),
+    (45, "000001010101"),
+    (46, "000001010110"),
+    (47, "000001010111"),
+    (48, "000001100100"),
+    (49, "000001100101"),
+    (50, "000001010010"),
+    (51, "000001010011"),
+    (52, "000000100100"),
+    (53, "000000110111"),
+    (54, "000000111000"),
+    (55, "000000100111"),
+    (56, "000000101000"),
+    (57, "000001011000"),
+    (58, "000001011001"),
+    (59, "000000101011"),
+    (60, "000000101100"),
+    (61, "000001011010"),
+    (62, "000001100110"),
+    (63, "000001100111"),
+    (64, "0000001111"),
+    (128, "000011001000"),
+    (192, "000011001001"),
+    (256, "000001011011"),
+    (320, "000000110011"),
+    (384, "000000110100"),
+    (448, "000000110101"),
+    (512, "0000001101100"),
+    (576, "0000001101101"),
+    (640, "0000001001010"),
+    (704, "0000001001011"),
+    (768, "0000001001100"),
+    (832, "0000001001101"),
+    (896, "0000001110010"),
+    (960, "0000001110011"),
+    (1024, "0000001110100"),
+    (1088, "0000001110101"),
+    (1152, "0000001110110"),
+    (1216, "0000001110111"),
+    (1280, "0000001010010"),
+    (1344, "0000001010011"),
+    (1408, "0000001010100"),
+    (1472, "0000001010101"),
+    (1536, "0000001011010"),
+    (1600, "0000001011011"),
+    (1664, "0000001100100"),
+    (1728, "0000001100101"),
+];
+
+// The makeup codes for long runs of either colour, as (run length, code), from T.4 table 3a
+static EXTENDED_MAKEUP_CODES: &[(u16, &str)] = &[
+    (1792, "00000001000"),
+    (1856, "00000001100"),
+    (1920, "00000001101"),
+    (1984, "000000010010"),
+    (2048, "000000010011"),
+    (2112, "000000010100"),
+    (2176, "000000010101"),
+    (2240, "000000010110"),
+    (2304, "000000010111"),
+    (2368, "000000011100"),
+    (2432, "000000011101"),
+    (2496, "000000011110"),
+    (2560, "000000011111"),
+];
+
+/// The end-of-line code, `000000000001`
+const EOL: u32 = 1;
+const EOL_LEN: u32 = 12;
+
+/// The longest code in any of the tables
+const MAX_CODE_LEN: u32 = 13;
+
+/// A table for decoding codes of at most `MAX_CODE_LEN` bits, indexed by the next
+/// `MAX_CODE_LEN` bits of input. Each entry is the code's length and value, or `None` if no code
+/// starts with those bits.
+struct CodeTable<T>(Vec<Option<(u32, T)>>);
+
+impl<T: Copy> CodeTable<T> {
+    fn new<'a>(codes: impl IntoIterator<Item = &'a (T, &'a str)>) -> Self
+    where
+        T: 'a,
+    {
+        let mut table = vec![None; 1 << MAX_CODE_LEN];
+        for (val, code) in codes {
+            let len = code.len() as u32;
+            let bits = code
+                .bytes()
+                .fold(0usize, |acc, bit| (acc << 1) | (bit - b'0') as usize);
+            let shift = MAX_CODE_LEN - len;
+            for entry in &mut table[bits << shift..(bits + 1) << shift] {
+                *entry = Some((len, *val));
+            }
+        }
+        Self(table)
+    }
+
+    fn read(&self, reader: &mut BitReader) -> Option<T> {
+        let (len, val) = self.0[reader.peek(MAX_CODE_LEN) as usize]?;
+        reader.consume(len);
+        Some(val)
+    }
+}
+
+struct Decoder<'a> {
+    params: &'a CcittParams,
+    /// The most bytes of bitmap we'll produce
+    limit: usize,
+    reader: BitReader<'a>,
+    modes: CodeTable<Mode>,
+    white: CodeTable<u16>,
+    black: CodeTable<u16>,
+}
+
+impl Filter for CcittFax {
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        self.decode_limited(data, MAX_DECODED_LEN)
+    }
+}
+
+impl Decoder<'_> {
+    fn decode(&mut self) -> Result<Vec<u8>, FilterError> {
+        let columns = self.params.columns;
+        let row_len = columns.div_ceil(8);
+        let mut out = Vec::new();
+        // The changing elements (pixels that differ from the one before them) of the previous
+        // row, starting with a white to black change. The line above the first is all white.
+        let mut reference = Vec::new();
+        let mut damaged = 0;
+
+        while self.params.rows == 0 || out.len() < self.params.rows * row_len {
+            if self.params.encoded_byte_align {
+                self.reader.align();
+            }
+            let two_dimensional = match self.params.k {
+                k if k < 0 => {
+                    // Group 4 data ends with an end-of-facsimile-block, which is two EOLs
+                    if self.reader.peek(EOL_LEN) == EOL {
+                        break;
+                    }
+                    true
+                }
+                k => {
+                    if self.read_eol() {
+                        // Group 3 data ends with a return-to-control, which is six EOLs in a row
+                        let tag_len = (k > 0) as u32;
+                        if self.reader.peek(EOL_LEN + tag_len) >> tag_len == EOL {
+                            break;
+                        }
+                    }
+                    // In mixed mode, a bit before each line says how it's coded
+                    k > 0 && self.reader.read(1) == 0
+                }
+            };
+            if self.reader.at_end() {
+                break;
+            }
+            if out.len() + row_len > self.limit {
+                return Err(FilterError::TooLarge(b"CCITTFaxDecode".to_vec()));
+            }
+
+            let changes = if two_dimensional {
+                self.decode_2d(&reference)
+            } else {
+                self.decode_1d()
+            };
+            let changes = match changes {
+                Some(changes) => changes,
+                None if out.is_empty() => {
+                    return Err(FilterError::InvalidData(b"CCITTFaxDecode".to_vec()));
+                }
+                // Corrupt data after some good rows. Up to `/DamagedRowsBeforeError` rows are
+                // replaced with the previous one, resuming at the next EOL, and after that we keep
+                // as much of the image as we have.
+                None if damaged < self.params.damaged_rows_before_error && self.skip_to_eol() => {
+                    damaged += 1;
+                    out.extend_from_within(out.len() - row_len..);
+                    continue;
+                }
+                None => break,
+            };
+            out.extend(self.render(&changes, row_len));
+            reference = changes;
+        }
+        Ok(out)
+    }
+
+    /// Skips any fill bits and an EOL, if there is one next, returning whether there was
+    fn read_eol(&mut self) -> bool {
+        // Fill is zeros before the EOL, but we can't skip zeros that begin the next line's
+        // codes, so only skip them if they lead up to an EOL
+        let mut skip = 0;
+        while self.reader.peek_at(skip, EOL_LEN) == 0 && !self.reader.at_end_at(skip) {
+            skip += 1;
+        }
+        if self.reader.peek_at(skip, EOL_LEN) == EOL {
+            self.reader.consume(skip + EOL_LEN);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skips to just after the next EOL, returning false if there isn't one
+    fn skip_to_eol(&mut self) -> bool {
+        while !self.reader.at_end() {
+            if self.reader.peek(EOL_LEN) == EOL {
+                self.reader.consume(EOL_LEN);
+                if self.params.k > 0 {
+                    self.reader.read(1);
+                }
+                return true;
+            }
+            self.reader.consume(1);
+        }
+        false
+    }
+
+    /// Reads a run length of the given colour: any number of makeup codes, then a terminating
+    /// code
+    fn read_run(&mut self, color: Color) -> Option<usize> {
+        let table = match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        };
+        let mut total = 0;
+        loop {
+            let run = table.read(&mut self.reader)? as usize;
+            total += run;
+            if run < 64 {
+                return Some(total);
+            }
+        }
+    }
+
+    /// Decodes a one-dimensional (Modified Huffman) line, returning its changing elements
+    fn decode_1d(&mut self) -> Option<Vec<usize>> {
+        let columns = self.params.columns;
+        let mut changes = Vec::new();
+        let mut pos = 0;
+        let mut color = Color::White;
+        while pos < columns {
+            pos = (pos + self.read_run(color)?).min(columns);
+            changes.push(pos);
+            color = color.flip();
+        }
+        Some(changes)
+    }
+
+    /// Decodes a two-dimensional (Modified READ) line, coded relative to the changing elements
+    /// of the line above, returning its changing elements
+    fn decode_2d(&mut self, reference: &[usize]) -> Option<Vec<usize>> {
+        let columns = self.params.columns;
+        let mut changes: Vec<usize> = Vec::new();
+        // The position before which the line has been decoded. It starts on an imaginary white
+        // pixel before the line.
+        let mut a0: Option<usize> = None;
+        let mut color = Color::White;
+        // Where to start looking for b1. Since a0 only moves right, changes before the last b1
+        // can't be the next one, except for the one just before it, which has the other colour.
+        let mut search = 0;
+
+        while a0.is_none_or(|a0| a0 < columns) {
+            // b1 is the first change on the line above after a0 to the opposite colour of a0, and
+            // b2 is the change after that. Changes to black have even indices.
+            let parity = match color {
+                Color::White => 0,
+                Color::Black => 1,
+            };
+            let b1_index = (search..reference.len())
+                .find(|&i| i % 2 == parity && a0.is_none_or(|a0| reference[i] > a0))
+                .unwrap_or(reference.len());
+            search = b1_index.saturating_sub(1);
+            let b1 = reference.get(b1_index).copied().unwrap_or(columns);
+            let b2 = reference.get(b1_index + 1).copied().unwrap_or(columns);
+
+            match self.modes.read(&mut self.reader)? {
+                Mode::Pass => a0 = Some(b2),
+                Mode::Horizontal => {
+                    let start = a0.unwrap_or(0);
+                    let a1 = (start + self.read_run(color)?).min(columns);
+                    let a2 = (a1 + self.read_run(color.flip())?).min(columns);
+                    changes.push(a1);
+                    changes.push(a2);
+                    a0 = Some(a2);
+                }
+                Mode::Vertical(offset) => {
+                    let a1 = b1.checked_add_signed(offset)?;
+                    if a1 > columns || a0.is_some_and(|a0| a1 < a0) {
+                        return None;
+                    }
+                    changes.push(a1);
+                    a0 = Some(a1);
+                    color = color.flip();
+                }
+            }
+        }
+        Some(changes)
+    }
+
+    /// Turns a line's changing elements into a row of the bitmap
+    fn render(&self, changes: &[usize], row_len: usize) -> Vec<u8> {
+        let (white, black) = if self.params.black_is_1 {
+            (0x00, 0xff)
+        } else {
+            (0xff, 0x00)
+        };
+        let mut row = vec![white; row_len];
+        for run in changes.chunks(2) {
+            let start = run[0];
+            let end = run.get(1).copied().unwrap_or(self.params.columns);
+            for pixel in start..end {
+                let mask = 0x80 >> (pixel % 8);
+                row[pixel / 8] = (row[pixel / 8] & !mask) | (black & mask);
+            }
+        }
+        row
+    }
+}
+
+/// Reads most-significant-bit-first codes, padding the input with zeros
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// Position in bits
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.at_end_at(0)
+    }
+
+    fn at_end_at(&self, offset: u32) -> bool {
+        self.pos + offset as usize >= self.data.len() * 8
+    }
+
+    fn peek(&self, len: u32) -> u32 {
+        self.peek_at(0, len)
+    }
+
+    /// Returns the `len` bits starting `offset` bits from here
+    fn peek_at(&self, offset: u32, len: u32) -> u32 {
+        let mut val = 0;
+        for i in 0..len as usize {
+            let pos = self.pos + offset as usize + i;
+            let bit = self
+                .data
+                .get(pos / 8)
+                .map_or(0, |byte| (byte >> (7 - pos % 8)) & 1);
+            val = (val << 1) | bit as u32;
+        }
+        val
+    }
+
+    fn consume(&mut self, len: u32) {
+        self.pos += len as usize;
+    }
+
+    fn read(&mut self, len: u32) -> u32 {
+        let val = self.peek(len);
+        self.consume(len);
+        val
+    }
+
+    /// Skips to the next byte boundary
+    fn align(&mut self) {
+        self.pos = self.pos.next_multiple_of(8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::object::{Name, Number, Object};
+
+    fn ccitt(params: &[(&[u8], Object)]) -> CcittFax {
+        let params = params
+            .iter()
+            .map(|(k, v)| (Name::from(*k), v.clone()))
+            .collect();
+        CcittFax::new(Some(&params)).unwrap()
+    }
+
+    fn int(val: i64) -> Object {
+        Object::Number(Number::Integer(val))
+    }
+
+    /// Packs a string of `0`s and `1`s into bytes, padding the last one with zeros. Spaces are
+    /// ignored, to separate codes.
+    fn bits(bits: &str) -> Vec<u8> {
+        let bits: Vec<u8> = bits.bytes().filter(|b| *b != b' ').collect();
+        bits.chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, bit)| acc | ((bit - b'0') << (7 - i)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn group_4() {
+        // Produced by an independent encoder. The image is 2600 pixels wide, so it needs every
+        // kind of run length code.
+        let data = [
+            0x27, 0x08, 0x40, 0x7c, 0xe1, 0x01, 0x90, 0xec, 0x89, 0x0a, 0x1c, 0xa1, 0xca, 0x1c,
+            0xa1, 0xca, 0x1c, 0xa1, 0xca, 0x1c, 0xa1, 0xca, 0x1c, 0xa1, 0xca, 0x1c, 0xa1, 0xca,
+            0x1c, 0xa1, 0xca, 0x1c, 0xa1, 0xca, 0x1c, 0xa1, 0xca, 0x1c, 0xa0, 0x13, 0x88, 0x32,
+            0x0e, 0x03, 0xe6, 0xb0, 0x64, 0x17, 0xd8, 0xe4, 0x05, 0x40, 0xbb, 0x71, 0x38, 0x32,
+            0x04, 0x00, 0xf1, 0x2c, 0x19, 0x0d, 0x0c, 0x9e, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e,
+            0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e, 0x50, 0xe5,
+            0x0e, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x00, 0x9c, 0x41, 0x90, 0x6e,
+            0x03, 0xc5, 0xb0, 0x64, 0x0b, 0x80, 0xf1, 0x44, 0x19, 0x0c, 0xa0, 0x1e, 0x13, 0x83,
+            0x21, 0x98, 0x6e, 0xa8, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e, 0x50,
+            0xe5, 0x0e, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x0e,
+            0x50, 0xe5, 0x0e, 0x50, 0xe5, 0x00, 0x9c, 0x41, 0x90, 0x68, 0x01, 0xe5, 0x10, 0x64,
+            0x1a, 0x80, 0x78, 0x64, 0x19, 0x0d, 0x20, 0x1e, 0xd2, 0x0c, 0x86, 0xa1, 0xb8, 0xd1,
+            0x43, 0x94, 0x39, 0x43, 0x94, 0x39, 0x43, 0x94, 0x39, 0x43, 0x94, 0x39, 0x43, 0x94,
+            0x39, 0x43, 0x94, 0x39, 0x43, 0x94, 0x39, 0x43, 0x94, 0x39, 0x43, 0x94, 0x39, 0x43,
+            0x94, 0x02, 0x71, 0x06, 0x43, 0x58, 0x07, 0xae, 0x0c, 0x83, 0x60, 0x0e, 0x9a, 0x40,
+            0x04, 0x00, 0x40,
+        ];
+        let black = |x: usize, y: usize| {
+            (y == 3 && (100..2300).contains(&x))
+                || (10 + 3 * y..20 + 5 * y).contains(&x)
+                || (y % 4 == 1 && (500..540).contains(&x) && x.is_multiple_of(2))
+                || x >= 2590
+        };
+        let (width, height) = (2600, 16);
+        let mut expected = vec![0xff; width / 8 * height];
+        for y in 0..height {
+            for x in 0..width {
+                if black(x, y) {
+                    expected[y * width / 8 + x / 8] &= !(0x80 >> (x % 8));
+                }
+            }
+        }
+
+        let filter = ccitt(&[(b"K", int(-1)), (b"Columns", int(width as i64))]);
+        assert_eq!(filter.decode(&data), Ok(expected.clone()));
+
+        // Stopping after the given number of rows
+        let filter = ccitt(&[
+            (b"K", int(-1)),
+            (b"Columns", int(width as i64)),
+            (b"Rows", int(5)),
+        ]);
+        assert_eq!(filter.decode(&data), Ok(expected[..width / 8 * 5].to_vec()));
+    }
+
+    #[test]
+    fn group_3_1d() {
+        // An EOL before each row, then six to finish
+        let eol = "000000000001 ";
+        let data = bits(
+            &[
+                eol,
+                "10011 ", // 8 white
+                eol,
+                "0111 011 0111 ", // 2 white, 4 black, 2 white
+                eol,
+                "00110101 000101 ", // 0 white, 8 black
+                &eol.repeat(6),
+            ]
+            .concat(),
+        );
+        let filter = ccitt(&[
+            (b"Columns", int(8)),
+            (b"EndOfLine", Object::Boolean(true)),
+            (b"BlackIs1", Object::Boolean(true)),
+        ]);
+        assert_eq!(filter.decode(&data), Ok(vec![0x00, 0x3c, 0xff]));
+
+        // Without EOLs, and with each row starting on a byte boundary
+        let data = bits("10011000 01110110 11100000");
+        let filter = ccitt(&[
+            (b"Columns", int(8)),
+            (b"EncodedByteAlign", Object::Boolean(true)),
+        ]);
+        assert_eq!(filter.decode(&data), Ok(vec![0xff, 0xc3]));
+    }
+
+    #[test]
+    fn group_3_2d() {
+        // A tag bit after each EOL says whether the row is 1D (1) or 2D (0)
+        let eol = "000000000001 ";
+        let data = bits(
+            &[
+                eol,
+                "1 0111 011 0111 ", // 2 white, 4 black, 2 white
+                eol,
+                "0 011 011 1 ", // The same, each edge 1 pixel right
+                eol,
+                "0 0001 1 ", // Pass over the black run, then to the end
+                &format!("{eol}1 ").repeat(6),
+            ]
+            .concat(),
+        );
+        let filter = ccitt(&[(b"K", int(2)), (b"Columns", int(8))]);
+        assert_eq!(filter.decode(&data), Ok(vec![0xc3, 0xe1, 0xff]));
+    }
+
+    #[test]
+    fn too_large() {
+        // Every bit is a row the same as the one above, so each byte decodes to 8 rows of 8 KiB
+        let filter = ccitt(&[(b"K", int(-1)), (b"Columns", int(65535))]);
+        let rows = filter.decode_limited(&[0xff], 8 * 8192).unwrap();
+        assert_eq!(rows.len(), 8 * 8192);
+        assert_eq!(
+            filter.decode_limited(&[0xff], 8 * 8192 - 1),
+            Err(FilterError::TooLarge(b"CCITTFaxDecode".to_vec()))
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        // An uncompressed mode extension, which isn't supported
+        let filter = ccitt(&[(b"K", int(-1)), (b"Columns", int(8))]);
+        assert_eq!(
+            filter.decode(&bits("0000001111")),
+            Err(FilterError::InvalidData(b"CCITTFaxDecode".to_vec()))
+        );
+        // Rows decoded before the damage are kept
+        assert_eq!(filter.decode(&bits("1 1 0000001111")), Ok(vec![0xff, 0xff]));
+
+        assert_eq!(
+            CcittFax::new(Some(&Dictionary::from_iter([(
+                Name::from(b"Columns"),
+                int(0)
+            )]))),
+            Err(FilterError::InvalidParameter(b"Columns".to_vec()))
+        );
+    }
+}
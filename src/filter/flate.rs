@@ -0,0 +1,184 @@
+//! The `FlateDecode` filter. See PDF Spec section 7.4.4
+
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+use super::{Encoder, Filter, FilterError, MAX_DECODED_LEN, Predictor};
+use crate::ast::object::Dictionary;
+
+// The zlib compression level used by default, which balances speed and size
+static DEFAULT_LEVEL: u32 = 6;
+
+/// Decompresses zlib/deflate data, then undoes any predictor applied before compression
+#[derive(Clone, Debug, PartialEq)]
+pub struct Flate {
+    /// The compression level when encoding, from 0 (none) to 9 (smallest)
+    pub level: u32,
+    pub predictor: Option<Predictor>,
+}
+
+impl Flate {
+    pub fn new(params: Option<&Dictionary>) -> Result<Self, FilterError> {
+        Ok(Self {
+            level: DEFAULT_LEVEL,
+            predictor: Predictor::new(params)?,
+        })
+    }
+}
+
+impl Default for Flate {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_LEVEL,
+            predictor: None,
+        }
+    }
+}
+
+impl Flate {
+    /// Decodes `data`, as long as it decompresses to at most `limit` bytes
+    fn decode_limited(&self, data: &[u8], limit: usize) -> Result<Vec<u8>, FilterError> {
+        let mut out = Vec::new();
+        // Reading one byte past the limit tells us whether there was more
+        let read = ZlibDecoder::new(data)
+            .take(limit as u64 + 1)
+            .read_to_end(&mut out);
+        // Plenty of files have streams that are truncated or have a bad checksum. As long as we
+        // got something out of them, use that.
+        if read.is_err() && out.is_empty() {
+            return Err(FilterError::InvalidData(b"FlateDecode".to_vec()));
+        }
+        if out.len() > limit {
+            return Err(FilterError::TooLarge(b"FlateDecode".to_vec()));
+        }
+        match &self.predictor {
+            Some(predictor) => predictor.decode(&out),
+            None => Ok(out),
+        }
+    }
+}
+
+impl Filter for Flate {
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        self.decode_limited(data, MAX_DECODED_LEN)
+    }
+}
+
+impl Encoder for Flate {
+    fn name(&self) -> &'static [u8] {
+        b"FlateDecode"
+    }
+
+    fn params(&self) -> Option<Dictionary> {
+        self.predictor.as_ref().map(Predictor::params)
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let predicted;
+        let data = match &self.predictor {
+            Some(predictor) => {
+                predicted = predictor.encode(data);
+                &predicted
+            }
+            None => data,
+        };
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(self.level.min(9)));
+        // Writing to a `Vec` can't fail
+        let _ = encoder.write_all(data);
+        encoder.finish().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::tests::zlib;
+    use crate::filter::{PngFilter, PredictorKind};
+
+    #[test]
+    fn decode() {
+        let flate = Flate::new(None).unwrap();
+        assert_eq!(flate.decode(&zlib(b"hello")), Ok(b"hello".to_vec()));
+        assert_eq!(flate.decode(&zlib(b"")), Ok(vec![]));
+    }
+
+    #[test]
+    fn encode() {
+        let data: Vec<u8> = (0..10000).map(|i| (i % 100) as u8).collect();
+        let flate = Flate::default();
+        assert_eq!(flate.params(), None);
+        let encoded = flate.encode(&data);
+        assert!(encoded.len() < data.len() / 10);
+        assert_eq!(flate.decode(&encoded), Ok(data.clone()));
+
+        let stored = Flate {
+            level: 0,
+            predictor: None,
+        };
+        assert!(stored.encode(&data).len() > data.len());
+        assert_eq!(stored.decode(&stored.encode(&data)), Ok(data));
+    }
+
+    #[test]
+    fn encode_predictor() {
+        // An RGB gradient, which predictors are good at
+        let data: Vec<u8> = (0..64 * 64)
+            .flat_map(|i| [(i % 64) as u8, (i / 64) as u8, 128])
+            .collect();
+        for kind in [
+            PredictorKind::Tiff,
+            PredictorKind::Png { filter: None },
+            PredictorKind::Png {
+                filter: Some(PngFilter::Paeth),
+            },
+        ] {
+            let flate = Flate {
+                level: 9,
+                predictor: Some(Predictor {
+                    kind,
+                    colors: 3,
+                    bits_per_component: 8,
+                    columns: 64,
+                }),
+            };
+            let encoded = flate.encode(&data);
+            // Decoding with the parameters we'd write
+            let decoder = Flate::new(flate.params().as_ref()).unwrap();
+            assert_eq!(decoder.predictor, flate.predictor);
+            assert_eq!(decoder.decode(&encoded), Ok(data.clone()));
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let data = vec![b'x'; 1000];
+        let compressed = zlib(&data);
+        // Missing the checksum
+        let flate = Flate::new(None).unwrap();
+        assert_eq!(flate.decode(&compressed[..compressed.len() - 4]), Ok(data));
+    }
+
+    #[test]
+    fn too_large() {
+        // A kilobyte of zeros compresses to a few bytes, which is how decompression bombs work
+        let compressed = zlib(&[0; 1024]);
+        let flate = Flate::new(None).unwrap();
+        assert_eq!(flate.decode_limited(&compressed, 1024), Ok(vec![0; 1024]));
+        assert_eq!(
+            flate.decode_limited(&compressed, 1023),
+            Err(FilterError::TooLarge(b"FlateDecode".to_vec()))
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let flate = Flate::new(None).unwrap();
+        assert_eq!(
+            flate.decode(b"not zlib"),
+            Err(FilterError::InvalidData(b"FlateDecode".to_vec()))
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Image compression filters, whose data is usually handed to an image codec as it is rather than
+//! decoded here. See PDF Spec sections 7.4.6 to 7.4.9
+
+use super::{CcittParams, FilterError, int_param, new_filter, stream_filters};
+use crate::ast::object::{Dictionary, Reference, Stream};
+
+/// An image filter, with the parameters needed to decode its data
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImageFilter {
+    /// `DCTDecode`: baseline or progressive JPEG
+    Dct {
+        /// `/ColorTransform`: whether 3- and 4-component data is stored as YUV(K) and should be
+        /// converted to RGB(K), or `None` to follow the JPEG's Adobe marker
+        color_transform: Option<bool>,
+    },
+    /// `JPXDecode`: JPEG 2000, which carries all its parameters itself
+    Jpx,
+    /// `JBIG2Decode`: a JBIG2 embedded stream
+    Jbig2 {
+        /// `/JBIG2Globals`: the stream holding segments shared between pages, which must be
+        /// decoded before the image's own segments
+        globals: Option<Reference>,
+    },
+    /// `CCITTFaxDecode`: Group 3 or Group 4 fax data
+    CcittFax(CcittParams),
+}
+
+impl ImageFilter {
+    /// Returns the image filter named `name`, configured by its decode parameters `params`, or
+    /// `None` if `name` isn't an image filter
+    pub fn new(name: &[u8], params: Option<&Dictionary>) -> Result<Option<Self>, FilterError> {
+        Ok(Some(match name {
+            b"DCTDecode" | b"DCT" => ImageFilter::Dct {
+                color_transform: params
+                    .and_then(|params| params.get(b"ColorTransform"))
+                    .map(|_| int_param(params, b"ColorTransform", 0, 0..=1).map(|val| val == 1))
+                    .transpose()?,
+            },
+            b"JPXDecode" => ImageFilter::Jpx,
+            b"JBIG2Decode" => {
+                ImageFilter::Jbig2 {
+                    globals: match params.and_then(|params| params.get(b"JBIG2Globals")) {
+                        None => None,
+                        Some(globals) => Some(globals.as_reference().ok_or_else(|| {
+                            FilterError::InvalidParameter(b"JBIG2Globals".to_vec())
+                        })?),
+                    },
+                }
+            }
+            b"CCITTFaxDecode" | b"CCF" => ImageFilter::CcittFax(CcittParams::new(params)?),
+            _ => return Ok(None),
+        }))
+    }
+
+    /// The filter's name, as used in `/Filter`
+    pub fn name(&self) -> &'static [u8] {
+        match self {
+            ImageFilter::Dct { .. } => b"DCTDecode",
+            ImageFilter::Jpx => b"JPXDecode",
+            ImageFilter::Jbig2 { .. } => b"JBIG2Decode",
+            ImageFilter::CcittFax(_) => b"CCITTFaxDecode",
+        }
+    }
+}
+
+/// Image data as stored in a stream, with any general-purpose filters undone
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncodedImage {
+    /// The image data, still encoded with `filter`. For DCT and JPX, this is a complete JPEG or
+    /// JPEG 2000 file.
+    pub data: Vec<u8>,
+    /// The image filter the data is encoded with, or `None` if the data is raw samples
+    pub filter: Option<ImageFilter>,
+}
+
+/// Returns the data of the image stream `stream`, applying every filter in its `/Filter` entry
+/// except a final image filter, whose data is returned unchanged along with its parameters
+pub fn decode_image_stream(stream: &Stream) -> Result<EncodedImage, FilterError> {
+    let filters = stream_filters(&stream.dict)?;
+    let mut data = stream.data.clone();
+    for (i, (name, params)) in filters.iter().enumerate() {
+        if let Some(filter) = ImageFilter::new(name.as_bytes(), *params)? {
+            // Nothing can be applied on top of an image codec
+            if i + 1 < filters.len() {
+                return Err(FilterError::UnsupportedFilter(
+                    filters[i + 1].0.as_bytes().to_vec(),
+                ));
+            }
+            return Ok(EncodedImage {
+                data,
+                filter: Some(filter),
+            });
+        }
+        data = new_filter(name.as_bytes(), *params)?.decode(&data)?;
+    }
+    Ok(EncodedImage { data, filter: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::object::{Name, Number, Object};
+    use crate::filter::tests::zlib;
+
+    fn name(name: &[u8]) -> Object {
+        Object::Name(Name::from(name))
+    }
+
+    #[test]
+    fn passthrough() {
+        let jpeg = b"\xff\xd8\xff\xe0 not really a JPEG \xff\xd9".to_vec();
+        let dict = Dictionary::from_iter([
+            (Name::from(b"Filter"), name(b"DCTDecode")),
+            (
+                Name::from(b"DecodeParms"),
+                Object::Dictionary(Dictionary::from_iter([(
+                    Name::from(b"ColorTransform"),
+                    Object::Number(Number::Integer(0)),
+                )])),
+            ),
+        ]);
+        let stream = Stream {
+            dict,
+            data: jpeg.clone(),
+        };
+        assert_eq!(
+            decode_image_stream(&stream),
+            Ok(EncodedImage {
+                data: jpeg.clone(),
+                filter: Some(ImageFilter::Dct {
+                    color_transform: Some(false)
+                }),
+            })
+        );
+        assert_eq!(
+            crate::filter::decode_stream(&stream),
+            Err(FilterError::UnsupportedFilter(b"DCTDecode".to_vec()))
+        );
+
+        // General-purpose filters before the image filter are undone
+        let stream = Stream {
+            dict: Dictionary::from_iter([(
+                Name::from(b"Filter"),
+                Object::Array(vec![name(b"FlateDecode"), name(b"JPXDecode")]),
+            )]),
+            data: zlib(&jpeg),
+        };
+        assert_eq!(
+            decode_image_stream(&stream),
+            Ok(EncodedImage {
+                data: jpeg,
+                filter: Some(ImageFilter::Jpx),
+            })
+        );
+
+        // Unfiltered samples
+        let stream = Stream {
+            dict: Dictionary::new(),
+            data: vec![1, 2, 3],
+        };
+        assert_eq!(
+            decode_image_stream(&stream),
+            Ok(EncodedImage {
+                data: vec![1, 2, 3],
+                filter: None,
+            })
+        );
+    }
+
+    #[test]
+    fn params() {
+        let globals = Reference {
+            number: 7,
+            generation: 0,
+        };
+        let params =
+            Dictionary::from_iter([(Name::from(b"JBIG2Globals"), Object::Reference(globals))]);
+        assert_eq!(
+            ImageFilter::new(b"JBIG2Decode", Some(&params)),
+            Ok(Some(ImageFilter::Jbig2 {
+                globals: Some(globals)
+            }))
+        );
+        assert_eq!(
+            ImageFilter::new(b"JBIG2Decode", None),
+            Ok(Some(ImageFilter::Jbig2 { globals: None }))
+        );
+        assert_eq!(
+            ImageFilter::new(b"DCT", None),
+            Ok(Some(ImageFilter::Dct {
+                color_transform: None
+            }))
+        );
+
+        let params = Dictionary::from_iter([
+            (Name::from(b"K"), Object::Number(Number::Integer(-1))),
+            (
+                Name::from(b"Columns"),
+                Object::Number(Number::Integer(2480)),
+            ),
+            (Name::from(b"BlackIs1"), Object::Boolean(true)),
+        ]);
+        assert_eq!(
+            ImageFilter::new(b"CCITTFaxDecode", Some(&params)),
+            Ok(Some(ImageFilter::CcittFax(CcittParams {
+                k: -1,
+                end_of_line: false,
+                encoded_byte_align: false,
+                columns: 2480,
+                rows: 0,
+                end_of_block: true,
+                black_is_1: true,
+                damaged_rows_before_error: 0,
+            })))
+        );
+
+        assert_eq!(ImageFilter::new(b"FlateDecode", None), Ok(None));
+        assert_eq!(
+            ImageFilter::new(
+                b"JBIG2Decode",
+                Some(&Dictionary::from_iter([(
+                    Name::from(b"JBIG2Globals"),
+                    Object::Number(Number::Integer(7)),
+                )]))
+            ),
+            Err(FilterError::InvalidParameter(b"JBIG2Globals".to_vec()))
+        );
+    }
+
+    #[test]
+    fn image_filter_not_last() {
+        let stream = Stream {
+            dict: Dictionary::from_iter([(
+                Name::from(b"Filter"),
+                Object::Array(vec![name(b"DCTDecode"), name(b"FlateDecode")]),
+            )]),
+            data: vec![],
+        };
+        assert_eq!(
+            decode_image_stream(&stream),
+            Err(FilterError::UnsupportedFilter(b"FlateDecode".to_vec()))
+        );
+    }
+}
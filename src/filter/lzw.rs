@@ -0,0 +1,379 @@
+//! The `LZWDecode` filter. See PDF Spec section 7.4.4
+
+use std::collections::HashMap;
+
+use super::{Encoder, Filter, FilterError, Predictor, int_param};
+use crate::ast::object::{Dictionary, Name, Number, Object};
+
+/// Clears the table and resets the code length to 9 bits
+const CLEAR_TABLE: u16 = 256;
+const END_OF_DATA: u16 = 257;
+/// The first code that's added to the table, rather than standing for a single byte
+const FIRST_CODE: u16 = 258;
+/// Codes are at most 12 bits long
+const MAX_CODES: usize = 4096;
+
+/// Decompresses Lempel-Ziv-Welch data with variable-length codes of 9 to 12 bits, then undoes any
+/// predictor applied before compression
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lzw {
+    /// Whether the code length increases one code early, as most encoders do (`/EarlyChange 1`,
+    /// the default)
+    pub early_change: bool,
+    pub predictor: Option<Predictor>,
+}
+
+impl Default for Lzw {
+    fn default() -> Self {
+        Self {
+            early_change: true,
+            predictor: None,
+        }
+    }
+}
+
+impl Lzw {
+    pub fn new(params: Option<&Dictionary>) -> Result<Self, FilterError> {
+        Ok(Self {
+            early_change: int_param(params, b"EarlyChange", 1, 0..=1)? == 1,
+            predictor: Predictor::new(params)?,
+        })
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        let invalid = || FilterError::InvalidData(b"LZWDecode".to_vec());
+        let mut reader = BitReader::new(data);
+        let mut out = Vec::with_capacity(data.len() * 2);
+        // Each entry past `FIRST_CODE` is an earlier entry followed by one more byte. For codes
+        // below it, the entries are unused.
+        let mut table: Vec<(u16, u8)> = Vec::with_capacity(MAX_CODES);
+        table.resize(FIRST_CODE as usize, (0, 0));
+        let mut code_len = 9;
+        let mut prev: Option<u16> = None;
+
+        // A truncated stream is decoded as far as it goes
+        while let Some(code) = reader.read(code_len) {
+            match code {
+                CLEAR_TABLE => {
+                    table.truncate(FIRST_CODE as usize);
+                    code_len = 9;
+                    prev = None;
+                    continue;
+                }
+                END_OF_DATA => break,
+                _ => {}
+            }
+
+            let start = out.len();
+            match prev {
+                None if code < CLEAR_TABLE => out.push(code as u8),
+                None => return Err(invalid()),
+                Some(prev) => {
+                    // The code may be the one we're about to add, which is `prev` followed by its
+                    // own first byte
+                    let first = if (code as usize) < table.len() {
+                        write_entry(&table, code, &mut out);
+                        out[start]
+                    } else if code as usize == table.len() {
+                        write_entry(&table, prev, &mut out);
+                        let first = out[start];
+                        out.push(first);
+                        first
+                    } else {
+                        return Err(invalid());
+                    };
+                    if table.len() < MAX_CODES {
+                        table.push((prev, first));
+                    }
+                }
+            }
+            prev = Some(code);
+
+            code_len = self.code_len(table.len(), code_len);
+        }
+        Ok(out)
+    }
+
+    /// The length of the next code, once the decoder's table has `table_len` entries
+    fn code_len(&self, table_len: usize, code_len: u32) -> u32 {
+        let next_code = table_len + self.early_change as usize;
+        if next_code >= 1 << code_len && code_len < 12 {
+            code_len + 1
+        } else {
+            code_len
+        }
+    }
+
+    fn compress(&self, data: &[u8]) -> Vec<u8> {
+        let mut writer = BitWriter::default();
+        let mut code_len = 9;
+        writer.write(CLEAR_TABLE, code_len);
+
+        // Maps an entry followed by a byte to the code for that string
+        let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+        let mut next_code = FIRST_CODE;
+        // How many entries the decoder's table will have. It adds each entry one code after we
+        // do, since it needs the next code to know the entry's last byte.
+        let mut decoder_len = FIRST_CODE as usize;
+        let mut first = true;
+
+        let mut it = data.iter();
+        let Some(&byte) = it.next() else {
+            writer.write(END_OF_DATA, code_len);
+            return writer.finish();
+        };
+        let mut current = byte as u16;
+        for &byte in it {
+            if let Some(&code) = table.get(&(current, byte)) {
+                current = code;
+                continue;
+            }
+
+            writer.write(current, code_len);
+            if !first {
+                decoder_len += 1;
+            }
+            first = false;
+            code_len = self.code_len(decoder_len, code_len);
+
+            table.insert((current, byte), next_code);
+            next_code += 1;
+            current = byte as u16;
+            // Start over before codes would need more than 12 bits
+            if next_code as usize == MAX_CODES {
+                writer.write(CLEAR_TABLE, code_len);
+                table.clear();
+                next_code = FIRST_CODE;
+                decoder_len = FIRST_CODE as usize;
+                first = true;
+                code_len = 9;
+            }
+        }
+
+        writer.write(current, code_len);
+        if !first {
+            decoder_len += 1;
+        }
+        code_len = self.code_len(decoder_len, code_len);
+        writer.write(END_OF_DATA, code_len);
+        writer.finish()
+    }
+}
+
+impl Filter for Lzw {
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        let out = self.decompress(data)?;
+        match &self.predictor {
+            Some(predictor) => predictor.decode(&out),
+            None => Ok(out),
+        }
+    }
+}
+
+impl Encoder for Lzw {
+    fn name(&self) -> &'static [u8] {
+        b"LZWDecode"
+    }
+
+    fn params(&self) -> Option<Dictionary> {
+        let mut params = self
+            .predictor
+            .as_ref()
+            .map_or_else(Dictionary::new, Predictor::params);
+        if !self.early_change {
+            params.insert(
+                Name::from(b"EarlyChange"),
+                Object::Number(Number::Integer(0)),
+            );
+        }
+        (!params.is_empty()).then_some(params)
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        match &self.predictor {
+            Some(predictor) => self.compress(&predictor.encode(data)),
+            None => self.compress(data),
+        }
+    }
+}
+
+/// Appends the bytes `code` stands for to `out`
+fn write_entry(table: &[(u16, u8)], mut code: u16, out: &mut Vec<u8>) {
+    let start = out.len();
+    while code >= FIRST_CODE {
+        let (prefix, byte) = table[code as usize];
+        out.push(byte);
+        code = prefix;
+    }
+    out.push(code as u8);
+    // We walked the entry from its end
+    out[start..].reverse();
+}
+
+/// Reads most-significant-bit-first codes
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    bits: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            buf: 0,
+            bits: 0,
+        }
+    }
+
+    fn read(&mut self, len: u32) -> Option<u16> {
+        while self.bits < len {
+            self.buf = (self.buf << 8) | *self.data.get(self.pos)? as u32;
+            self.pos += 1;
+            self.bits += 8;
+        }
+        self.bits -= len;
+        let code = (self.buf >> self.bits) & ((1 << len) - 1);
+        Some(code as u16)
+    }
+}
+
+/// Writes most-significant-bit-first codes
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    buf: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, len: u32) {
+        self.buf = (self.buf << len) | code as u32;
+        self.bits += len;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.out.push((self.buf >> self.bits) as u8);
+        }
+    }
+
+    /// Pads the last byte with zeros
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.out.push((self.buf << (8 - self.bits)) as u8);
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::object::{Name, Number, Object};
+
+    fn lzw(early_change: i64) -> Lzw {
+        let params = Dictionary::from_iter([(
+            Name::from(b"EarlyChange"),
+            Object::Number(Number::Integer(early_change)),
+        )]);
+        Lzw::new(Some(&params)).unwrap()
+    }
+
+    /// Packs codes of the given lengths most-significant-bit-first
+    fn pack(codes: &[(u16, u32)]) -> Vec<u8> {
+        let mut writer = BitWriter::default();
+        for &(code, len) in codes {
+            writer.write(code, len);
+        }
+        writer.finish()
+    }
+
+    #[test]
+    fn spec_example() {
+        // The example from PDF Spec section 7.4.4.2
+        let data = [0x80, 0x0B, 0x60, 0x50, 0x22, 0x0C, 0x0C, 0x85, 0x01];
+        assert_eq!(
+            Lzw::new(None).unwrap().decode(&data),
+            Ok(vec![45, 45, 45, 45, 45, 65, 45, 45, 45, 66])
+        );
+    }
+
+    #[test]
+    fn repeated_code() {
+        // `a`, then the code being defined (`aa`), then that again (`aaa`)
+        let data = pack(&[(256, 9), (97, 9), (258, 9), (259, 9), (257, 9)]);
+        assert_eq!(lzw(1).decode(&data), Ok(b"aaaaaa".to_vec()));
+    }
+
+    #[test]
+    fn early_change() {
+        // Emit enough codes to grow the table to the point where the code length increases. With
+        // `/EarlyChange 1`, that happens once the table has 511 entries, and otherwise 512.
+        for early_change in [0, 1] {
+            let mut codes = vec![(256, 9)];
+            let mut expected = Vec::new();
+            // Each literal code adds an entry to the table from the second one on
+            let count = 511 - 258 + 1 - early_change as usize;
+            for i in 0..=count {
+                codes.push(((i % 256) as u16, 9));
+                expected.push((i % 256) as u8);
+            }
+            codes.push((b'!' as u16, 10));
+            codes.push((257, 10));
+            expected.push(b'!');
+            assert_eq!(lzw(early_change).decode(&pack(&codes)), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let data = pack(&[(256, 9), (104, 9), (105, 9)]);
+        assert_eq!(lzw(1).decode(&data), Ok(b"hi".to_vec()));
+    }
+
+    #[test]
+    fn invalid() {
+        let data = pack(&[(256, 9), (104, 9), (300, 9)]);
+        assert_eq!(
+            lzw(1).decode(&data),
+            Err(FilterError::InvalidData(b"LZWDecode".to_vec()))
+        );
+        assert_eq!(
+            Lzw::new(Some(&Dictionary::from_iter([(
+                Name::from(b"EarlyChange"),
+                Object::Number(Number::Integer(2)),
+            )]))),
+            Err(FilterError::InvalidParameter(b"EarlyChange".to_vec()))
+        );
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(
+            Lzw::default().encode(&[45, 45, 45, 45, 45, 65, 45, 45, 45, 66]),
+            vec![0x80, 0x0B, 0x60, 0x50, 0x22, 0x0C, 0x0C, 0x85, 0x01]
+        );
+        assert_eq!(Lzw::default().params(), None);
+        assert_eq!(
+            Lzw::default().decode(&Lzw::default().encode(b"")),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        // Enough varied data to fill the table several times over, and enough repetition to
+        // exercise long entries
+        let data: Vec<u8> = (0..100_000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> (i % 29)) as u8 % (1 + (i / 5000) as u8))
+            .collect();
+        for early_change in [0, 1] {
+            let lzw = lzw(early_change);
+            let encoded = lzw.encode(&data);
+            let decoder = Lzw::new(lzw.params().as_ref()).unwrap();
+            assert_eq!(decoder, lzw);
+            assert_eq!(decoder.decode(&encoded), Ok(data.clone()));
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! Predictors, which transform image-like data so that it compresses better. The LZW and Flate
+//! filters can have one applied before compression. See PDF Spec section 7.4.4.4
+
+use super::{FilterError, int_param};
+use crate::ast::object::{Dictionary, Name, Number, Object};
+
+/// The longest row we'll decode, so that a huge `/Columns` can't make us allocate the row buffer
+/// before seeing any data
+static MAX_ROW_LEN: usize = 1 << 24;
+
+/// The PNG filter type of a row, which is stored in the byte before the row. See RFC 2083 section
+/// 6
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PngFilter {
+    None = 0,
+    Sub = 1,
+    Up = 2,
+    Average = 3,
+    Paeth = 4,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PredictorKind {
+    /// TIFF Predictor 2, where each sample is stored as the difference from the same component of
+    /// the previous pixel
+    Tiff,
+    /// PNG predictors, where each row can use a different [`PngFilter`]. `filter` is the one the
+    /// encoder claims to have used, or `None` for "optimum", which picks the best one per row.
+    /// Decoding always uses the filter stored with each row.
+    Png { filter: Option<PngFilter> },
+}
+
+/// A predictor, as described by the `/Predictor`, `/Colors`, `/BitsPerComponent` and `/Columns`
+/// entries of a filter's decode parameters
+#[derive(Clone, Debug, PartialEq)]
+pub struct Predictor {
+    pub kind: PredictorKind,
+    /// Components per pixel
+    pub colors: usize,
+    /// Bits per component: 1, 2, 4, 8 or 16
+    pub bits_per_component: usize,
+    /// Pixels per row
+    pub columns: usize,
+}
+
+impl Predictor {
+    /// Reads the predictor from a filter's decode parameters. Returns `None` if there is no
+    /// predictor (`/Predictor 1`, the default).
+    pub fn new(params: Option<&Dictionary>) -> Result<Option<Self>, FilterError> {
+        let kind = match int_param(params, b"Predictor", 1, 1..=15)? {
+            1 => return Ok(None),
+            2 => PredictorKind::Tiff,
+            10 => PredictorKind::Png {
+                filter: Some(PngFilter::None),
+            },
+            11 => PredictorKind::Png {
+                filter: Some(PngFilter::Sub),
+            },
+            12 => PredictorKind::Png {
+                filter: Some(PngFilter::Up),
+            },
+            13 => PredictorKind::Png {
+                filter: Some(PngFilter::Average),
+            },
+            14 => PredictorKind::Png {
+                filter: Some(PngFilter::Paeth),
+            },
+            15 => PredictorKind::Png { filter: None },
+            _ => return Err(FilterError::InvalidParameter(b"Predictor".to_vec())),
+        };
+        let predictor = Self {
+            kind,
+            colors: int_param(params, b"Colors", 1, 1..=32)?,
+            bits_per_component: int_param(params, b"BitsPerComponent", 8, 1..=16)?,
+            columns: int_param(params, b"Columns", 1, 1..=u32::MAX as usize)?,
+        };
+        if ![1, 2, 4, 8, 16].contains(&predictor.bits_per_component) {
+            return Err(FilterError::InvalidParameter(b"BitsPerComponent".to_vec()));
+        }
+        let bits = (predictor.colors * predictor.bits_per_component).checked_mul(predictor.columns);
+        if bits.is_none_or(|bits| bits.div_ceil(8) > MAX_ROW_LEN) {
+            return Err(FilterError::InvalidParameter(b"Columns".to_vec()));
+        }
+        Ok(Some(predictor))
+    }
+
+    /// The decode parameters describing this predictor
+    pub fn params(&self) -> Dictionary {
+        let predictor = match self.kind {
+            PredictorKind::Tiff => 2,
+            PredictorKind::Png { filter: None } => 15,
+            PredictorKind::Png {
+                filter: Some(filter),
+            } => 10 + filter as usize,
+        };
+        let mut params = Dictionary::new();
+        let mut set = |key: &[u8], val: usize| {
+            params.insert(Name::from(key), Object::Number(Number::Integer(val as i64)));
+        };
+        set(b"Predictor", predictor);
+        set(b"Colors", self.colors);
+        set(b"BitsPerComponent", self.bits_per_component);
+        set(b"Columns", self.columns);
+        params
+    }
+
+    /// The number of bytes in each row of (decoded) data
+    fn row_len(&self) -> usize {
+        (self.colors * self.bits_per_component)
+            .saturating_mul(self.columns)
+            .div_ceil(8)
+    }
+
+    /// The number of bytes in a pixel, rounded up to at least one. PNG filters operate on the
+    /// corresponding byte of the previous pixel.
+    fn bytes_per_pixel(&self) -> usize {
+        (self.colors * self.bits_per_component).div_ceil(8)
+    }
+
+    /// Undoes the predictor. A partial final row is decoded as far as it goes, but data that isn't
+    /// empty and is shorter than a single row means `/Columns` is wrong.
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        if data.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.row_len() > data.len() {
+            return Err(FilterError::InvalidParameter(b"Columns".to_vec()));
+        }
+        match self.kind {
+            PredictorKind::Tiff => Ok(self.decode_tiff(data)),
+            PredictorKind::Png { .. } => self.decode_png(data),
+        }
+    }
+
+    /// Applies the predictor, for data that's about to be compressed
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        match self.kind {
+            PredictorKind::Tiff => self.encode_tiff(data),
+            PredictorKind::Png { filter } => self.encode_png(data, filter),
+        }
+    }
+
+    fn encode_tiff(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = data.to_vec();
+        let bpc = self.bits_per_component;
+        let samples = self.colors * self.columns;
+        let mask = (1 << bpc) - 1;
+        for row in out.chunks_mut(self.row_len()) {
+            let len = samples.min(row.len() * 8 / bpc);
+            // Work backwards, so each sample is still the original when it's subtracted
+            for i in (self.colors..len).rev() {
+                let val = sample(row, i, bpc).wrapping_sub(sample(row, i - self.colors, bpc));
+                set_sample(row, i, bpc, val & mask);
+            }
+        }
+        out
+    }
+
+    fn encode_png(&self, data: &[u8], filter: Option<PngFilter>) -> Vec<u8> {
+        let row_len = self.row_len();
+        let bpp = self.bytes_per_pixel();
+        let mut out = Vec::with_capacity(data.len() + data.len() / row_len + 1);
+        let zeros = vec![0u8; row_len];
+        let mut prev: &[u8] = &zeros;
+        let mut encoded = Vec::with_capacity(row_len);
+        for row in data.chunks(row_len) {
+            let filters = match filter {
+                Some(filter) => &[filter][..],
+                None => &[
+                    PngFilter::None,
+                    PngFilter::Sub,
+                    PngFilter::Up,
+                    PngFilter::Average,
+                    PngFilter::Paeth,
+                ],
+            };
+            // For "optimum", use the filter that gives the smallest sum of the bytes taken as
+            // signed, the heuristic recommended by the PNG spec
+            let mut best: Option<(u64, PngFilter, Vec<u8>)> = None;
+            for &filter in filters {
+                encoded.clear();
+                encoded.extend(row.iter().enumerate().map(|(i, &cur)| {
+                    let up = prev[i];
+                    let (left, up_left) = if i >= bpp {
+                        (row[i - bpp], prev[i - bpp])
+                    } else {
+                        (0, 0)
+                    };
+                    cur.wrapping_sub(match filter {
+                        PngFilter::None => 0,
+                        PngFilter::Sub => left,
+                        PngFilter::Up => up,
+                        PngFilter::Average => ((left as u16 + up as u16) / 2) as u8,
+                        PngFilter::Paeth => paeth(left, up, up_left),
+                    })
+                }));
+                let cost = encoded
+                    .iter()
+                    .map(|b| (*b as i8).unsigned_abs() as u64)
+                    .sum();
+                if best.as_ref().is_none_or(|(best, _, _)| cost < *best) {
+                    best = Some((cost, filter, encoded.clone()));
+                }
+            }
+            if let Some((_, filter, encoded)) = best {
+                out.push(filter as u8);
+                out.extend_from_slice(&encoded);
+            }
+            prev = row;
+        }
+        out
+    }
+
+    fn decode_tiff(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = data.to_vec();
+        let bpc = self.bits_per_component;
+        let samples = self.colors * self.columns;
+        let mask = (1 << bpc) - 1;
+        for row in out.chunks_mut(self.row_len()) {
+            let len = samples.min(row.len() * 8 / bpc);
+            for i in self.colors..len {
+                let val = sample(row, i, bpc) + sample(row, i - self.colors, bpc);
+                set_sample(row, i, bpc, val & mask);
+            }
+        }
+        out
+    }
+
+    fn decode_png(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        let row_len = self.row_len();
+        let bpp = self.bytes_per_pixel();
+        let mut out = Vec::with_capacity(data.len() / (row_len + 1) * row_len);
+        let mut prev = vec![0u8; row_len];
+        for row in data.chunks(row_len + 1) {
+            let (&filter, row) = row.split_first().unwrap_or((&0, &[]));
+            let mut cur = row.to_vec();
+            match filter {
+                0 => {}
+                1 => {
+                    for i in bpp..cur.len() {
+                        cur[i] = cur[i].wrapping_add(cur[i - bpp]);
+                    }
+                }
+                2 => {
+                    for i in 0..cur.len() {
+                        cur[i] = cur[i].wrapping_add(prev[i]);
+                    }
+                }
+                3 => {
+                    for i in 0..cur.len() {
+                        let left = if i >= bpp { cur[i - bpp] } else { 0 };
+                        let avg = (left as u16 + prev[i] as u16) / 2;
+                        cur[i] = cur[i].wrapping_add(avg as u8);
+                    }
+                }
+                4 => {
+                    for i in 0..cur.len() {
+                        let (left, up_left) = if i >= bpp {
+                            (cur[i - bpp], prev[i - bpp])
+                        } else {
+                            (0, 0)
+                        };
+                        cur[i] = cur[i].wrapping_add(paeth(left, prev[i], up_left));
+                    }
+                }
+                _ => return Err(FilterError::InvalidData(b"Predictor".to_vec())),
+            }
+            prev[..cur.len()].copy_from_slice(&cur);
+            out.extend_from_slice(&cur);
+        }
+        Ok(out)
+    }
+}
+
+/// The Paeth predictor: whichever of the pixels to the left, above and above-left is closest to
+/// `left + up - up_left`
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let p = left as i16 + up as i16 - up_left as i16;
+    let pa = (p - left as i16).abs();
+    let pb = (p - up as i16).abs();
+    let pc = (p - up_left as i16).abs();
+    if pa <= pb && pa <= pc {
+        left
+    } else if pb <= pc {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Reads the `index`th `bpc`-bit sample of `row`
+fn sample(row: &[u8], index: usize, bpc: usize) -> u32 {
+    match bpc {
+        16 => u16::from_be_bytes([row[2 * index], row[2 * index + 1]]) as u32,
+        8 => row[index] as u32,
+        _ => {
+            let bit = index * bpc;
+            let shift = 8 - bpc - bit % 8;
+            (row[bit / 8] as u32 >> shift) & ((1 << bpc) - 1)
+        }
+    }
+}
+
+/// Writes the `index`th `bpc`-bit sample of `row`
+fn set_sample(row: &mut [u8], index: usize, bpc: usize, val: u32) {
+    match bpc {
+        16 => row[2 * index..2 * index + 2].copy_from_slice(&(val as u16).to_be_bytes()),
+        8 => row[index] = val as u8,
+        _ => {
+            let bit = index * bpc;
+            let shift = 8 - bpc - bit % 8;
+            let mask = ((1 << bpc) - 1) << shift;
+            row[bit / 8] = (row[bit / 8] & !mask) | ((val << shift) as u8 & mask);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn predictor(predictor: i64, colors: i64, bpc: i64, columns: i64) -> Predictor {
+        let params = Dictionary::from_iter([
+            (
+                Name::from(b"Predictor"),
+                Object::Number(Number::Integer(predictor)),
+            ),
+            (
+                Name::from(b"Colors"),
+                Object::Number(Number::Integer(colors)),
+            ),
+            (
+                Name::from(b"BitsPerComponent"),
+                Object::Number(Number::Integer(bpc)),
+            ),
+            (
+                Name::from(b"Columns"),
+                Object::Number(Number::Integer(columns)),
+            ),
+        ]);
+        Predictor::new(Some(&params)).unwrap().unwrap()
+    }
+
+    #[test]
+    fn params() {
+        assert_eq!(Predictor::new(None), Ok(None));
+        assert_eq!(
+            predictor(15, 3, 8, 100),
+            Predictor {
+                kind: PredictorKind::Png { filter: None },
+                colors: 3,
+                bits_per_component: 8,
+                columns: 100,
+            }
+        );
+
+        for (key, val) in [
+            (&b"Predictor"[..], 3),
+            (b"Predictor", 16),
+            (b"Colors", 0),
+            (b"BitsPerComponent", 3),
+            (b"Columns", -1),
+            (b"Columns", u32::MAX as i64),
+        ] {
+            let params = Dictionary::from_iter([
+                (Name::from(b"Predictor"), Object::Number(Number::Integer(2))),
+                (Name::from(key), Object::Number(Number::Integer(val))),
+            ]);
+            assert_eq!(
+                Predictor::new(Some(&params)),
+                Err(FilterError::InvalidParameter(key.to_vec()))
+            );
+        }
+    }
+
+    #[test]
+    fn png() {
+        let data = [
+            0, 10, 20, 30, // None
+            4, 5, 254, 10, // Paeth
+            3, 13, 1, 246, // Average
+            1, 1, 1, 1, // Sub
+            2, 1, 2, 3, // Up
+        ];
+        assert_eq!(
+            predictor(10, 1, 8, 3).decode(&data),
+            Ok(vec![10, 20, 30, 15, 18, 40, 20, 20, 20, 1, 2, 3, 2, 4, 6])
+        );
+        // The row tags are used regardless of `/Predictor`
+        assert_eq!(
+            predictor(14, 1, 8, 3).decode(&data),
+            predictor(10, 1, 8, 3).decode(&data)
+        );
+    }
+
+    #[test]
+    fn png_multibyte_pixels() {
+        // With 2 colors of 16 bits each, Sub refers back 4 bytes
+        assert_eq!(
+            predictor(11, 2, 16, 2).decode(&[1, 1, 2, 3, 4, 1, 1, 1, 1]),
+            Ok(vec![1, 2, 3, 4, 2, 3, 4, 5])
+        );
+    }
+
+    #[test]
+    fn png_partial_row() {
+        assert_eq!(
+            predictor(12, 1, 8, 3).decode(&[2, 1, 2, 3, 2, 1]),
+            Ok(vec![1, 2, 3, 2])
+        );
+    }
+
+    #[test]
+    fn huge_rows() {
+        // Rows longer than the data are rejected before anything is allocated for them
+        let data = [2, 0, 0, 0];
+        assert_eq!(
+            predictor(12, 32, 16, 1 << 15).decode(&data),
+            Err(FilterError::InvalidParameter(b"Columns".to_vec()))
+        );
+        assert_eq!(
+            predictor(2, 1, 8, 5).decode(&data),
+            Err(FilterError::InvalidParameter(b"Columns".to_vec()))
+        );
+        // But there's nothing wrong with no rows at all
+        assert_eq!(predictor(12, 32, 16, 1 << 15).decode(&[]), Ok(Vec::new()));
+        assert_eq!(predictor(2, 1, 8, 5).decode(&[]), Ok(Vec::new()));
+
+        let params = Dictionary::from_iter([
+            (
+                Name::from(b"Predictor"),
+                Object::Number(Number::Integer(12)),
+            ),
+            (Name::from(b"Colors"), Object::Number(Number::Integer(32))),
+            (
+                Name::from(b"BitsPerComponent"),
+                Object::Number(Number::Integer(16)),
+            ),
+            (
+                Name::from(b"Columns"),
+                Object::Number(Number::Integer(u32::MAX as i64)),
+            ),
+        ]);
+        assert_eq!(
+            Predictor::new(Some(&params)),
+            Err(FilterError::InvalidParameter(b"Columns".to_vec()))
+        );
+    }
+
+    #[test]
+    fn png_invalid_filter() {
+        assert_eq!(
+            predictor(10, 1, 8, 1).decode(&[5, 0]),
+            Err(FilterError::InvalidData(b"Predictor".to_vec()))
+        );
+    }
+
+    #[test]
+    fn tiff() {
+        assert_eq!(
+            predictor(2, 2, 8, 3).decode(&[1, 2, 1, 1, 1, 1, 5, 5, 0, 0, 255, 0]),
+            Ok(vec![1, 2, 2, 3, 3, 4, 5, 5, 5, 5, 4, 5])
+        );
+        assert_eq!(
+            predictor(2, 1, 16, 2).decode(&[0x00, 0xFF, 0x00, 0x01]),
+            Ok(vec![0x00, 0xFF, 0x01, 0x00])
+        );
+        assert_eq!(predictor(2, 1, 4, 2).decode(&[0x1F]), Ok(vec![0x10]));
+        // Each row starts over, even mid-byte
+        assert_eq!(
+            predictor(2, 1, 1, 5).decode(&[0b1000_0000, 0b1000_1000]),
+            Ok(vec![0b1111_1000, 0b1111_0000])
+        );
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(
+            predictor(2, 2, 8, 3).encode(&[1, 2, 2, 3, 3, 4, 5, 5, 5, 5, 4, 5]),
+            vec![1, 2, 1, 1, 1, 1, 5, 5, 0, 0, 255, 0]
+        );
+        assert_eq!(
+            predictor(12, 1, 8, 3).encode(&[1, 2, 3, 2, 4, 6]),
+            vec![2, 1, 2, 3, 2, 1, 2, 3]
+        );
+        // A constant row is best encoded with Sub, and a repeated row with Up
+        assert_eq!(
+            predictor(15, 1, 8, 4).encode(&[9, 9, 9, 9, 9, 9, 9, 9]),
+            vec![1, 9, 0, 0, 0, 2, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * i / 7) as u8).collect();
+        for (kind, colors, bpc, columns) in [
+            (2, 1, 1, 13),
+            (2, 3, 2, 7),
+            (2, 2, 4, 9),
+            (2, 3, 8, 10),
+            (2, 2, 16, 5),
+            (10, 3, 8, 10),
+            (11, 1, 4, 33),
+            (12, 2, 8, 7),
+            (13, 3, 16, 4),
+            (14, 4, 8, 6),
+            (15, 3, 8, 10),
+        ] {
+            let predictor = predictor(kind, colors, bpc, columns);
+            assert_eq!(predictor.decode(&predictor.encode(&data)), Ok(data.clone()));
+            assert_eq!(
+                Predictor::new(Some(&predictor.params())),
+                Ok(Some(predictor))
+            );
+        }
+    }
+}
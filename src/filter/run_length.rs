@@ -0,0 +1,107 @@
+//! The `RunLengthDecode` filter. See PDF Spec section 7.4.5
+
+use super::{Encoder, Filter, FilterError};
+
+/// Decodes runs of literal bytes and of a repeated byte, each preceded by a length byte
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunLength;
+
+impl Filter for RunLength {
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>, FilterError> {
+        let mut out = Vec::with_capacity(data.len());
+        let mut it = data.iter();
+        // A truncated run is decoded as far as it goes
+        while let Some(&len) = it.next() {
+            match len {
+                // Copy the next `len + 1` bytes
+                0..=127 => out.extend(it.by_ref().take(len as usize + 1)),
+                // End of data
+                128 => break,
+                // Repeat the next byte `257 - len` times
+                _ => {
+                    let Some(&byte) = it.next() else {
+                        break;
+                    };
+                    out.resize(out.len() + 257 - len as usize, byte);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Encoder for RunLength {
+    fn name(&self) -> &'static [u8] {
+        b"RunLengthDecode"
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() + data.len() / 128 + 2);
+        let mut literal_start = 0;
+        let mut i = 0;
+        while i < data.len() {
+            let run = data[i..]
+                .iter()
+                .take(128)
+                .take_while(|b| **b == data[i])
+                .count();
+            // Runs of 2 are only worth it if they don't split up a literal
+            if run >= 3 || (run == 2 && literal_start == i) {
+                flush_literal(&data[literal_start..i], &mut out);
+                out.push((257 - run) as u8);
+                out.push(data[i]);
+                i += run;
+                literal_start = i;
+            } else {
+                i += 1;
+            }
+        }
+        flush_literal(&data[literal_start..], &mut out);
+        out.push(128);
+        out
+    }
+}
+
+/// Writes `literal` as runs of at most 128 bytes
+fn flush_literal(literal: &[u8], out: &mut Vec<u8>) {
+    for chunk in literal.chunks(128) {
+        out.push(chunk.len() as u8 - 1);
+        out.extend_from_slice(chunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        assert_eq!(
+            RunLength.decode(&[2, b'a', b'b', b'c', 254, b'x', 0, b'd', 128, 5]),
+            Ok(b"abcxxxd".to_vec())
+        );
+        assert_eq!(RunLength.decode(&[129, b'-', 128]), Ok(vec![b'-'; 128]));
+        assert_eq!(RunLength.decode(&[128]), Ok(vec![]));
+        assert_eq!(RunLength.decode(&[]), Ok(vec![]));
+        assert_eq!(RunLength.decode(&[5, b'a', b'b']), Ok(b"ab".to_vec()));
+        assert_eq!(RunLength.decode(&[255]), Ok(vec![]));
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(
+            RunLength.encode(b"abcxxxd"),
+            vec![2, b'a', b'b', b'c', 254, b'x', 0, b'd', 128]
+        );
+        assert_eq!(
+            RunLength.encode(&[b'-'; 130]),
+            vec![129, b'-', 255, b'-', 128]
+        );
+        assert_eq!(RunLength.encode(b""), vec![128]);
+
+        let data: Vec<u8> = (0..1000).map(|i| (i / 5 % 3 + i % 2) as u8).collect();
+        assert_eq!(RunLength.decode(&RunLength.encode(&data)), Ok(data));
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        assert_eq!(RunLength.decode(&RunLength.encode(&data)), Ok(data));
+    }
+}
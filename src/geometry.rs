@@ -0,0 +1,291 @@
+//! Rectangles and transformation matrices. See PDF Spec sections 7.9.5 and 8.3
+
+use crate::ast::object::{Number, Object};
+
+/// A rectangle, normalized so that `(x0, y0)` is the lower-left corner and `(x1, y1)` the
+/// upper-right. See PDF Spec section 7.9.5
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rectangle {
+    pub x0: Number,
+    pub y0: Number,
+    pub x1: Number,
+    pub y1: Number,
+}
+
+impl Rectangle {
+    /// Creates a rectangle from any two diagonally opposite corners
+    pub fn new(x0: Number, y0: Number, x1: Number, y1: Number) -> Self {
+        let (x0, x1) = ordered(x0, x1);
+        let (y0, y1) = ordered(y0, y1);
+        Self { x0, y0, x1, y1 }
+    }
+
+    /// Reads a rectangle from an array of four numbers, which many producers write with the
+    /// corners in the wrong order
+    pub fn from_array(array: &[Object]) -> Option<Self> {
+        match array {
+            [x0, y0, x1, y1] => Some(Self::new(
+                x0.as_number()?,
+                y0.as_number()?,
+                x1.as_number()?,
+                y1.as_number()?,
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.x1.as_f64() - self.x0.as_f64()
+    }
+
+    pub fn height(&self) -> f64 {
+        self.y1.as_f64() - self.y0.as_f64()
+    }
+
+    /// The area both rectangles cover, if they overlap
+    pub fn intersect(&self, other: &Rectangle) -> Option<Rectangle> {
+        let max = |a: Number, b: Number| if b.as_f64() > a.as_f64() { b } else { a };
+        let min = |a: Number, b: Number| if b.as_f64() < a.as_f64() { b } else { a };
+        let rect = Rectangle {
+            x0: max(self.x0, other.x0),
+            y0: max(self.y0, other.y0),
+            x1: min(self.x1, other.x1),
+            y1: min(self.y1, other.y1),
+        };
+        (rect.width() >= 0.0 && rect.height() >= 0.0).then_some(rect)
+    }
+
+    pub fn to_array(&self) -> Object {
+        Object::Array(
+            [self.x0, self.y0, self.x1, self.y1]
+                .into_iter()
+                .map(Object::Number)
+                .collect(),
+        )
+    }
+}
+
+fn ordered(a: Number, b: Number) -> (Number, Number) {
+    if b.as_f64() < a.as_f64() {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+/// An affine transformation `[a b c d e f]`, which maps `(x, y)` to
+/// `(a x + c y + e, b x + d y + f)`. See PDF Spec section 8.3.4
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Matrix {
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    pub fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+
+    pub fn translate(x: f64, y: f64) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    pub fn scale(x: f64, y: f64) -> Self {
+        Self::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// Reads a matrix from an array of six numbers
+    pub fn from_array(array: &[Object]) -> Option<Self> {
+        let values: Vec<f64> = array
+            .iter()
+            .map(|obj| obj.as_number().map(Number::as_f64))
+            .collect::<Option<_>>()?;
+        match values[..] {
+            [a, b, c, d, e, f] => Some(Self::new(a, b, c, d, e, f)),
+            _ => None,
+        }
+    }
+
+    /// The transformation that applies `self`, then `other`. This is `self × other` in the
+    /// spec's notation, and is how `cm` combines its operand with the current transformation
+    /// matrix: `operand.then(&ctm)`.
+    pub fn then(&self, other: &Matrix) -> Matrix {
+        Matrix {
+            a: self.a * other.a + self.b * other.c,
+            b: self.a * other.b + self.b * other.d,
+            c: self.c * other.a + self.d * other.c,
+            d: self.c * other.b + self.d * other.d,
+            e: self.e * other.a + self.f * other.c + other.e,
+            f: self.e * other.b + self.f * other.d + other.f,
+        }
+    }
+
+    /// The transformation that undoes this one, unless it's degenerate
+    pub fn invert(&self) -> Option<Matrix> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Matrix {
+            a,
+            b,
+            c,
+            d,
+            e: -(self.e * a + self.f * c),
+            f: -(self.e * b + self.f * d),
+        })
+    }
+
+    pub fn transform_point(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+
+    /// Transforms a distance, ignoring the translation
+    pub fn transform_vector(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.a * x + self.c * y, self.b * x + self.d * y)
+    }
+
+    /// The smallest rectangle containing the transformed corners of `rect`
+    pub fn transform_rect(&self, rect: &Rectangle) -> Rectangle {
+        let (x0, y0, x1, y1) = (
+            rect.x0.as_f64(),
+            rect.y0.as_f64(),
+            rect.x1.as_f64(),
+            rect.y1.as_f64(),
+        );
+        let corners =
+            [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].map(|(x, y)| self.transform_point(x, y));
+        let bound = |f: fn(f64, f64) -> f64, pick: fn(&(f64, f64)) -> f64| {
+            Number::Real(corners.iter().map(pick).reduce(f).unwrap())
+        };
+        Rectangle {
+            x0: bound(f64::min, |p| p.0),
+            y0: bound(f64::min, |p| p.1),
+            x1: bound(f64::max, |p| p.0),
+            y1: bound(f64::max, |p| p.1),
+        }
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(val: i64) -> Number {
+        Number::Integer(val)
+    }
+
+    #[test]
+    fn rectangle() {
+        let rect = Rectangle::from_array(&[
+            Object::Number(int(612)),
+            Object::Number(int(792)),
+            Object::Number(Number::Real(-0.5)),
+            Object::Number(int(0)),
+        ])
+        .unwrap();
+        assert_eq!(
+            rect,
+            Rectangle {
+                x0: Number::Real(-0.5),
+                y0: int(0),
+                x1: int(612),
+                y1: int(792)
+            }
+        );
+        assert_eq!(rect.width(), 612.5);
+        assert_eq!(rect.height(), 792.0);
+        assert_eq!(
+            Rectangle::from_array(rect.to_array().as_array().unwrap()),
+            Some(rect)
+        );
+
+        assert_eq!(
+            Rectangle::from_array(&vec![Object::Number(int(0)); 3]),
+            None
+        );
+        assert_eq!(
+            Rectangle::from_array(&[
+                Object::Number(int(0)),
+                Object::Number(int(0)),
+                Object::Null,
+                Object::Number(int(0))
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn intersect() {
+        let a = Rectangle::new(int(0), int(0), int(100), int(100));
+        let b = Rectangle::new(int(50), int(-10), int(200), Number::Real(60.5));
+        assert_eq!(
+            a.intersect(&b),
+            Some(Rectangle::new(
+                int(50),
+                int(0),
+                int(100),
+                Number::Real(60.5)
+            ))
+        );
+        let c = Rectangle::new(int(101), int(0), int(200), int(100));
+        assert_eq!(a.intersect(&c), None);
+    }
+
+    #[test]
+    fn matrix() {
+        let m = Matrix::scale(2.0, 3.0).then(&Matrix::translate(10.0, 20.0));
+        assert_eq!(m.transform_point(1.0, 1.0), (12.0, 23.0));
+        assert_eq!(m.transform_vector(1.0, 1.0), (2.0, 3.0));
+        assert_eq!(
+            Matrix::translate(10.0, 20.0)
+                .then(&Matrix::scale(2.0, 3.0))
+                .transform_point(1.0, 1.0),
+            (22.0, 63.0)
+        );
+
+        let inverse = m.invert().unwrap();
+        assert_eq!(m.then(&inverse), Matrix::identity());
+        assert_eq!(Matrix::scale(0.0, 1.0).invert(), None);
+
+        // A quarter turn anticlockwise
+        let rotate = Matrix::new(0.0, 1.0, -1.0, 0.0, 0.0, 0.0);
+        assert_eq!(
+            rotate.transform_rect(&Rectangle::new(int(0), int(0), int(20), int(10))),
+            Rectangle::new(
+                Number::Real(-10.0),
+                Number::Real(0.0),
+                Number::Real(0.0),
+                Number::Real(20.0)
+            )
+        );
+
+        let objects: Vec<_> = [1, 0, 0, 1, 5, 6]
+            .into_iter()
+            .map(|n| Object::Number(int(n)))
+            .collect();
+        assert_eq!(
+            Matrix::from_array(&objects),
+            Some(Matrix::translate(5.0, 6.0))
+        );
+        assert_eq!(Matrix::from_array(&objects[..5]), None);
+    }
+}
@@ -4,6 +4,11 @@ use std::fmt::Display;
 use std::iter::{Enumerate, Peekable};
 use std::slice::Iter;
 
+use crate::error::WarningKind;
+use crate::parser_helper::ParseContext;
+
+// `UnknownTok` matches the name of its terminal in pdf.lalrpop
+#[allow(clippy::enum_variant_names)]
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum Tok<'input> {
     RawStrDelimOpen,
@@ -15,8 +20,28 @@ pub(crate) enum Tok<'input> {
 
     Name(&'input [u8]),
 
+    ArrayOpen,
+    ArrayClose,
+    DictOpen,
+    DictClose,
+    /// `{`, which only appears in PostScript calculator functions (PDF Spec section 7.10.5)
+    BraceOpen,
+    BraceClose,
+
+    /// The `stream` keyword. This is always followed by a `StreamData` token
+    Stream,
+    /// The raw (still encoded) bytes between `stream` and `endstream`
+    StreamData(&'input [u8]),
+    EndStream,
+
     True,
     False,
+    Null,
+
+    /// The `R` in an indirect reference such as `12 0 R`
+    R,
+    Obj,
+    EndObj,
 
     Number(&'input [u8]),
 
@@ -28,6 +53,24 @@ enum PdfLexerMode {
     Base,
     RawString,
     HexString,
+    /// We've lexed the content of a hex string, and the next `>` closes it (rather than being
+    /// the first half of a `>>`)
+    HexStringEnd,
+    /// We've just lexed the `stream` keyword, so everything up to `endstream` is binary data
+    StreamData,
+}
+
+/// Tracks whether we've seen a direct `/Length` entry in the current top-level dictionary, so we
+/// know how many bytes of stream data follow a `stream` keyword. See PDF Spec section 7.3.8.2
+#[derive(Clone, Copy)]
+enum StreamLengthState {
+    Idle,
+    /// We just lexed `/Length`, and are waiting for its value
+    ExpectValue,
+    /// We lexed `/Length N`, but it may turn out to be the start of an indirect reference
+    Value,
+    /// We lexed `/Length N G`, so if an `R` follows the length is indirect
+    MaybeReference,
 }
 
 #[derive(Debug)]
@@ -39,6 +82,17 @@ pub enum PdfLexError<'input> {
     UnexpectedEOF,
     /// Represents a situation where the token we're lexing is (much) longer than it should be
     TokenTooLong(&'input [u8]),
+    /// Represents an object or generation number (e.g., in `12 0 R`) that isn't a non-negative
+    /// integer in range
+    InvalidObjectId(&'input [u8]),
+    /// Represents hex string content containing something other than hex digits
+    InvalidHexString(&'input [u8]),
+    /// Represents a name with an invalid `#` escape
+    InvalidName(&'input [u8]),
+    /// Represents a number token that isn't a valid (or in range) number, e.g. `1.2.3` or `--5`
+    InvalidNumber(&'input [u8]),
+    /// Represents a `[` or `<<` nested inside more than `MAX_NESTING` arrays and dictionaries
+    TooDeep(&'input [u8]),
 }
 
 impl Display for PdfLexError<'_> {
@@ -53,26 +107,86 @@ impl Display for PdfLexError<'_> {
                     write!(f, "Token too long")
                 }
             }
+            PdfLexError::InvalidObjectId(tok) => {
+                if let Ok(s) = str::from_utf8(tok) {
+                    write!(f, "`{s}` is not a valid object or generation number")
+                } else {
+                    write!(f, "Invalid object or generation number")
+                }
+            }
+            PdfLexError::InvalidHexString(_) => write!(f, "Invalid character in hex string"),
+            PdfLexError::InvalidName(tok) => {
+                if let Ok(s) = str::from_utf8(tok) {
+                    write!(f, "Invalid escape in name `{s}`")
+                } else {
+                    write!(f, "Invalid escape in name")
+                }
+            }
+            PdfLexError::InvalidNumber(tok) => {
+                if let Ok(s) = str::from_utf8(tok) {
+                    write!(f, "`{s}` is not a valid number")
+                } else {
+                    write!(f, "Invalid number")
+                }
+            }
+            PdfLexError::TooDeep(_) => write!(f, "Arrays and dictionaries nested too deeply"),
         }
     }
 }
 
-struct PdfLexerForwards<'input> {
+#[derive(Clone)]
+pub(crate) struct PdfLexerForwards<'input> {
     chars: Peekable<Enumerate<Iter<'input, u8>>>,
     input: &'input [u8],
+    /// Where `input` starts in the full input. Spans are reported relative to the full input
+    offset: usize,
     mode: PdfLexerMode,
+
+    /// How many `<<` deep we currently are
+    dict_depth: usize,
+    length_state: StreamLengthState,
+    /// The direct `/Length` of the most recent top-level dictionary, if it had one
+    stream_length: Option<usize>,
 }
 
 impl<'input> PdfLexerForwards<'input> {
-    pub fn new(input: &'input [u8]) -> Self {
+    /// Creates a lexer that starts lexing at byte `start` of `input`
+    pub(crate) fn new_at(input: &'input [u8], start: usize) -> Self {
+        let input = &input[start.min(input.len())..];
         Self {
             chars: input.iter().enumerate().peekable(),
             input,
+            offset: start,
             mode: PdfLexerMode::Base,
+            dict_depth: 0,
+            length_state: StreamLengthState::Idle,
+            stream_length: None,
         }
     }
 }
 
+/// Whitespace characters, as defined by PDF Spec section 7.2.3, table 1
+pub(crate) fn is_whitespace(c: u8) -> bool {
+    matches!(
+        c,
+        b'\x00' | b'\t' | b'\n' | b'\x0C' /* FORM FEED */ | b'\r' | b' '
+    )
+}
+
+/// Delimiter characters, as defined by PDF Spec section 7.2.3, table 2. These end any name,
+/// number or keyword token they immediately follow.
+pub(crate) fn is_delimiter(c: u8) -> bool {
+    matches!(
+        c,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+/// Finds the first occurrence of `needle` in `haystack`
+pub(crate) fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
 // How many bytes to look for a keyword before giving up and issuing a lexer error
 static KEYWORD_LOOKAHEAD: usize = 30;
 
@@ -81,6 +195,21 @@ impl<'input> Iterator for PdfLexerForwards<'input> {
     type Item = Spanned<Tok<'input>, usize, PdfLexError<'input>>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let next = self.next_token();
+        if let Some(Ok((_, tok, _))) = &next {
+            self.track_stream_length(tok);
+        }
+        let offset = self.offset;
+        next.map(|res| match res {
+            Ok((l, tok, r)) => Ok((l + offset, tok, r + offset)),
+            Err(PdfLexError::UnexpectedChar(i)) => Err(PdfLexError::UnexpectedChar(i + offset)),
+            Err(e) => Err(e),
+        })
+    }
+}
+
+impl PdfLexerForwards<'_> {
+    fn next_token(&mut self) -> Option<<Self as Iterator>::Item> {
         match self.mode {
             PdfLexerMode::Base => {
                 'base: loop {
@@ -97,15 +226,30 @@ impl<'input> Iterator for PdfLexerForwards<'input> {
                             return Some(Ok((i, Tok::RawStrDelimClose, i + 1)));
                         }
 
-                        // We're starting a hex string, switch to its mode
+                        // `<<` opens a dictionary, a lone `<` starts a hex string
                         Some((i, b'<')) => {
+                            if let Some((_, b'<')) = self.chars.peek() {
+                                self.chars.next();
+                                return Some(Ok((i, Tok::DictOpen, i + 2)));
+                            }
                             self.mode = PdfLexerMode::HexString;
                             return Some(Ok((i, Tok::HexStrDelimOpen, i + 1)));
                         }
+                        // Hex strings are closed in `HexStringEnd` mode, so in base mode we
+                        // should only ever see `>>`
                         Some((i, b'>')) => {
-                            return Some(Ok((i, Tok::HexStrDelimClose, i + 1)));
+                            if let Some((_, b'>')) = self.chars.peek() {
+                                self.chars.next();
+                                return Some(Ok((i, Tok::DictClose, i + 2)));
+                            }
+                            return Some(Err(PdfLexError::UnexpectedChar(i)));
                         }
 
+                        Some((i, b'[')) => return Some(Ok((i, Tok::ArrayOpen, i + 1))),
+                        Some((i, b']')) => return Some(Ok((i, Tok::ArrayClose, i + 1))),
+                        Some((i, b'{')) => return Some(Ok((i, Tok::BraceOpen, i + 1))),
+                        Some((i, b'}')) => return Some(Ok((i, Tok::BraceClose, i + 1))),
+
                         // Handle a name token
                         Some((i, b'/')) => return self.lex_name(i),
 
@@ -133,12 +277,7 @@ impl<'input> Iterator for PdfLexerForwards<'input> {
                         },
 
                         // Skip whitespace in base mode
-                        Some((_, b'\x00'))
-                        | Some((_, b'\t'))
-                        | Some((_, b'\n'))
-                        | Some((_, b'\x0C' /* FORM FEED */))
-                        | Some((_, b'\r'))
-                        | Some((_, b' ')) => continue,
+                        Some((_, c)) if is_whitespace(*c) => continue,
 
                         // It's not some kind of delimiter, so look for a keyword
                         Some((i, _)) => return self.lex_keyword(i),
@@ -146,24 +285,120 @@ impl<'input> Iterator for PdfLexerForwards<'input> {
                 }
             }
 
-            PdfLexerMode::RawString => return self.lex_raw_string(),
+            PdfLexerMode::RawString => self.lex_raw_string(),
 
-            PdfLexerMode::HexString => return self.lex_hex_string(),
+            PdfLexerMode::HexString => self.lex_hex_string(),
+
+            PdfLexerMode::HexStringEnd => {
+                self.mode = PdfLexerMode::Base;
+                match self.chars.next() {
+                    Some((i, b'>')) => Some(Ok((i, Tok::HexStrDelimClose, i + 1))),
+                    Some((i, _)) => Some(Err(PdfLexError::UnexpectedChar(i))),
+                    None => Some(Err(PdfLexError::UnexpectedEOF)),
+                }
+            }
+
+            PdfLexerMode::StreamData => self.lex_stream_data(),
         }
     }
-}
 
-impl PdfLexerForwards<'_> {
+    /// Updates our knowledge of the current stream's `/Length` after lexing `tok`, and switches
+    /// into stream data mode after a `stream` keyword.
+    fn track_stream_length(&mut self, tok: &Tok) {
+        use StreamLengthState::*;
+
+        self.length_state = match (self.length_state, tok) {
+            (_, Tok::DictOpen) => {
+                self.dict_depth += 1;
+                if self.dict_depth == 1 {
+                    self.stream_length = None;
+                }
+                Idle
+            }
+            (_, Tok::DictClose) => {
+                self.dict_depth = self.dict_depth.saturating_sub(1);
+                Idle
+            }
+            (_, Tok::Name(b"/Length")) if self.dict_depth == 1 => ExpectValue,
+            (ExpectValue, Tok::Number(n)) => {
+                self.stream_length = str::from_utf8(n).ok().and_then(|n| n.parse().ok());
+                Value
+            }
+            (Value, Tok::Number(_)) => MaybeReference,
+            (MaybeReference, Tok::R) => {
+                // The length is an indirect object, which we can't resolve from in here
+                self.stream_length = None;
+                Idle
+            }
+            (_, Tok::Stream) => {
+                self.mode = PdfLexerMode::StreamData;
+                Idle
+            }
+            _ => Idle,
+        };
+    }
+
+    /// Lexes the data of a stream, which starts after the end-of-line marker following the
+    /// `stream` keyword. We trust the dictionary's direct `/Length` if it's followed by
+    /// `endstream`, and otherwise fall back on searching for `endstream`.
+    fn lex_stream_data(&mut self) -> Option<<Self as Iterator>::Item> {
+        self.mode = PdfLexerMode::Base;
+
+        // PDF Spec section 7.3.8.1:
+        // The keyword stream that follows the stream dictionary shall be followed by an
+        // end-of-line marker consisting of either a CARRIAGE RETURN and a LINE FEED or just a LINE
+        // FEED, and not by a CARRIAGE RETURN alone.
+        // We accept a lone CARRIAGE RETURN anyway, as some producers write one.
+        match self.chars.peek() {
+            Some((_, b'\r')) => {
+                self.chars.next();
+                if let Some((_, b'\n')) = self.chars.peek() {
+                    self.chars.next();
+                }
+            }
+            Some((_, b'\n')) => {
+                self.chars.next();
+            }
+            _ => {}
+        }
+        let start = self.chars.peek().map_or(self.input.len(), |(i, _)| *i);
+        let rest = &self.input[start..];
+
+        let len = match self.stream_length.take() {
+            Some(len) if len <= rest.len() && Self::is_stream_end(&rest[len..]) => len,
+            _ => {
+                let Some(end) = find_subslice(rest, b"endstream") else {
+                    return Some(Err(PdfLexError::UnexpectedEOF));
+                };
+                // The end-of-line marker before `endstream` isn't part of the data
+                if rest[..end].ends_with(b"\r\n") {
+                    end - 2
+                } else if rest[..end].ends_with(b"\n") || rest[..end].ends_with(b"\r") {
+                    end - 1
+                } else {
+                    end
+                }
+            }
+        };
+
+        if len > 0 {
+            self.chars.nth(len - 1);
+        }
+        Some(Ok((start, Tok::StreamData(&rest[..len]), start + len)))
+    }
+
+    /// Whether `rest` begins with `endstream`, optionally preceded by whitespace
+    fn is_stream_end(rest: &[u8]) -> bool {
+        let ws = rest.iter().take_while(|c| is_whitespace(**c)).count();
+        rest[ws..].starts_with(b"endstream")
+    }
+
     fn lex_raw_string(&mut self) -> Option<<Self as Iterator>::Item> {
         let mut depth = 1;
         // FIXME once
         // https://doc.rust-lang.org/std/iter/struct.Enumerate.html#method.next_index is
         // stabilized
-        let head = self.chars.peek();
-        if head.is_none() {
-            return None;
-        }
-        let start = head.unwrap().0;
+        let start = self.chars.peek()?.0;
         loop {
             match self.chars.peek() {
                 None => {
@@ -221,12 +456,16 @@ impl PdfLexerForwards<'_> {
             match self.chars.peek() {
                 None => return Some(Err(PdfLexError::UnexpectedEOF)),
                 Some((i, b'>')) => {
-                    self.mode = PdfLexerMode::Base;
+                    self.mode = PdfLexerMode::HexStringEnd;
                     return Some(Ok((start, Tok::HexStrContent(&self.input[start..*i]), *i)));
                 }
+                // Whitespace is ignored in hex strings, per PDF Spec section 7.3.4.3
                 Some((_, b'0'..=b'9')) | Some((_, b'a'..=b'f')) | Some((_, b'A'..=b'F')) => {
                     self.chars.next();
                 }
+                Some((_, c)) if is_whitespace(**c) => {
+                    self.chars.next();
+                }
                 Some((i, _)) => return Some(Err(PdfLexError::UnexpectedChar(*i))),
             }
         }
@@ -235,12 +474,8 @@ impl PdfLexerForwards<'_> {
     fn lex_name(&mut self, start: usize) -> Option<<Self as Iterator>::Item> {
         loop {
             match self.chars.peek() {
-                // Names are ended by (non-NUL) whitespace
-                Some((j, b'\t'))
-                | Some((j, b'\n'))
-                | Some((j, b'\x0C' /* FORM FEED */))
-                | Some((j, b'\r'))
-                | Some((j, b' ')) => {
+                // Names are ended by (non-NUL) whitespace or a delimiter
+                Some((j, c)) if **c != b'\x00' && (is_whitespace(**c) || is_delimiter(**c)) => {
                     return Some(Ok((start, Tok::Name(&self.input[start..*j]), *j)));
                 }
                 // I suppose a name could be ended by EOF as well...
@@ -266,14 +501,9 @@ impl PdfLexerForwards<'_> {
         let mut keyword_len = 1;
         loop {
             match self.chars.peek() {
-                // Whitespace or EOF separates tokens
-                None
-                | Some((_, b'\x00'))
-                | Some((_, b'\t'))
-                | Some((_, b'\n'))
-                | Some((_, b'\x0C' /* FORM FEED */))
-                | Some((_, b'\r'))
-                | Some((_, b' ')) => break,
+                // Whitespace, delimiters or EOF separate tokens
+                None => break,
+                Some((_, c)) if is_whitespace(**c) || is_delimiter(**c) => break,
 
                 Some((_, _)) => {
                     self.chars.next();
@@ -290,25 +520,28 @@ impl PdfLexerForwards<'_> {
         let tok = match &self.input[start..(start + keyword_len)] {
             b"true" => Tok::True,
             b"false" => Tok::False,
+            b"null" => Tok::Null,
+            b"R" => Tok::R,
+            b"obj" => Tok::Obj,
+            b"endobj" => Tok::EndObj,
+            b"stream" => Tok::Stream,
+            b"endstream" => Tok::EndStream,
             _ => Tok::UnknownTok(&self.input[start..(start + keyword_len)]),
         };
 
-        return Some(Ok((start, tok, start + keyword_len)));
+        Some(Ok((start, tok, start + keyword_len)))
     }
 
     fn lex_number(&mut self, start: usize) -> Option<<Self as Iterator>::Item> {
         loop {
             match self.chars.peek() {
-                Some((_, b'0'..=b'9')) | Some((_, b'.')) => {
+                // Signs are only valid at the start, but we leave it to the parser to reject
+                // misplaced ones so it can report the whole token
+                Some((_, b'0'..=b'9')) | Some((_, b'.')) | Some((_, b'+')) | Some((_, b'-')) => {
                     self.chars.next();
                 }
                 // End of the number
-                Some((j, b'\x00'))
-                | Some((j, b'\t'))
-                | Some((j, b'\n'))
-                | Some((j, b'\x0C' /* FORM FEED */))
-                | Some((j, b'\r'))
-                | Some((j, b' ')) => {
+                Some((j, c)) if is_whitespace(**c) || is_delimiter(**c) => {
                     return Some(Ok((start, Tok::Number(&self.input[start..*j]), *j)));
                 }
                 // An EOF could end the number as well
@@ -328,15 +561,152 @@ impl PdfLexerForwards<'_> {
     }
 }
 
+// How many tokens `PdfLexer` lexes at a time when iterating backwards
+static CHUNK_LEN: usize = 256;
+
+/// How deeply arrays and dictionaries can be nested. Objects are dropped, cloned and written
+/// recursively, so anything deeper could overflow the stack.
+pub(crate) static MAX_NESTING: usize = 256;
+
+/// Iterates over the tokens of (part of) the input last-to-first, which is what the parser
+/// expects.
+///
+/// Rather than holding on to every token, we lex forwards once, taking a snapshot of the lexer
+/// before every `CHUNK_LEN`th token. Iterating backwards then re-lexes one chunk at a time,
+/// starting from the last snapshot. Memory use is proportional to the number of chunks rather
+/// than the number of tokens, at the cost of lexing everything twice.
+#[derive(Default)]
 pub(crate) struct PdfLexer<'input> {
-    toks: Vec<(usize, Tok<'input>, usize)>,
+    /// Snapshots of the lexer, taken before every `CHUNK_LEN`th token
+    chunks: Vec<PdfLexerForwards<'input>>,
+    /// How many tokens there are in total
+    len: usize,
+    /// The remaining tokens of the chunk we're iterating over
+    current: Vec<(usize, Tok<'input>, usize)>,
+    /// The `endobj` we added to close an object that was missing one
+    closing: Option<(usize, Tok<'input>, usize)>,
+    /// How many arrays and dictionaries the last token lexed is inside
+    depth: usize,
 }
 
 impl<'input> PdfLexer<'input> {
     pub(crate) fn new(input: &'input [u8]) -> Result<PdfLexer<'input>, PdfLexError<'input>> {
-        let lex = PdfLexerForwards::new(input);
-        let toks = lex.collect::<Result<Vec<_>, PdfLexError>>()?;
-        Ok(Self { toks })
+        Self::new_until(input, 0, |_| false)
+    }
+
+    /// Lexes from byte `start` of `input` up to and including the first token for which
+    /// `is_last` returns true (or to the end of `input` if there's no such token). This lets us
+    /// parse a single object out of a larger file.
+    pub(crate) fn new_until(
+        input: &'input [u8],
+        start: usize,
+        mut is_last: impl FnMut(&Tok<'input>) -> bool,
+    ) -> Result<PdfLexer<'input>, PdfLexError<'input>> {
+        let mut lexer = PdfLexerForwards::new_at(input, start);
+        let mut toks = Self::default();
+        while let Some(tok) = toks.lex(&mut lexer) {
+            if is_last(&tok?.1) {
+                break;
+            }
+        }
+        Ok(toks.finish())
+    }
+
+    /// Lexes the indirect object starting at byte `start` of `input`, up to its `endobj`.
+    ///
+    /// In lenient mode, an object without an `endobj` is closed where the next object's header,
+    /// the cross-reference section or the end of the input begins, with a warning.
+    pub(crate) fn new_object(
+        input: &'input [u8],
+        start: usize,
+        ctx: &ParseContext,
+    ) -> Result<PdfLexer<'input>, PdfLexError<'input>> {
+        let mut lexer = PdfLexerForwards::new_at(input, start);
+        let mut toks = Self::default();
+        let mut seen_header = false;
+        // The last two tokens, in case they turn out to be the header of the next object
+        let mut prev: [Option<(usize, Tok)>; 2] = [None, None];
+        while let Some(tok) = toks.lex(&mut lexer) {
+            let (l, tok, _) = tok?;
+            let end = match tok {
+                Tok::EndObj => return Ok(toks.finish()),
+                Tok::Obj if !seen_header => {
+                    seen_header = true;
+                    None
+                }
+                // `N G obj` starts the next object
+                Tok::Obj => match prev {
+                    [Some((l, Tok::Number(_))), Some((_, Tok::Number(_)))] => {
+                        toks.len -= 2;
+                        Some(l)
+                    }
+                    _ => Some(l),
+                },
+                Tok::UnknownTok(b"xref" | b"trailer" | b"startxref") if seen_header => Some(l),
+                _ => None,
+            };
+            if let Some(end) = end
+                && ctx.is_lenient()
+            {
+                toks.len -= 1;
+                return Ok(toks.close_object(end, ctx));
+            }
+            prev = [prev[1], Some((l, tok))];
+        }
+        if ctx.is_lenient() && seen_header {
+            return Ok(toks.close_object(input.len(), ctx));
+        }
+        Ok(toks.finish())
+    }
+
+    /// Lexes the dictionary starting at byte `start` of `input`
+    pub(crate) fn new_dictionary(
+        input: &'input [u8],
+        start: usize,
+    ) -> Result<PdfLexer<'input>, PdfLexError<'input>> {
+        let mut depth = 0usize;
+        Self::new_until(input, start, |tok| {
+            match tok {
+                Tok::DictOpen => depth += 1,
+                Tok::DictClose => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            depth == 0
+        })
+    }
+
+    /// Lexes the next token with `lexer`, taking a snapshot first if it starts a new chunk
+    fn lex(&mut self, lexer: &mut PdfLexerForwards<'input>) -> Option<<Self as Iterator>::Item> {
+        if self.len.is_multiple_of(CHUNK_LEN) {
+            self.chunks.push(lexer.clone());
+        }
+        let tok = lexer.next()?;
+        if let Ok((l, tok, r)) = &tok {
+            match tok {
+                Tok::ArrayOpen | Tok::DictOpen if self.depth >= MAX_NESTING => {
+                    let bytes = &lexer.input[l - lexer.offset..r - lexer.offset];
+                    return Some(Err(PdfLexError::TooDeep(bytes)));
+                }
+                Tok::ArrayOpen | Tok::DictOpen => self.depth += 1,
+                Tok::ArrayClose | Tok::DictClose => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+            self.len += 1;
+        }
+        Some(tok)
+    }
+
+    /// Drops any snapshots past the last token, once we're done lexing
+    fn finish(mut self) -> Self {
+        self.chunks.truncate(self.len.div_ceil(CHUNK_LEN));
+        self
+    }
+
+    /// Adds the `endobj` missing from the end of the tokens, which should be at byte `end`
+    fn close_object(mut self, end: usize, ctx: &ParseContext) -> Self {
+        ctx.warn(WarningKind::MissingEndobj, end);
+        self.closing = Some((end, Tok::EndObj, end));
+        self.finish()
     }
 }
 
@@ -344,6 +714,20 @@ impl<'input> Iterator for PdfLexer<'input> {
     type Item = Spanned<Tok<'input>, usize, PdfLexError<'input>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(Ok(self.toks.pop()?))
+        if let Some(tok) = self.closing.take() {
+            return Some(Ok(tok));
+        }
+        if self.current.is_empty() {
+            let lexer = self.chunks.pop()?;
+            let first = self.chunks.len() * CHUNK_LEN;
+            // This chunk lexed successfully the first time, so it will again
+            for tok in lexer.take(CHUNK_LEN.min(self.len - first)) {
+                match tok {
+                    Ok(tok) => self.current.push(tok),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        }
+        Some(Ok(self.current.pop()?))
     }
 }
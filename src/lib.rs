@@ -1,18 +1,23 @@
 use lalrpop_util::lalrpop_mod;
 
-use crate::lexer::PdfLexer;
-
 // NOTE! This parser expects the token stream backwards. See the comment in `pdf.lalrpop` for
 // details.
 lalrpop_mod!(pdf);
 
-mod ast;
+pub mod ast;
+pub mod content;
+pub mod crypt;
+pub mod document;
+pub mod error;
+pub mod filter;
+pub mod geometry;
 mod lexer;
+pub mod page;
 mod parser_helper;
 #[cfg(test)]
 mod parser_tests;
+pub mod writer;
+pub mod xref;
 
-pub fn parse_pdf(input: &[u8]) {
-    let _ = PdfLexer::new(input);
-}
-
+pub use document::Document;
+pub use error::{Error, ParseMode, Result};
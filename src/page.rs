@@ -0,0 +1,665 @@
+//! The pages of a document, read from the page tree. See PDF Spec section 7.7.3
+
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use crate::Document;
+use crate::ast::object::{Dictionary, Name, Number, Object, Reference};
+use crate::error::Result;
+use crate::filter::decode_stream;
+use crate::geometry::{Matrix, Rectangle};
+
+/// The attributes a page inherits from its ancestors in the page tree if it doesn't set them
+/// itself. See PDF Spec section 7.7.3.4
+static INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// The media box of pages without a valid one: US Letter, which most readers assume
+static DEFAULT_MEDIA_BOX: [i64; 4] = [0, 0, 612, 792];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageTreeError {
+    /// The catalog is missing, or has no `/Pages`
+    MissingPages,
+    /// The referenced node isn't a dictionary, or has no `/Kids` array, or has a kid that isn't
+    /// a reference
+    InvalidNode(Reference),
+    /// The referenced page's `/Contents` isn't a stream or an array of streams
+    InvalidContents(Reference),
+    /// The referenced node appears more than once in the tree, so the tree has a cycle or shares
+    /// a subtree
+    Cycle(Reference),
+}
+
+impl Display for PageTreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageTreeError::MissingPages => write!(f, "Missing page tree"),
+            PageTreeError::InvalidNode(r) => write!(
+                f,
+                "Page tree node {} {} is malformed",
+                r.number, r.generation
+            ),
+            PageTreeError::InvalidContents(r) => {
+                write!(f, "Page {} {} has invalid contents", r.number, r.generation)
+            }
+            PageTreeError::Cycle(r) => write!(
+                f,
+                "Page tree node {} {} appears more than once",
+                r.number, r.generation
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PageTreeError {}
+
+/// A page, a leaf of the page tree
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+    /// The page object
+    pub reference: Reference,
+    /// The page object's dictionary, with the attributes it inherits filled in. Inheritable
+    /// attributes are resolved if they're references.
+    pub dict: Dictionary,
+}
+
+impl Page {
+    /// `/Resources`, the named resources the page's content refers to
+    pub fn resources(&self) -> Option<&Dictionary> {
+        self.dict.get(b"Resources").and_then(Object::as_dictionary)
+    }
+
+    /// The decoded data of the page's content streams. The streams in a `/Contents` array are
+    /// joined with a newline, since a stream can end without whitespace before a token
+    /// continues in the next one. A page without contents is empty. See PDF Spec section 7.8.2
+    pub fn contents(&self, doc: &Document) -> Result<Vec<u8>> {
+        let invalid = || PageTreeError::InvalidContents(self.reference);
+        let streams = match self.dict.get(b"Contents") {
+            Some(contents) => match doc.resolve(contents)? {
+                Object::Array(streams) => streams,
+                Object::Null => Vec::new(),
+                stream => vec![stream],
+            },
+            None => Vec::new(),
+        };
+        let mut data = Vec::new();
+        for (i, stream) in streams.iter().enumerate() {
+            let stream = doc.resolve(stream)?;
+            let stream = stream.as_stream().ok_or_else(invalid)?;
+            if i > 0 {
+                data.push(b'\n');
+            }
+            data.extend_from_slice(&decode_stream(stream)?);
+        }
+        Ok(data)
+    }
+
+    /// `/MediaBox`, the boundaries of the physical medium. It's required, but if it's missing or
+    /// invalid, US Letter is assumed. See PDF Spec section 14.11.2
+    pub fn media_box(&self) -> Rectangle {
+        self.page_box(b"MediaBox").unwrap_or_else(|| {
+            let [x0, y0, x1, y1] = DEFAULT_MEDIA_BOX.map(Number::Integer);
+            Rectangle::new(x0, y0, x1, y1)
+        })
+    }
+
+    /// `/CropBox`, the region the page is clipped to when displayed or printed. It defaults to
+    /// the media box, and is reduced to the part that's within the media box.
+    pub fn crop_box(&self) -> Rectangle {
+        self.bounded_box(b"CropBox", self.media_box())
+    }
+
+    /// `/BleedBox`, the region to clip to in a production environment. Like the trim and art
+    /// boxes, it defaults to the crop box, and is reduced to the part within the media box.
+    pub fn bleed_box(&self) -> Rectangle {
+        self.bounded_box(b"BleedBox", self.crop_box())
+    }
+
+    /// `/TrimBox`, the intended dimensions of the finished page after trimming
+    pub fn trim_box(&self) -> Rectangle {
+        self.bounded_box(b"TrimBox", self.crop_box())
+    }
+
+    /// `/ArtBox`, the extent of the page's meaningful content
+    pub fn art_box(&self) -> Rectangle {
+        self.bounded_box(b"ArtBox", self.crop_box())
+    }
+
+    /// `/Rotate`, the clockwise rotation in degrees applied when displaying or printing,
+    /// normalized to 0, 90, 180 or 270. Values that aren't a multiple of 90 are ignored.
+    pub fn rotate(&self) -> u16 {
+        let rotate = self
+            .dict
+            .get(b"Rotate")
+            .and_then(Object::as_number)
+            .map_or(0.0, Number::as_f64);
+        if rotate.is_finite() && rotate % 90.0 == 0.0 {
+            rotate.rem_euclid(360.0) as u16
+        } else {
+            0
+        }
+    }
+
+    /// `/UserUnit`, the size of a unit of default user space in multiples of 1/72 inch. It
+    /// defaults to 1. See PDF Spec section 14.11.2
+    pub fn user_unit(&self) -> f64 {
+        self.dict
+            .get(b"UserUnit")
+            .and_then(Object::as_number)
+            .map(Number::as_f64)
+            .filter(|unit| unit.is_finite() && *unit > 0.0)
+            .unwrap_or(1.0)
+    }
+
+    /// The width and height of the page as displayed, in 1/72 inch: the crop box, rotated and
+    /// scaled by the user unit
+    pub fn display_size(&self) -> (f64, f64) {
+        let crop_box = self.crop_box();
+        let (width, height) = (crop_box.width(), crop_box.height());
+        let unit = self.user_unit();
+        match self.rotate() {
+            90 | 270 => (height * unit, width * unit),
+            _ => (width * unit, height * unit),
+        }
+    }
+
+    /// Maps default user space to display space, where the crop box fills the rectangle from
+    /// the origin to [`Page::display_size`], after rotation. Display space is measured in 1/72
+    /// inch from the top-left corner, with y increasing downwards.
+    pub fn display_matrix(&self) -> Matrix {
+        let crop_box = self.crop_box();
+        let (x0, y0, x1, y1) = (
+            crop_box.x0.as_f64(),
+            crop_box.y0.as_f64(),
+            crop_box.x1.as_f64(),
+            crop_box.y1.as_f64(),
+        );
+        let rotation = match self.rotate() {
+            90 => Matrix::new(0.0, 1.0, 1.0, 0.0, -y0, -x0),
+            180 => Matrix::new(-1.0, 0.0, 0.0, 1.0, x1, -y0),
+            270 => Matrix::new(0.0, -1.0, -1.0, 0.0, y1, x1),
+            _ => Matrix::new(1.0, 0.0, 0.0, -1.0, -x0, y1),
+        };
+        let unit = self.user_unit();
+        rotation.then(&Matrix::scale(unit, unit))
+    }
+
+    fn page_box(&self, key: &[u8]) -> Option<Rectangle> {
+        self.dict
+            .get(key)
+            .and_then(Object::as_array)
+            .and_then(Rectangle::from_array)
+    }
+
+    /// A box other than the media box, which can't extend beyond the media box. If it doesn't
+    /// overlap the media box at all, it's ignored.
+    fn bounded_box(&self, key: &[u8], default: Rectangle) -> Rectangle {
+        self.page_box(key)
+            .and_then(|rect| rect.intersect(&self.media_box()))
+            .unwrap_or(default)
+    }
+}
+
+/// Every page of a document, in order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pages(Vec<Page>);
+
+impl Pages {
+    /// Walks the page tree from the catalog's `/Pages`
+    pub(crate) fn read(doc: &Document) -> Result<Self> {
+        let catalog = match doc.trailer().get(b"Root") {
+            Some(root) => doc.resolve(root)?,
+            None => return Err(PageTreeError::MissingPages.into()),
+        };
+        let root = catalog
+            .as_dictionary()
+            .and_then(|catalog| catalog.get(b"Pages"))
+            .and_then(Object::as_reference)
+            .ok_or(PageTreeError::MissingPages)?;
+
+        let mut pages = Vec::new();
+        let mut visited = HashSet::new();
+        // The nodes still to visit, last first, with the attributes they inherit. A stack rather
+        // than recursion, so that deep trees can't overflow.
+        let mut stack = vec![(root, Dictionary::new())];
+        while let Some((reference, mut attributes)) = stack.pop() {
+            if !visited.insert(reference) {
+                return Err(PageTreeError::Cycle(reference).into());
+            }
+            let Object::Dictionary(mut dict) = doc.resolve(&Object::Reference(reference))? else {
+                return Err(PageTreeError::InvalidNode(reference).into());
+            };
+            for key in INHERITABLE {
+                if let Some(val) = dict.get(key) {
+                    attributes.insert(Name::from(key), doc.resolve(val)?);
+                }
+            }
+
+            if !is_intermediate(&dict) {
+                for (key, val) in attributes {
+                    dict.insert(key, val);
+                }
+                pages.push(Page { reference, dict });
+                continue;
+            }
+            let kids = match dict.get(b"Kids") {
+                Some(kids) => doc.resolve(kids)?,
+                None => Object::Null,
+            };
+            let kids = kids
+                .as_array()
+                .ok_or(PageTreeError::InvalidNode(reference))?;
+            for kid in kids.iter().rev() {
+                let kid = kid
+                    .as_reference()
+                    .ok_or(PageTreeError::InvalidNode(reference))?;
+                stack.push((kid, attributes.clone()));
+            }
+        }
+        Ok(Self(pages))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The page at `index`, counting from 0
+    pub fn get(&self, index: usize) -> Option<&Page> {
+        self.0.get(index)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Page> {
+        self.0.iter()
+    }
+}
+
+impl IntoIterator for Pages {
+    type Item = Page;
+    type IntoIter = std::vec::IntoIter<Page>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Pages {
+    type Item = &'a Page;
+    type IntoIter = std::slice::Iter<'a, Page>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Whether a page tree node is an intermediate node rather than a page. Some producers leave out
+/// `/Type`, so a node with `/Kids` is taken to be intermediate unless it says it's a page.
+fn is_intermediate(dict: &Dictionary) -> bool {
+    match dict.get(b"Type").and_then(Object::as_name) {
+        Some(ty) if ty.as_bytes() == b"Pages" => true,
+        Some(ty) if ty.as_bytes() == b"Page" => false,
+        _ => dict.contains_key(b"Kids"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::object::Number;
+    use crate::document::Version;
+    use crate::error::Error;
+    use crate::filter::{Flate, encode_stream};
+    use crate::writer::Writer;
+
+    fn reference(number: u32) -> Reference {
+        Reference {
+            number,
+            generation: 0,
+        }
+    }
+
+    fn name(name: &[u8]) -> Object {
+        Object::Name(Name::from(name))
+    }
+
+    fn int(val: i64) -> Object {
+        Object::Number(Number::Integer(val))
+    }
+
+    fn dict(entries: &[(&[u8], Object)]) -> Object {
+        Object::Dictionary(
+            entries
+                .iter()
+                .map(|(key, val)| (Name::from(*key), val.clone()))
+                .collect(),
+        )
+    }
+
+    fn kids(numbers: &[u32]) -> Object {
+        Object::Array(
+            numbers
+                .iter()
+                .map(|n| Object::Reference(reference(*n)))
+                .collect(),
+        )
+    }
+
+    fn media_box(width: i64, height: i64) -> Object {
+        Object::Array(vec![int(0), int(0), int(width), int(height)])
+    }
+
+    fn rect(x0: i64, y0: i64, x1: i64, y1: i64) -> Rectangle {
+        let [x0, y0, x1, y1] = [x0, y0, x1, y1].map(Number::Integer);
+        Rectangle::new(x0, y0, x1, y1)
+    }
+
+    /// A page with the given entries, outside of any document
+    fn page(entries: &[(&[u8], Object)]) -> Page {
+        let Object::Dictionary(dict) = dict(entries) else {
+            unreachable!()
+        };
+        Page {
+            reference: reference(1),
+            dict,
+        }
+    }
+
+    /// Writes a document whose catalog is object 1 and whose page tree root is object 2, with
+    /// the rest of the objects numbered from 3
+    fn open(nodes: Vec<Object>) -> Document {
+        let mut writer = Writer::new(Version { major: 1, minor: 7 });
+        let root = writer.add(dict(&[
+            (b"Type", name(b"Catalog")),
+            (b"Pages", Object::Reference(reference(2))),
+        ]));
+        for node in nodes {
+            writer.add(node);
+        }
+        writer
+            .trailer_mut()
+            .insert(Name::from(b"Root"), Object::Reference(root));
+        Document::open(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn inherited_attributes() {
+        let doc = open(vec![
+            // 2: the root, with the defaults
+            dict(&[
+                (b"Type", name(b"Pages")),
+                (b"Kids", kids(&[3, 5])),
+                (b"Count", int(3)),
+                (b"MediaBox", media_box(612, 792)),
+                (b"Resources", Object::Reference(reference(7))),
+                (b"Rotate", int(90)),
+            ]),
+            // 3: overrides the media box, without `/Type`
+            dict(&[(b"Kids", kids(&[4, 6])), (b"MediaBox", media_box(100, 100))]),
+            // 4
+            dict(&[(b"Type", name(b"Page")), (b"Rotate", int(0))]),
+            // 5: a page directly under the root
+            dict(&[
+                (b"Type", name(b"Page")),
+                (b"CropBox", media_box(10, 10)),
+                (b"Resources", dict(&[])),
+            ]),
+            // 6
+            dict(&[
+                (b"Type", name(b"Page")),
+                (b"Parent", Object::Reference(reference(3))),
+            ]),
+            // 7: shared resources
+            dict(&[(b"ProcSet", Object::Array(vec![name(b"PDF")]))]),
+        ]);
+        let pages = doc.pages().unwrap();
+        assert_eq!(pages.len(), 3);
+        let references: Vec<_> = pages.iter().map(|page| page.reference).collect();
+        assert_eq!(references, [reference(4), reference(6), reference(5)]);
+
+        let page = pages.get(0).unwrap();
+        assert_eq!(page.media_box(), rect(0, 0, 100, 100));
+        assert_eq!(page.rotate(), 0);
+        assert!(!page.dict.contains_key(b"CropBox"));
+        // Inherited references are resolved
+        assert!(page.resources().unwrap().contains_key(b"ProcSet"));
+
+        let page = pages.get(1).unwrap();
+        assert_eq!(page.rotate(), 90);
+        assert_eq!(page.media_box(), rect(0, 0, 100, 100));
+        assert!(page.dict.contains_key(b"Parent"));
+
+        let page = pages.get(2).unwrap();
+        assert_eq!(page.media_box(), rect(0, 0, 612, 792));
+        assert_eq!(page.crop_box(), rect(0, 0, 10, 10));
+        assert_eq!(page.resources(), Some(&Dictionary::new()));
+        assert!(pages.get(3).is_none());
+
+        let rotations: Vec<_> = pages.into_iter().map(|page| page.rotate()).collect();
+        assert_eq!(rotations, [0, 90, 90]);
+    }
+
+    #[test]
+    fn cycles() {
+        // 3 is its own grandparent
+        let doc = open(vec![
+            dict(&[(b"Type", name(b"Pages")), (b"Kids", kids(&[3]))]),
+            dict(&[(b"Type", name(b"Pages")), (b"Kids", kids(&[4]))]),
+            dict(&[(b"Type", name(b"Pages")), (b"Kids", kids(&[3]))]),
+        ]);
+        assert_eq!(
+            doc.pages(),
+            Err(Error::PageTree(PageTreeError::Cycle(reference(3))))
+        );
+
+        // The same page twice
+        let doc = open(vec![
+            dict(&[(b"Type", name(b"Pages")), (b"Kids", kids(&[3, 3]))]),
+            dict(&[(b"Type", name(b"Page"))]),
+        ]);
+        assert_eq!(
+            doc.pages(),
+            Err(Error::PageTree(PageTreeError::Cycle(reference(3))))
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let doc = open(vec![dict(&[(b"Type", name(b"Pages")), (b"Kids", int(1))])]);
+        assert_eq!(
+            doc.pages(),
+            Err(Error::PageTree(PageTreeError::InvalidNode(reference(2))))
+        );
+
+        // A missing kid resolves to `null`
+        let doc = open(vec![dict(&[
+            (b"Type", name(b"Pages")),
+            (b"Kids", kids(&[9])),
+        ])]);
+        assert_eq!(
+            doc.pages(),
+            Err(Error::PageTree(PageTreeError::InvalidNode(reference(9))))
+        );
+
+        let doc = open(vec![dict(&[
+            (b"Type", name(b"Pages")),
+            (b"Kids", Object::Array(vec![dict(&[])])),
+        ])]);
+        assert_eq!(
+            doc.pages(),
+            Err(Error::PageTree(PageTreeError::InvalidNode(reference(2))))
+        );
+
+        let mut writer = Writer::new(Version { major: 1, minor: 7 });
+        let root = writer.add(dict(&[(b"Type", name(b"Catalog"))]));
+        writer
+            .trailer_mut()
+            .insert(Name::from(b"Root"), Object::Reference(root));
+        let doc = Document::open(writer.finish().unwrap()).unwrap();
+        assert_eq!(
+            doc.pages(),
+            Err(Error::PageTree(PageTreeError::MissingPages))
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let doc = open(vec![dict(&[
+            (b"Type", name(b"Pages")),
+            (b"Kids", Object::Array(Vec::new())),
+            (b"Count", int(0)),
+        ])]);
+        let pages = doc.pages().unwrap();
+        assert!(pages.is_empty());
+        assert_eq!(pages.iter().count(), 0);
+    }
+
+    #[test]
+    fn boxes() {
+        let boxes = |entries: &[(&[u8], Object)]| {
+            let page = page(entries);
+            [
+                page.media_box(),
+                page.crop_box(),
+                page.bleed_box(),
+                page.trim_box(),
+                page.art_box(),
+            ]
+        };
+        let real = |val: f64| Object::Number(Number::Real(val));
+        let letter = rect(0, 0, 612, 792);
+        assert_eq!(boxes(&[]), [letter; 5]);
+
+        // Inverted corners, and a crop box partly outside the media box
+        let cropped = rect(0, 100, 300, 792);
+        assert_eq!(
+            boxes(&[
+                (
+                    b"MediaBox",
+                    Object::Array(vec![int(612), int(792), int(0), int(0)])
+                ),
+                (
+                    b"CropBox",
+                    Object::Array(vec![int(-50), int(100), int(300), int(900)])
+                ),
+                (b"TrimBox", media_box(10, 20)),
+            ]),
+            [letter, cropped, cropped, rect(0, 0, 10, 20), cropped]
+        );
+
+        // Boxes that are invalid or entirely outside the media box are ignored
+        let page = page(&[
+            (
+                b"MediaBox",
+                Object::Array(vec![int(0), int(0), real(100.5)]),
+            ),
+            (b"CropBox", media_box(100, 100)),
+            (
+                b"ArtBox",
+                Object::Array(vec![int(700), int(0), int(800), int(10)]),
+            ),
+        ]);
+        assert_eq!(page.media_box(), letter);
+        assert_eq!(page.art_box(), rect(0, 0, 100, 100));
+    }
+
+    #[test]
+    fn rotation_and_user_unit() {
+        let rotate = |val: Object| page(&[(b"Rotate", val)]).rotate();
+        assert_eq!(rotate(int(90)), 90);
+        assert_eq!(rotate(int(-90)), 270);
+        assert_eq!(rotate(int(540)), 180);
+        assert_eq!(rotate(Object::Number(Number::Real(270.0))), 270);
+        assert_eq!(rotate(int(45)), 0);
+        assert_eq!(rotate(name(b"90")), 0);
+
+        let user_unit = |val: Object| page(&[(b"UserUnit", val)]).user_unit();
+        assert_eq!(page(&[]).user_unit(), 1.0);
+        assert_eq!(user_unit(int(2)), 2.0);
+        assert_eq!(user_unit(Object::Number(Number::Real(0.5))), 0.5);
+        assert_eq!(user_unit(int(-1)), 1.0);
+    }
+
+    #[test]
+    fn display_matrix() {
+        // A crop box 200 wide and 100 high
+        let crop_box = Object::Array(vec![int(10), int(20), int(210), int(120)]);
+        let corners = |rotate: i64, unit: i64| {
+            let page = page(&[
+                (b"CropBox", crop_box.clone()),
+                (b"Rotate", int(rotate)),
+                (b"UserUnit", int(unit)),
+            ]);
+            let matrix = page.display_matrix();
+            // The top-left, top-right and bottom-left corners
+            let corners = [(10.0, 120.0), (210.0, 120.0), (10.0, 20.0)]
+                .map(|(x, y)| matrix.transform_point(x, y));
+            (page.display_size(), corners)
+        };
+        assert_eq!(
+            corners(0, 1),
+            ((200.0, 100.0), [(0.0, 0.0), (200.0, 0.0), (0.0, 100.0)])
+        );
+        assert_eq!(
+            corners(90, 1),
+            ((100.0, 200.0), [(100.0, 0.0), (100.0, 200.0), (0.0, 0.0)])
+        );
+        assert_eq!(
+            corners(180, 1),
+            ((200.0, 100.0), [(200.0, 100.0), (0.0, 100.0), (200.0, 0.0)])
+        );
+        assert_eq!(
+            corners(270, 1),
+            ((100.0, 200.0), [(0.0, 200.0), (0.0, 0.0), (100.0, 200.0)])
+        );
+        assert_eq!(
+            corners(0, 2),
+            ((400.0, 200.0), [(0.0, 0.0), (400.0, 0.0), (0.0, 200.0)])
+        );
+    }
+
+    #[test]
+    fn contents() {
+        let stream = |data: &[u8]| Object::Stream(encode_stream(data, &[&Flate::default()]));
+        let doc = open(vec![
+            dict(&[(b"Type", name(b"Pages")), (b"Kids", kids(&[3, 4, 5, 6]))]),
+            // 3: split in the middle of the operands, without whitespace at the end
+            dict(&[
+                (b"Type", name(b"Page")),
+                (
+                    b"Contents",
+                    Object::Array(vec![
+                        Object::Reference(reference(7)),
+                        Object::Reference(reference(8)),
+                    ]),
+                ),
+            ]),
+            // 4
+            dict(&[
+                (b"Type", name(b"Page")),
+                (b"Contents", Object::Reference(reference(8))),
+            ]),
+            // 5
+            dict(&[(b"Type", name(b"Page"))]),
+            // 6
+            dict(&[(b"Type", name(b"Page")), (b"Contents", int(1))]),
+            stream(b"q 1 0 0 1"),
+            stream(b"0 0 cm Q"),
+        ]);
+        let pages = doc.pages().unwrap();
+        assert_eq!(
+            pages.get(0).unwrap().contents(&doc).unwrap(),
+            b"q 1 0 0 1\n0 0 cm Q"
+        );
+        assert_eq!(pages.get(1).unwrap().contents(&doc).unwrap(), b"0 0 cm Q");
+        assert_eq!(pages.get(2).unwrap().contents(&doc).unwrap(), b"");
+        assert_eq!(
+            pages.get(3).unwrap().contents(&doc),
+            Err(Error::PageTree(PageTreeError::InvalidContents(reference(
+                6
+            ))))
+        );
+    }
+}
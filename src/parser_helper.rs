@@ -1,6 +1,47 @@
 //! Helper functions called from the parser
 
+use std::cell::RefCell;
+
 use crate::ast;
+use crate::error::{ParseMode, Warning, WarningKind};
+use crate::lexer::is_whitespace;
+
+/// State shared by everything involved in parsing an object: how strict to be, and the
+/// deviations from the spec we've accepted so far
+#[derive(Debug, Default)]
+pub(crate) struct ParseContext {
+    mode: ParseMode,
+    warnings: RefCell<Vec<Warning>>,
+}
+
+impl ParseContext {
+    pub(crate) fn new(mode: ParseMode) -> Self {
+        Self {
+            mode,
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn mode(&self) -> ParseMode {
+        self.mode
+    }
+
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.mode == ParseMode::Lenient
+    }
+
+    pub(crate) fn warn(&self, kind: WarningKind, offset: usize) {
+        self.warnings.borrow_mut().push(Warning {
+            kind,
+            offset,
+            object: None,
+        });
+    }
+
+    pub(crate) fn into_warnings(self) -> Vec<Warning> {
+        self.warnings.into_inner()
+    }
+}
 
 pub(crate) fn handle_raw_str_escapes(val: &[u8]) -> Vec<u8> {
     let mut it = val.iter().peekable();
@@ -21,9 +62,12 @@ pub(crate) fn handle_raw_str_escapes(val: &[u8]) -> Vec<u8> {
             }
 
             Some(b'\\') => {
-                // We unwrap because a string cannot end with an unescaped `\`, since then it would
-                // be escaping the end delimiter
-                match **it.peek().unwrap() {
+                // The lexer won't produce a string ending with an unescaped `\`, since then it
+                // would be escaping the end delimiter. If we get one anyway, drop it.
+                let Some(escaped) = it.peek() else {
+                    return out;
+                };
+                match **escaped {
                     b'n' => {
                         out.push(b'\n');
                         it.next();
@@ -127,12 +171,13 @@ pub(crate) fn handle_raw_str_escapes(val: &[u8]) -> Vec<u8> {
     }
 }
 
-/// Decodes the hex representation of the string content into the actual bytes.
+/// Decodes the hex representation of the string content into the actual bytes. Whitespace is
+/// ignored, per PDF Spec section 7.3.4.3
 ///
-/// Returns `None` if any of the characters are outside the range `b'0'..=b'9' | b'a'..=b'f' |
-/// b'A'..=b'F'`
+/// Returns `None` if any of the other characters are outside the range `b'0'..=b'9' |
+/// b'a'..=b'f' | b'A'..=b'F'`
 pub(crate) fn handle_hex_str(val: &[u8]) -> Option<Vec<u8>> {
-    let mut it = val.iter();
+    let mut it = val.iter().filter(|c| !is_whitespace(**c));
     let mut out = Vec::with_capacity(val.len() / 2 + 1);
     loop {
         let Some(digit1) = it.next() else {
@@ -146,7 +191,8 @@ pub(crate) fn handle_hex_str(val: &[u8]) -> Option<Vec<u8>> {
     }
 }
 
-fn char_to_val(c: u8) -> Option<u8> {
+/// The value of the hex digit `c`
+pub(crate) fn char_to_val(c: u8) -> Option<u8> {
     match c {
         b'0'..=b'9' => Some(c - b'0'),
         b'a'..=b'f' => Some(c - b'a' + 10),
@@ -155,10 +201,15 @@ fn char_to_val(c: u8) -> Option<u8> {
     }
 }
 
+/// Decodes the `#xx` escapes in a name token, which includes its leading `/`.
+///
+/// Returns `None` if an escape isn't followed by two hex digits.
 pub(crate) fn handle_name_escapes(val: &[u8]) -> Option<Vec<u8>> {
     let mut it = val.iter();
     // Consume the leading b'/'
-    assert!(it.next() == Some(&b'/'));
+    if it.next() != Some(&b'/') {
+        return None;
+    }
 
     let mut out = Vec::with_capacity(val.len());
 
@@ -175,27 +226,118 @@ pub(crate) fn handle_name_escapes(val: &[u8]) -> Option<Vec<u8>> {
     }
 }
 
-pub(crate) fn handle_number(mut num: &[u8]) -> ast::object::Number {
+/// Converts a number token, which starts at byte `offset`, into a number. See PDF Spec section
+/// 7.3.3
+///
+/// Returns `None` if the token isn't a valid number (e.g. `1.2.3`, `--5` or `-`), or is an integer
+/// too large to represent. In lenient mode these are read the way Acrobat reads them instead, with
+/// a warning.
+pub(crate) fn handle_number(
+    ctx: &ParseContext,
+    offset: usize,
+    num: &[u8],
+) -> Option<ast::object::Number> {
+    use ast::object::Number;
+
+    if let Some(val) = handle_strict_number(num) {
+        return Some(val);
+    }
+    if !ctx.is_lenient() {
+        return None;
+    }
+
+    // Any number of leading signs, where a single `-` makes the number negative
+    let signs = num.iter().take_while(|c| matches!(c, b'+' | b'-')).count();
+    if signs > 1 {
+        ctx.warn(WarningKind::DoubleSign, offset);
+    }
+    let is_negative = num[..signs].contains(&b'-');
+
+    // Followed by digits with at most one decimal point. Anything after that is ignored.
+    let rest = &num[signs..];
+    let whole_len = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+    let (whole, rest) = rest.split_at(whole_len);
+    let frac = rest
+        .strip_prefix(b".")
+        .map(|frac| &frac[..frac.iter().take_while(|c| c.is_ascii_digit()).count()]);
+    let frac_len = frac.map_or(0, |frac| frac.len() + 1);
+    if whole.is_empty() && frac.is_none_or(<[u8]>::is_empty)
+        || signs + whole_len + frac_len < num.len()
+    {
+        ctx.warn(WarningKind::MalformedNumber, offset);
+    }
+
+    let val = match frac {
+        Some(frac) => Number::Real(handle_real_val(whole, frac).unwrap_or(0.0)),
+        None if whole.is_empty() => Number::Integer(0),
+        None => {
+            let whole = str::from_utf8(whole).ok()?;
+            match whole.parse() {
+                Ok(val) => Number::Integer(val),
+                Err(_) => {
+                    ctx.warn(WarningKind::IntegerOverflow, offset);
+                    Number::Real(whole.parse().ok()?)
+                }
+            }
+        }
+    };
+
+    Some(match val {
+        Number::Integer(i) if is_negative => Number::Integer(-i),
+        Number::Real(r) if is_negative => Number::Real(-r),
+        val => val,
+    })
+}
+
+fn handle_strict_number(mut num: &[u8]) -> Option<ast::object::Number> {
     use ast::object::Number;
 
     let mut is_negative = false;
-    if num[0] == b'+' {
+    if let Some(b'+') = num.first() {
         num = &num[1..];
-    } else if num[0] == b'-' {
+    } else if let Some(b'-') = num.first() {
         num = &num[1..];
         is_negative = true;
     }
 
     if let Some(decimal_point) = num.iter().position(|c| *c == b'.') {
-        let val = handle_real_val(&num[..decimal_point], &num[decimal_point + 1..]).unwrap();
-        Number::Real(if is_negative { -1.0 * val } else { val })
+        let val = handle_real_val(&num[..decimal_point], &num[decimal_point + 1..])?;
+        Some(Number::Real(if is_negative { -val } else { val }))
     } else {
-        let val = str::from_utf8(num).unwrap().parse().unwrap();
-        Number::Integer(if is_negative { -1 * val } else { val })
+        if num.is_empty() || !num.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let val: i64 = str::from_utf8(num).ok()?.parse().ok()?;
+        Some(Number::Integer(if is_negative { -val } else { val }))
+    }
+}
+
+/// Converts the object number and generation number tokens of an indirect object or reference
+/// into a [`ast::object::Reference`].
+///
+/// Returns `None` if either isn't a plain non-negative integer, or is out of range. See PDF Spec
+/// section 7.3.10
+pub(crate) fn handle_object_id(num: &[u8], generation: &[u8]) -> Option<ast::object::Reference> {
+    fn parse_digits<T: std::str::FromStr>(val: &[u8]) -> Option<T> {
+        if val.is_empty() || !val.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        str::from_utf8(val).ok()?.parse().ok()
     }
+
+    Some(ast::object::Reference {
+        number: parse_digits(num)?,
+        generation: parse_digits(generation)?,
+    })
 }
 
 fn handle_real_val(whole: &[u8], frac: &[u8]) -> Option<f64> {
+    if whole.is_empty() && frac.is_empty() {
+        return None;
+    }
+    if !whole.iter().chain(frac).all(u8::is_ascii_digit) {
+        return None;
+    }
     let whole_str = str::from_utf8(whole).ok()?;
     let frac_str = str::from_utf8(frac).ok()?;
     let mut owned_string = String::with_capacity(whole.len() + frac.len() + 1);
@@ -1,6 +1,12 @@
 //! Since the parser is generated by LALRPOP, we can't really include tests alongside the code
 //! being tested. So, tests for the parser live in this module.
 
+mod errors;
+mod indirect;
+mod lenient;
+#[allow(clippy::bool_assert_comparison, clippy::unnecessary_cast)]
 mod literal;
 mod name;
+mod object;
+mod stream;
 mod string;
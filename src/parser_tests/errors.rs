@@ -0,0 +1,150 @@
+//! Tests for the errors produced from malformed input, which should never panic
+
+use crate::ast::object::{Object, Reference};
+use crate::error::{ParseError, ParseErrorKind, ParseMode};
+use crate::lexer::PdfLexer;
+use crate::parser_helper::ParseContext;
+use crate::pdf::ObjectParser;
+use crate::xref::parse_indirect_object;
+
+fn parse(inp: &[u8]) -> Result<Object, ParseError> {
+    let lexer = PdfLexer::new(inp).map_err(|e| ParseError::from_lex(inp, e))?;
+    ObjectParser::new()
+        .parse(inp, &ParseContext::default(), lexer)
+        .map_err(|e| ParseError::from_parse(inp, 0, e))
+}
+
+#[test]
+fn invalid_numbers() {
+    for inp in [
+        &b"--5"[..],
+        b"1.2.3",
+        b"-",
+        b"+",
+        b".",
+        b"-.",
+        b"99999999999999999999",
+    ] {
+        let err = parse(inp).unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::InvalidNumber);
+        assert_eq!(err.offset, 0);
+        assert_eq!(err.bytes, inp.to_vec());
+    }
+
+    let err = parse(b"[1 2 3..4]").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::InvalidNumber);
+    assert_eq!(err.offset, 5);
+    assert_eq!(err.bytes, b"3..4".to_vec());
+}
+
+#[test]
+fn invalid_names() {
+    for inp in [&b"/A#"[..], b"/A#4", b"/A#zz", b"/#4G"] {
+        let err = parse(inp).unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::InvalidName);
+        assert_eq!(err.bytes, inp.to_vec());
+    }
+}
+
+#[test]
+fn unexpected_char() {
+    let err = parse(b"<</A <4x>>>").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::UnexpectedChar);
+    assert_eq!(err.offset, 7);
+    assert_eq!(err.bytes, b"x".to_vec());
+}
+
+#[test]
+fn unexpected_token() {
+    // A dictionary key that isn't a name
+    let err = parse(b"<</A 1 2 3>>").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(err.offset, 7);
+    assert_eq!(err.bytes, b"2".to_vec());
+    assert!(err.expected.contains(&"name".to_string()));
+}
+
+#[test]
+fn missing_tokens() {
+    let err = parse(b"1 2]").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::MissingTokens);
+    assert_eq!(err.offset, 0);
+    assert!(err.expected.contains(&"`[`".to_string()));
+}
+
+#[test]
+fn leading_token() {
+    let err = parse(b"1 2").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(err.offset, 0);
+    assert_eq!(err.bytes, b"1".to_vec());
+}
+
+#[test]
+fn indirect_object() {
+    let inp = b"%PDF-1.4\n3 0 obj\n[1 0 R --2]\nendobj\n";
+    let err = parse_indirect_object(inp, 9, &ParseContext::default())
+        .unwrap_err()
+        .in_object(Reference {
+            number: 3,
+            generation: 0,
+        });
+    assert_eq!(err.kind, ParseErrorKind::InvalidNumber);
+    assert_eq!(err.offset, 24);
+    assert_eq!(
+        err.to_string(),
+        "Invalid number `--2` at byte 24 in object 3 0"
+    );
+
+    // Starting partway through the object header
+    let inp = b"%PDF-1.4\n3 0 obj\n(x)\nendobj\n";
+    let err = parse_indirect_object(inp, 11, &ParseContext::default()).unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::MissingTokens);
+    assert_eq!(err.offset, 11);
+}
+
+#[test]
+fn no_panics() {
+    let inputs: &[&[u8]] = &[
+        b"",
+        b"(",
+        b")",
+        b"(\\",
+        b"<",
+        b">",
+        b"<<",
+        b">>",
+        b"<<<<>>",
+        b"[[[",
+        b"]]]",
+        b"/",
+        b"//",
+        b"/#",
+        b"R",
+        b"0 R",
+        b"-0 -0 R",
+        b"obj",
+        b"endobj",
+        b"stream",
+        b"endstream",
+        b"<<>>stream",
+        b"<<>>stream\nendstream",
+        b"<</Length -1>>stream\nendstream",
+        b"<</Length 99999999999999999999>>stream\nendstream",
+        b"1 0 obj",
+        b"+-.",
+        b"\x00\xff\xfe",
+        b"{}",
+        b"%",
+        b"thisisaverylongkeywordthatgoesonandonandon",
+    ];
+    for inp in inputs {
+        let _ = parse(inp);
+        for mode in [ParseMode::Strict, ParseMode::Lenient] {
+            let ctx = ParseContext::new(mode);
+            let _ = parse_indirect_object(inp, 0, &ctx);
+            let _ = parse_indirect_object(inp, inp.len() + 10, &ctx);
+            let _ = crate::Document::open_with(inp.to_vec(), mode);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Tests for indirect objects and references to them
+
+mod reference {
+    use crate::ast::object::{Number, Object, Reference};
+    use crate::lexer::PdfLexer;
+    use crate::parser_helper::ParseContext;
+    use crate::pdf::{ObjectParser, ReferenceParser};
+
+    #[test]
+    fn smoke() {
+        let inp = b"12 0 R";
+        assert_eq!(
+            ReferenceParser::new()
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+                .unwrap(),
+            Reference {
+                number: 12,
+                generation: 0
+            }
+        );
+    }
+
+    #[test]
+    fn in_array() {
+        let inp = b"[1 0 R 5 2 3 R]";
+        assert_eq!(
+            ObjectParser::new()
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+                .unwrap(),
+            Object::Array(vec![
+                Object::Reference(Reference {
+                    number: 1,
+                    generation: 0
+                }),
+                Object::Number(Number::Integer(5)),
+                Object::Reference(Reference {
+                    number: 2,
+                    generation: 3
+                }),
+            ])
+        );
+    }
+
+    #[test]
+    fn in_dictionary() {
+        let inp = b"<</Pages 2 0 R/Count 3>>";
+        let obj = ObjectParser::new()
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+            .unwrap();
+        let dict = obj.as_dictionary().unwrap();
+        assert_eq!(
+            dict.get(b"Pages"),
+            Some(&Object::Reference(Reference {
+                number: 2,
+                generation: 0
+            }))
+        );
+        assert_eq!(
+            dict.get(b"Count"),
+            Some(&Object::Number(Number::Integer(3)))
+        );
+    }
+
+    #[test]
+    fn failure() {
+        for inp in [&b"-1 0 R"[..], b"1 -0 R", b"1.0 0 R", b"1 99999 R", b"0 R"] {
+            assert!(
+                ReferenceParser::new()
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+                    .is_err()
+            );
+        }
+    }
+}
+
+mod indirect_object {
+    use crate::ast::object::{IndirectObject, Object, PdfString, Reference};
+    use crate::lexer::PdfLexer;
+    use crate::parser_helper::ParseContext;
+    use crate::pdf::IndirectObjectParser;
+
+    #[test]
+    fn smoke() {
+        let inp = b"12 0 obj\n(Brillig)\nendobj";
+        assert_eq!(
+            IndirectObjectParser::new()
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+                .unwrap(),
+            IndirectObject {
+                id: Reference {
+                    number: 12,
+                    generation: 0
+                },
+                object: Object::String(PdfString::Literal(b"Brillig".to_vec())),
+            }
+        );
+    }
+
+    #[test]
+    fn dictionary() {
+        let inp = b"1 0 obj<</Type/Catalog/Pages 2 0 R>>endobj";
+        let obj = IndirectObjectParser::new()
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+            .unwrap();
+        assert_eq!(
+            obj.id,
+            Reference {
+                number: 1,
+                generation: 0
+            }
+        );
+        assert_eq!(
+            obj.object.as_dictionary().unwrap().get(b"Pages"),
+            Some(&Object::Reference(Reference {
+                number: 2,
+                generation: 0
+            }))
+        );
+    }
+
+    #[test]
+    fn failure() {
+        for inp in [
+            &b"12 0 obj (x)"[..],
+            b"0 obj (x) endobj",
+            b"12 0 obj endobj",
+        ] {
+            assert!(
+                IndirectObjectParser::new()
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+                    .is_err()
+            );
+        }
+    }
+}
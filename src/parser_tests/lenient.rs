@@ -0,0 +1,146 @@
+//! Tests for the deviations from the spec accepted in lenient mode
+
+use crate::ast::object::{Number, Object, Reference};
+use crate::error::{ParseMode, Warning, WarningKind};
+use crate::lexer::PdfLexer;
+use crate::parser_helper::ParseContext;
+use crate::pdf::ObjectParser;
+use crate::xref::parse_indirect_object;
+
+fn lenient(inp: &[u8]) -> (Object, Vec<WarningKind>) {
+    let ctx = ParseContext::new(ParseMode::Lenient);
+    let obj = ObjectParser::new()
+        .parse(inp, &ctx, PdfLexer::new(inp).unwrap())
+        .unwrap();
+    let warnings = ctx.into_warnings().into_iter().map(|w| w.kind).collect();
+    (obj, warnings)
+}
+
+#[test]
+fn double_signs() {
+    assert_eq!(
+        lenient(b"--5"),
+        (
+            Object::Number(Number::Integer(-5)),
+            vec![WarningKind::DoubleSign]
+        )
+    );
+    assert_eq!(
+        lenient(b"+-2.5"),
+        (
+            Object::Number(Number::Real(-2.5)),
+            vec![WarningKind::DoubleSign]
+        )
+    );
+}
+
+#[test]
+fn malformed_numbers() {
+    assert_eq!(
+        lenient(b"1.2.3"),
+        (
+            Object::Number(Number::Real(1.2)),
+            vec![WarningKind::MalformedNumber]
+        )
+    );
+    assert_eq!(
+        lenient(b"4-2"),
+        (
+            Object::Number(Number::Integer(4)),
+            vec![WarningKind::MalformedNumber]
+        )
+    );
+    for inp in [&b"-"[..], b"+", b".", b"-."] {
+        let (obj, warnings) = lenient(inp);
+        assert_eq!(obj.as_number().map(Number::as_f64), Some(0.0));
+        assert_eq!(warnings, vec![WarningKind::MalformedNumber]);
+    }
+}
+
+#[test]
+fn integer_overflow() {
+    assert_eq!(
+        lenient(b"-99999999999999999999"),
+        (
+            Object::Number(Number::Real(-99999999999999999999.0)),
+            vec![WarningKind::IntegerOverflow]
+        )
+    );
+}
+
+#[test]
+fn valid_numbers() {
+    // Valid numbers are read the same as in strict mode, without warnings
+    assert_eq!(
+        lenient(b"[-5 +.5 12]"),
+        (
+            Object::Array(vec![
+                Object::Number(Number::Integer(-5)),
+                Object::Number(Number::Real(0.5)),
+                Object::Number(Number::Integer(12)),
+            ]),
+            vec![]
+        )
+    );
+}
+
+#[test]
+fn warning_offsets() {
+    let ctx = ParseContext::new(ParseMode::Lenient);
+    let inp = b"[1 --2 3]";
+    ObjectParser::new()
+        .parse(inp, &ctx, PdfLexer::new(inp).unwrap())
+        .unwrap();
+    assert_eq!(
+        ctx.into_warnings(),
+        vec![Warning {
+            kind: WarningKind::DoubleSign,
+            offset: 3,
+            object: None,
+        }]
+    );
+}
+
+#[test]
+fn missing_endobj() {
+    let id = Reference {
+        number: 1,
+        generation: 0,
+    };
+    for (inp, end) in [
+        (&b"1 0 obj\n(one)\n2 0 obj\n(two)\nendobj\n"[..], 14),
+        (b"1 0 obj\n<</Length 0>>stream\n\nendstream\nxref\n", 39),
+        (b"1 0 obj\n42\ntrailer\n<<>>", 11),
+        (b"1 0 obj\n42", 10),
+    ] {
+        let ctx = ParseContext::new(ParseMode::Lenient);
+        let obj = parse_indirect_object(inp, 0, &ctx).unwrap();
+        assert_eq!(obj.id, id);
+        assert_eq!(
+            ctx.into_warnings(),
+            vec![Warning {
+                kind: WarningKind::MissingEndobj,
+                offset: end,
+                object: None,
+            }]
+        );
+
+        assert!(parse_indirect_object(inp, 0, &ParseContext::default()).is_err());
+    }
+
+    // The next object's header may be split from the `obj` keyword across the lexer's chunks
+    for len in 250..260 {
+        let elems = vec!["0"; len].join(" ");
+        let inp = format!("1 0 obj\n[{elems}]\n2 0 obj\n(two)\nendobj\n");
+        let ctx = ParseContext::new(ParseMode::Lenient);
+        let obj = parse_indirect_object(inp.as_bytes(), 0, &ctx).unwrap();
+        assert_eq!(obj.object.as_array().map(<[Object]>::len), Some(len));
+        assert_eq!(ctx.into_warnings().len(), 1);
+    }
+
+    // An object that does have an `endobj` doesn't get another one
+    let ctx = ParseContext::new(ParseMode::Lenient);
+    let inp = b"1 0 obj\n(one)\nendobj\n2 0 obj\n(two)\nendobj\n";
+    parse_indirect_object(inp, 0, &ctx).unwrap();
+    assert!(ctx.into_warnings().is_empty());
+}
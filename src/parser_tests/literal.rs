@@ -2,6 +2,7 @@
 
 mod boolean {
     use crate::lexer::PdfLexer;
+    use crate::parser_helper::ParseContext;
     use crate::pdf::BooleanParser;
 
     #[test]
@@ -9,7 +10,7 @@ mod boolean {
         let inp = b"true";
         assert_eq!(
             BooleanParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             true
         );
@@ -20,7 +21,7 @@ mod boolean {
         let inp = b"false";
         assert_eq!(
             BooleanParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             false
         );
@@ -31,6 +32,7 @@ mod number {
     mod integer {
         use crate::ast::object::Number;
         use crate::lexer::PdfLexer;
+        use crate::parser_helper::ParseContext;
         use crate::pdf::NumberParser;
 
         #[test]
@@ -38,7 +40,7 @@ mod number {
             let inp = b"0";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Integer(0)
             );
@@ -49,7 +51,7 @@ mod number {
             let inp = b"00";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Integer(0)
             );
@@ -57,7 +59,7 @@ mod number {
             let inp = b"07";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Integer(7)
             );
@@ -65,7 +67,7 @@ mod number {
             let inp = b"09";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Integer(9)
             );
@@ -73,7 +75,7 @@ mod number {
             let inp = b"0900";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Integer(900)
             );
@@ -84,7 +86,7 @@ mod number {
             let inp = b"-0";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Integer(-0)
             );
@@ -92,7 +94,7 @@ mod number {
             let inp = b"-1";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Integer(-1)
             );
@@ -100,7 +102,7 @@ mod number {
             let inp = b"-100";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Integer(-100)
             );
@@ -110,6 +112,7 @@ mod number {
     mod real {
         use crate::ast::object::Number;
         use crate::lexer::PdfLexer;
+        use crate::parser_helper::ParseContext;
         use crate::pdf::NumberParser;
 
         #[test]
@@ -117,7 +120,7 @@ mod number {
             let inp = b"0.";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(0.0)
             );
@@ -125,7 +128,7 @@ mod number {
             let inp = b".0";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(0.0)
             );
@@ -133,7 +136,7 @@ mod number {
             let inp = b"0.0";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(0.0)
             );
@@ -141,7 +144,7 @@ mod number {
             let inp = b"0.00000";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(0.0)
             );
@@ -149,7 +152,7 @@ mod number {
             let inp = b"-0.";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(-0.0)
             );
@@ -157,7 +160,7 @@ mod number {
             let inp = b"-.0";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(-0.0)
             );
@@ -165,7 +168,7 @@ mod number {
             let inp = b"-.0";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(-0.0)
             );
@@ -173,7 +176,7 @@ mod number {
             let inp = b"-0.0";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(-0.0)
             );
@@ -181,7 +184,7 @@ mod number {
             let inp = b"-0.000000";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(-0.0)
             );
@@ -192,7 +195,7 @@ mod number {
             let inp = b"12.";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(12 as f64)
             );
@@ -200,7 +203,7 @@ mod number {
             let inp = b".12";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(0.12)
             );
@@ -208,7 +211,7 @@ mod number {
             let inp = b"0.12";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(0.12)
             );
@@ -216,7 +219,7 @@ mod number {
             let inp = b"12.34";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(12.34)
             );
@@ -227,7 +230,7 @@ mod number {
             let inp = b"-12.";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(-12 as f64)
             );
@@ -235,7 +238,7 @@ mod number {
             let inp = b"-.12";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(-0.12)
             );
@@ -243,7 +246,7 @@ mod number {
             let inp = b"-0.12";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(-0.12)
             );
@@ -251,7 +254,7 @@ mod number {
             let inp = b"-12.34";
             assert_eq!(
                 NumberParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 Number::Real(-12.34)
             );
@@ -1,6 +1,7 @@
 //! Tests for parsing PDF names
 
 use crate::lexer::PdfLexer;
+use crate::parser_helper::ParseContext;
 use crate::pdf::NameParser;
 
 #[test]
@@ -8,7 +9,7 @@ fn empty() {
     let inp = b"/";
     assert_eq!(
         NameParser::new()
-            .parse(inp, PdfLexer::new(inp).unwrap())
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
             .unwrap(),
         b"".to_vec()
     );
@@ -19,7 +20,7 @@ fn smoke() {
     let inp = b"/Name";
     assert_eq!(
         NameParser::new()
-            .parse(inp, PdfLexer::new(inp).unwrap())
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
             .unwrap(),
         b"Name".to_vec()
     );
@@ -30,7 +31,7 @@ fn escapes() {
     let inp = b"/#2F#2F";
     assert_eq!(
         NameParser::new()
-            .parse(inp, PdfLexer::new(inp).unwrap())
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
             .unwrap(),
         br"//".to_vec()
     );
@@ -38,7 +39,7 @@ fn escapes() {
     let inp = b"/#20#20";
     assert_eq!(
         NameParser::new()
-            .parse(inp, PdfLexer::new(inp).unwrap())
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
             .unwrap(),
         b"  ".to_vec()
     );
@@ -47,6 +48,7 @@ fn escapes() {
 /// Tests to ensure examples provided in PDF Spec section 7.3.5 are handled as described
 mod sec735 {
     use crate::lexer::PdfLexer;
+    use crate::parser_helper::ParseContext;
     use crate::pdf::NameParser;
 
     #[test]
@@ -54,7 +56,7 @@ mod sec735 {
         let inp = b"/Name1";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"Name1".to_vec()
         );
@@ -62,7 +64,7 @@ mod sec735 {
         let inp = b"/ASomewhatLongerName";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"ASomewhatLongerName".to_vec()
         );
@@ -70,7 +72,7 @@ mod sec735 {
         let inp = b"/A;Name_With-Various***Characters?";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"A;Name_With-Various***Characters?".to_vec()
         );
@@ -78,7 +80,7 @@ mod sec735 {
         let inp = b"/1.2";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"1.2".to_vec()
         );
@@ -86,7 +88,7 @@ mod sec735 {
         let inp = b"/$$";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"$$".to_vec()
         );
@@ -94,7 +96,7 @@ mod sec735 {
         let inp = b"/@pattern";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"@pattern".to_vec()
         );
@@ -102,7 +104,7 @@ mod sec735 {
         let inp = b"/.notdef";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b".notdef".to_vec()
         );
@@ -110,7 +112,7 @@ mod sec735 {
         let inp = b"/Lime#20Green";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"Lime Green".to_vec()
         );
@@ -118,7 +120,7 @@ mod sec735 {
         let inp = b"/paired#28#29parentheses";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"paired()parentheses".to_vec()
         );
@@ -126,7 +128,7 @@ mod sec735 {
         let inp = b"/The_Key_of_F#23_Minor";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"The_Key_of_F#_Minor".to_vec()
         );
@@ -134,7 +136,7 @@ mod sec735 {
         let inp = b"/A#42";
         assert_eq!(
             NameParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"AB".to_vec()
         );
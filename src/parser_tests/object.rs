@@ -0,0 +1,188 @@
+//! Tests for parsing arbitrary PDF objects with the top-level `Object` production
+
+use crate::ast::object::{Dictionary, Name, Number, Object, PdfString};
+use crate::lexer::PdfLexer;
+use crate::parser_helper::ParseContext;
+use crate::pdf::ObjectParser;
+
+fn parse(inp: &[u8]) -> Object {
+    ObjectParser::new()
+        .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+        .unwrap()
+}
+
+#[test]
+fn boolean() {
+    assert_eq!(parse(b"true"), Object::Boolean(true));
+    assert_eq!(parse(b"false"), Object::Boolean(false));
+}
+
+#[test]
+fn number() {
+    assert_eq!(parse(b"42"), Object::Number(Number::Integer(42)));
+    assert_eq!(parse(b"-.5"), Object::Number(Number::Real(-0.5)));
+}
+
+#[test]
+fn string() {
+    assert_eq!(
+        parse(b"(abc)"),
+        Object::String(PdfString::Literal(b"abc".to_vec()))
+    );
+    assert_eq!(
+        parse(b"<616263>"),
+        Object::String(PdfString::Hex(b"abc".to_vec()))
+    );
+}
+
+#[test]
+fn name() {
+    assert_eq!(parse(b"/Type"), Object::Name(Name(b"Type".to_vec())));
+}
+
+#[test]
+fn null() {
+    assert_eq!(parse(b"null"), Object::Null);
+    assert_eq!(parse(b"  null  "), Object::Null);
+}
+
+#[test]
+fn array() {
+    assert_eq!(parse(b"[]"), Object::Array(vec![]));
+    assert_eq!(
+        parse(b"[1 2]"),
+        Object::Array(vec![
+            Object::Number(Number::Integer(1)),
+            Object::Number(Number::Integer(2)),
+        ])
+    );
+    assert_eq!(
+        parse(b"[/F1 12(x)<41>[true]null]"),
+        Object::Array(vec![
+            Object::Name(Name(b"F1".to_vec())),
+            Object::Number(Number::Integer(12)),
+            Object::String(PdfString::Literal(b"x".to_vec())),
+            Object::String(PdfString::Hex(b"A".to_vec())),
+            Object::Array(vec![Object::Boolean(true)]),
+            Object::Null,
+        ])
+    );
+}
+
+#[test]
+fn long_array() {
+    // Long enough that the lexer has to re-lex it in several chunks, with strings, hex strings
+    // and dictionaries falling across chunk boundaries
+    let elems: Vec<String> = (0..1000)
+        .map(|i| format!("{i} ({i}) <{i:04X}> [<</N {i}>>]"))
+        .collect();
+    let inp = format!("[{}]", elems.join(" "));
+    let expected = (0..1000)
+        .flat_map(|i| {
+            [
+                Object::Number(Number::Integer(i)),
+                Object::String(PdfString::Literal(i.to_string().into_bytes())),
+                Object::String(PdfString::Hex((i as u16).to_be_bytes().to_vec())),
+                Object::Array(vec![Object::Dictionary(Dictionary::from_iter([(
+                    Name(b"N".to_vec()),
+                    Object::Number(Number::Integer(i)),
+                )]))]),
+            ]
+        })
+        .collect();
+    assert_eq!(parse(inp.as_bytes()), Object::Array(expected));
+}
+
+#[test]
+fn dictionary() {
+    assert_eq!(parse(b"<<>>"), Object::Dictionary(Dictionary::new()));
+
+    let dict: Dictionary = [
+        (Name(b"Type".to_vec()), Object::Name(Name(b"Page".to_vec()))),
+        (Name(b"A".to_vec()), Object::Number(Number::Integer(1))),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(
+        parse(b"<</Type/Page/A 1>>"),
+        Object::Dictionary(dict.clone())
+    );
+    assert_eq!(parse(b"<< /Type /Page\n/A 1 >>"), Object::Dictionary(dict));
+}
+
+#[test]
+fn dictionary_hex_value() {
+    // The `>` closing the hex string must not be confused with the `>>` closing the dictionary
+    let dict: Dictionary = [
+        (
+            Name(b"A".to_vec()),
+            Object::String(PdfString::Hex(b"A".to_vec())),
+        ),
+        (
+            Name(b"B".to_vec()),
+            Object::String(PdfString::Hex(b"".to_vec())),
+        ),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(parse(b"<</A<41>/B<>>>"), Object::Dictionary(dict));
+}
+
+#[test]
+fn dictionary_nested() {
+    let inner: Dictionary = [(Name(b"B".to_vec()), Object::Array(vec![]))]
+        .into_iter()
+        .collect();
+    let outer: Dictionary = [(Name(b"A".to_vec()), Object::Dictionary(inner))]
+        .into_iter()
+        .collect();
+    assert_eq!(parse(b"<</A<</B[]>>>>"), Object::Dictionary(outer));
+}
+
+#[test]
+fn dictionary_null_value() {
+    // Entries with a null value are equivalent to absent entries
+    assert_eq!(parse(b"<</A null>>"), Object::Dictionary(Dictionary::new()));
+}
+
+#[test]
+fn comments() {
+    assert_eq!(
+        parse(b"[1%comment]\n2]"),
+        Object::Array(vec![
+            Object::Number(Number::Integer(1)),
+            Object::Number(Number::Integer(2)),
+        ])
+    );
+}
+
+#[test]
+fn failure() {
+    let inp = b"nul";
+    assert!(
+        ObjectParser::new()
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+            .is_err()
+    );
+
+    let inp = b"<</A>>";
+    assert!(
+        ObjectParser::new()
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+            .is_err()
+    );
+
+    let inp = b"[1 2";
+    assert!(
+        ObjectParser::new()
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+            .is_err()
+    );
+
+    let inp = b"true false";
+    assert!(
+        ObjectParser::new()
+            .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+            .is_err()
+    );
+}
@@ -0,0 +1,98 @@
+//! Tests for stream objects, whose data is lexed as a single binary token
+
+use crate::ast::object::{Number, Object, Reference, Stream};
+use crate::lexer::PdfLexer;
+use crate::parser_helper::ParseContext;
+use crate::pdf::{IndirectObjectParser, StreamParser};
+
+fn parse(inp: &[u8]) -> Stream {
+    StreamParser::new()
+        .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+        .unwrap()
+}
+
+#[test]
+fn smoke() {
+    let stream = parse(b"<</Length 5>>\nstream\nhello\nendstream");
+    assert_eq!(stream.data, b"hello".to_vec());
+    assert_eq!(
+        stream.dict.get(b"Length"),
+        Some(&Object::Number(Number::Integer(5)))
+    );
+}
+
+#[test]
+fn crlf() {
+    let stream = parse(b"<</Length 5>>stream\r\nhello\r\nendstream");
+    assert_eq!(stream.data, b"hello".to_vec());
+}
+
+#[test]
+fn empty() {
+    let stream = parse(b"<</Length 0>>stream\nendstream");
+    assert_eq!(stream.data, b"".to_vec());
+
+    let stream = parse(b"<</Length 0>>stream\n\nendstream");
+    assert_eq!(stream.data, b"".to_vec());
+}
+
+#[test]
+fn binary() {
+    // Data that would be nonsense (or an error) if it were tokenized
+    let mut inp = b"<</Length 12>>stream\n".to_vec();
+    let data = b"(<<]\x00\xff%>>)\\>";
+    inp.extend_from_slice(data);
+    inp.extend_from_slice(b"\nendstream");
+    assert_eq!(parse(&inp).data, data.to_vec());
+}
+
+#[test]
+fn data_contains_endstream() {
+    // Only the `/Length` lets us get this right
+    let stream = parse(b"<</Length 12>>stream\nxendstream y\nendstream");
+    assert_eq!(stream.data, b"xendstream y".to_vec());
+}
+
+#[test]
+fn indirect_length() {
+    let stream = parse(b"<</Length 8 0 R>>stream\nhello\nendstream");
+    assert_eq!(stream.data, b"hello".to_vec());
+    assert_eq!(
+        stream.dict.get(b"Length"),
+        Some(&Object::Reference(Reference {
+            number: 8,
+            generation: 0
+        }))
+    );
+}
+
+#[test]
+fn wrong_length() {
+    let stream = parse(b"<</Length 3>>stream\nhello\nendstream");
+    assert_eq!(stream.data, b"hello".to_vec());
+
+    let stream = parse(b"<</Length 500>>stream\nhello\nendstream");
+    assert_eq!(stream.data, b"hello".to_vec());
+}
+
+#[test]
+fn nested_length() {
+    // Only the `/Length` of the stream dictionary itself counts
+    let stream = parse(b"<</DecodeParms<</Length 1>>>>stream\nhello\nendstream");
+    assert_eq!(stream.data, b"hello".to_vec());
+}
+
+#[test]
+fn indirect_object() {
+    let inp = b"4 0 obj\n<</Length 2>>\nstream\nhi\nendstream\nendobj";
+    let obj = IndirectObjectParser::new()
+        .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+        .unwrap();
+    assert_eq!(obj.object.as_stream().unwrap().data, b"hi".to_vec());
+}
+
+#[test]
+fn failure() {
+    let inp = b"<</Length 5>>stream\nhello";
+    assert!(PdfLexer::new(inp).is_err());
+}
@@ -3,6 +3,7 @@
 /// Tests for `RawPdfStr`
 mod raw {
     use crate::lexer::PdfLexer;
+    use crate::parser_helper::ParseContext;
     use crate::pdf::RawPdfStrParser;
 
     #[test]
@@ -10,7 +11,7 @@ mod raw {
         let inp = b"(test)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"test".to_vec()
         );
@@ -18,7 +19,7 @@ mod raw {
         let inp = b"(\n\n\n)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\n\n\n".to_vec()
         );
@@ -29,7 +30,7 @@ mod raw {
         let inp = b"()";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"".to_vec()
         );
@@ -40,21 +41,21 @@ mod raw {
         let inp = br"()()";
         assert!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .is_err()
         );
 
         let inp = br"()\)";
         assert!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .is_err()
         );
 
         let inp = br"(\\\)";
         assert!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .is_err()
         );
     }
@@ -64,7 +65,7 @@ mod raw {
         let inp = b"(())";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"()".to_vec()
         );
@@ -72,7 +73,7 @@ mod raw {
         let inp = b"(abc () xyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abc () xyz".to_vec()
         );
@@ -80,7 +81,7 @@ mod raw {
         let inp = b"(abc (()) () xyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abc (()) () xyz".to_vec()
         );
@@ -91,7 +92,7 @@ mod raw {
         let inp = b"(abc\nxyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abc\nxyz".to_vec()
         );
@@ -102,7 +103,7 @@ mod raw {
         let inp = br"(\)\()";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             br")(".to_vec()
         );
@@ -110,7 +111,7 @@ mod raw {
         let inp = br"(\\\)\()";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             br"\)(".to_vec()
         );
@@ -118,7 +119,7 @@ mod raw {
         let inp = br"(( \) \( ))";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             br"( ) ( )".to_vec()
         );
@@ -129,7 +130,7 @@ mod raw {
         let inp = b"(abc\\\nxyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abcxyz".to_vec()
         );
@@ -137,7 +138,7 @@ mod raw {
         let inp = b"(abc\\\r\nxyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abcxyz".to_vec()
         );
@@ -145,7 +146,7 @@ mod raw {
         let inp = b"(abc\\\n\nxyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abc\nxyz".to_vec()
         );
@@ -160,7 +161,7 @@ mod raw {
         // end-of-line marker was a CARRIAGE RETURN (0Dh), a LINE FEED (0Ah), or both.
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\t\n\n\n".to_vec()
         );
@@ -174,7 +175,7 @@ mod raw {
         // end-of-line marker was a CARRIAGE RETURN (0Dh), a LINE FEED (0Ah), or both.
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\n\n\n\n\n\n\n".to_vec()
         );
@@ -184,7 +185,7 @@ mod raw {
         let inp = br"(\n\r\t\b\f)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\n\r\t\x08\x0C".to_vec()
         );
@@ -195,7 +196,7 @@ mod raw {
         let inp = br"(\\\\\\n)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             br"\\\n".to_vec()
         );
@@ -206,7 +207,7 @@ mod raw {
         let inp = br"(\0)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\x00".to_vec()
         );
@@ -214,7 +215,7 @@ mod raw {
         let inp = br"(\000)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\x00".to_vec()
         );
@@ -224,14 +225,14 @@ mod raw {
         let inp = br"(\1)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\x01".to_vec()
         );
         let inp = br"(\100)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\x40".to_vec()
         );
@@ -241,7 +242,7 @@ mod raw {
         let inp = br"(\377)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\xFF".to_vec()
         );
@@ -252,7 +253,7 @@ mod raw {
         let inp = br"(\x\y\z)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"xyz".to_vec()
         );
@@ -262,28 +263,28 @@ mod raw {
         let inp = br"(abc\400xyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abcxyz".to_vec()
         );
         let inp = br"(abc\800xyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abc800xyz".to_vec()
         );
         let inp = br"(abc\08xyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abc\x008xyz".to_vec()
         );
         let inp = br"(abc\008xyz)";
         assert_eq!(
             RawPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"abc\x008xyz".to_vec()
         );
@@ -293,6 +294,7 @@ mod raw {
     /// there.
     mod sec7342 {
         use crate::lexer::PdfLexer;
+        use crate::parser_helper::ParseContext;
         use crate::pdf::RawPdfStrParser;
 
         #[test]
@@ -300,7 +302,7 @@ mod raw {
             let inp = b"(This is a string)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"This is a string".to_vec()
             );
@@ -309,7 +311,7 @@ mod raw {
  newlines and such.)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"Strings can contain\n newlines and such.".to_vec()
             );
@@ -318,7 +320,7 @@ mod raw {
  and special characters ( * ! & } ^ %and so on) .)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"Strings can contain balanced parentheses ()\n and special characters ( * ! & } ^ %and so on) .".to_vec()
             );
@@ -326,7 +328,7 @@ mod raw {
             let inp = br"(The following is an empty string .)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"The following is an empty string .".to_vec()
             );
@@ -334,7 +336,7 @@ mod raw {
             let inp = br"()";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"".to_vec()
             );
@@ -342,7 +344,7 @@ mod raw {
             let inp = br"(It has zero (0) length.)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"It has zero (0) length.".to_vec()
             );
@@ -354,12 +356,12 @@ mod raw {
 two strings \
 are the same.)";
             let out1 = RawPdfStrParser::new()
-                .parse(inp1, PdfLexer::new(inp1).unwrap())
+                .parse(inp1, &ParseContext::default(), PdfLexer::new(inp1).unwrap())
                 .unwrap();
 
             let inp2 = br"(These two strings are the same.)";
             let out2 = RawPdfStrParser::new()
-                .parse(inp2, PdfLexer::new(inp2).unwrap())
+                .parse(inp2, &ParseContext::default(), PdfLexer::new(inp2).unwrap())
                 .unwrap();
 
             assert_eq!(out1, out2);
@@ -372,7 +374,7 @@ are the same.)";
 )";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"This string has an end-of-line at the end of it.\n".to_vec()
             );
@@ -380,7 +382,7 @@ are the same.)";
             let inp = br"(So does this one.\n)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"So does this one.\n".to_vec()
             );
@@ -391,7 +393,7 @@ are the same.)";
             let inp = br"(This string contains \245two octal characters\307.)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"This string contains \xA5two octal characters\xC7.".to_vec()
             );
@@ -402,7 +404,7 @@ are the same.)";
             let inp = br"(\0053)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"\x053".to_vec()
             );
@@ -410,7 +412,7 @@ are the same.)";
             let inp = br"(\053)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"\x2B".to_vec()
             );
@@ -418,7 +420,7 @@ are the same.)";
             let inp = br"(\53)";
             assert_eq!(
                 RawPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"\x2B".to_vec()
             );
@@ -428,6 +430,7 @@ are the same.)";
 
 mod hex {
     use crate::lexer::PdfLexer;
+    use crate::parser_helper::ParseContext;
     use crate::pdf::HexPdfStrParser;
 
     #[test]
@@ -435,7 +438,7 @@ mod hex {
         let inp = b"<>";
         assert_eq!(
             HexPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"".to_vec()
         );
@@ -446,7 +449,7 @@ mod hex {
         let inp = b"<3A3B20486578203F3F5044463F3F20737472202A2B2C>";
         assert_eq!(
             HexPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b":; Hex ??PDF?? str *+,".to_vec()
         );
@@ -457,7 +460,7 @@ mod hex {
         let inp = b"<3a3b20486578203f3f5044463f3f20737472202a2b2c>";
         assert_eq!(
             HexPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b":; Hex ??PDF?? str *+,".to_vec()
         );
@@ -468,7 +471,7 @@ mod hex {
         let inp = b"<0>";
         assert_eq!(
             HexPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\x00".to_vec()
         );
@@ -476,7 +479,7 @@ mod hex {
         let inp = b"<4>";
         assert_eq!(
             HexPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"\x40".to_vec()
         );
@@ -484,16 +487,28 @@ mod hex {
         let inp = b"<6174207369676E3A204>";
         assert_eq!(
             HexPdfStrParser::new()
-                .parse(inp, PdfLexer::new(inp).unwrap())
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                 .unwrap(),
             b"at sign: \x40".to_vec()
         );
     }
 
+    #[test]
+    fn whitespace() {
+        let inp = b"<48 65\r\n6C\t6C 6\x0CF >";
+        assert_eq!(
+            HexPdfStrParser::new()
+                .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
+                .unwrap(),
+            b"Hello".to_vec()
+        );
+    }
+
     /// Tests that the examples given in section 7.3.4.3 of the PDF Spec are handled as described
     /// there.
     mod sec7343 {
         use crate::lexer::PdfLexer;
+        use crate::parser_helper::ParseContext;
         use crate::pdf::HexPdfStrParser;
 
         #[test]
@@ -501,7 +516,7 @@ mod hex {
             let inp = b"<4E6F762073686D6F7A206B6120706F702E>";
             assert_eq!(
                 HexPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 // Apparently it's a reference:
                 // https://en.wikipedia.org/wiki/Gene_Ahern#Influence
@@ -514,7 +529,7 @@ mod hex {
             let inp = b"<901FA3>";
             assert_eq!(
                 HexPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"\x90\x1F\xA3".to_vec()
             );
@@ -522,7 +537,7 @@ mod hex {
             let inp = b"<901FA>";
             assert_eq!(
                 HexPdfStrParser::new()
-                    .parse(inp, PdfLexer::new(inp).unwrap())
+                    .parse(inp, &ParseContext::default(), PdfLexer::new(inp).unwrap())
                     .unwrap(),
                 b"\x90\x1F\xA0".to_vec()
             );
@@ -0,0 +1,424 @@
+//! Writing objects and whole documents as PDF syntax. See PDF Spec section 7.5
+
+use md5::{Digest, Md5};
+
+use crate::ast::object::{Dictionary, Name, Number, Object, PdfString, Reference};
+use crate::crypt::{Algorithm, Encryption, StandardSecurity};
+use crate::document::Version;
+use crate::error::Result;
+
+/// Builds a new document from objects, and writes it with a cross-reference table. Objects are
+/// numbered in the order they're added, from 1, with generation 0.
+pub struct Writer {
+    version: Version,
+    objects: Vec<Object>,
+    trailer: Dictionary,
+    encryption: Option<Encryption>,
+}
+
+impl Writer {
+    /// Starts an empty document. The version in the header is raised if encryption needs a later
+    /// one.
+    pub fn new(version: Version) -> Self {
+        Self {
+            version,
+            objects: Vec::new(),
+            trailer: Dictionary::new(),
+            encryption: None,
+        }
+    }
+
+    /// Adds an indirect object, returning the reference to it
+    pub fn add(&mut self, obj: Object) -> Reference {
+        self.objects.push(obj);
+        Reference {
+            number: self.objects.len() as u32,
+            generation: 0,
+        }
+    }
+
+    /// The trailer dictionary, for `/Root` and `/Info`. `/Size`, `/Encrypt` and, unless it's
+    /// already set, `/ID` are filled in by [`Writer::finish`].
+    pub fn trailer_mut(&mut self) -> &mut Dictionary {
+        &mut self.trailer
+    }
+
+    /// Encrypts every string and stream in the document with the standard security handler
+    pub fn encrypt(&mut self, encryption: Encryption) {
+        self.encryption = Some(encryption);
+    }
+
+    /// Writes the document
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        let mut bodies: Vec<Vec<u8>> = self.objects.iter().map(serialize).collect();
+
+        // The file identifier is derived from the contents. See PDF Spec section 14.4
+        let id = match self.trailer.get(b"ID").and_then(Object::as_array) {
+            Some([Object::String(id), ..]) => id.as_bytes().to_vec(),
+            _ => {
+                let id = bodies
+                    .iter()
+                    .fold(Md5::new(), |hash, body| hash.chain_update(body))
+                    .finalize()
+                    .to_vec();
+                let string = Object::String(PdfString::Hex(id.clone()));
+                self.trailer.insert(
+                    Name::from(b"ID"),
+                    Object::Array(vec![string.clone(), string]),
+                );
+                id
+            }
+        };
+
+        if let Some(encryption) = &self.encryption {
+            let (security, dict) = StandardSecurity::create(encryption, &id);
+            bodies = self
+                .objects
+                .into_iter()
+                .enumerate()
+                .map(|(i, obj)| {
+                    let reference = Reference {
+                        number: i as u32 + 1,
+                        generation: 0,
+                    };
+                    Ok(serialize(&security.encrypt_object(reference, obj)?))
+                })
+                .collect::<Result<_>>()?;
+            // The encryption dictionary itself is never encrypted
+            bodies.push(serialize(&Object::Dictionary(dict)));
+            let encrypt = Reference {
+                number: bodies.len() as u32,
+                generation: 0,
+            };
+            self.trailer
+                .insert(Name::from(b"Encrypt"), Object::Reference(encrypt));
+            let required = match encryption.algorithm {
+                Algorithm::Rc4 if encryption.encrypt_metadata => Version { major: 1, minor: 4 },
+                Algorithm::Rc4 | Algorithm::Aes128 => Version { major: 1, minor: 6 },
+                Algorithm::Aes256 => Version { major: 2, minor: 0 },
+            };
+            self.version = self.version.max(required);
+        }
+
+        // A comment with high bytes marks the file as binary. See PDF Spec section 7.5.2
+        let mut data = format!("%PDF-{}\n", self.version).into_bytes();
+        data.extend_from_slice(b"%\xe2\xe3\xcf\xd3\n");
+        let mut offsets = Vec::new();
+        for (i, body) in bodies.iter().enumerate() {
+            offsets.push(data.len());
+            data.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            data.extend_from_slice(body);
+            data.extend_from_slice(b"\nendobj\n");
+        }
+
+        let startxref = data.len();
+        data.extend_from_slice(format!("xref\n0 {}\n", bodies.len() + 1).as_bytes());
+        data.extend_from_slice(b"0000000000 65535 f\r\n");
+        for offset in offsets {
+            data.extend_from_slice(format!("{offset:010} 00000 n\r\n").as_bytes());
+        }
+        self.trailer.insert(
+            Name::from(b"Size"),
+            Object::Number(Number::Integer(bodies.len() as i64 + 1)),
+        );
+        data.extend_from_slice(b"trailer\n");
+        write_object(&mut data, &Object::Dictionary(self.trailer));
+        data.extend_from_slice(format!("\nstartxref\n{startxref}\n%%EOF\n").as_bytes());
+        Ok(data)
+    }
+}
+
+fn serialize(obj: &Object) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_object(&mut out, obj);
+    out
+}
+
+/// Appends the PDF syntax for `obj` to `out`. A stream's `/Length` is set to the length of its
+/// data.
+pub fn write_object(out: &mut Vec<u8>, obj: &Object) {
+    match obj {
+        Object::Boolean(b) => out.extend_from_slice(if *b { b"true" } else { b"false" }),
+        Object::Number(Number::Integer(i)) => out.extend_from_slice(i.to_string().as_bytes()),
+        Object::Number(Number::Real(r)) => write_real(out, *r),
+        Object::String(PdfString::Literal(s)) => write_literal_string(out, s),
+        Object::String(PdfString::Hex(s)) => {
+            out.push(b'<');
+            out.extend(s.iter().flat_map(|b| format!("{b:02x}").into_bytes()));
+            out.push(b'>');
+        }
+        Object::Name(name) => write_name(out, name),
+        Object::Array(objects) => {
+            out.push(b'[');
+            for (i, obj) in objects.iter().enumerate() {
+                if i > 0 {
+                    out.push(b' ');
+                }
+                write_object(out, obj);
+            }
+            out.push(b']');
+        }
+        Object::Dictionary(dict) => write_dictionary(out, dict),
+        Object::Stream(stream) => {
+            let mut dict = stream.dict.clone();
+            dict.insert(
+                Name::from(b"Length"),
+                Object::Number(Number::Integer(stream.data.len() as i64)),
+            );
+            write_dictionary(out, &dict);
+            out.extend_from_slice(b"\nstream\n");
+            out.extend_from_slice(&stream.data);
+            out.extend_from_slice(b"\nendstream");
+        }
+        Object::Null => out.extend_from_slice(b"null"),
+        Object::Reference(r) => {
+            out.extend_from_slice(format!("{} {} R", r.number, r.generation).as_bytes())
+        }
+    }
+}
+
+fn write_dictionary(out: &mut Vec<u8>, dict: &Dictionary) {
+    out.extend_from_slice(b"<<");
+    for (key, val) in dict.iter() {
+        write_name(out, key);
+        out.push(b' ');
+        write_object(out, val);
+    }
+    out.extend_from_slice(b">>");
+}
+
+/// Reals can't be written with an exponent, and must have a decimal point to stay reals when
+/// read back. See PDF Spec section 7.3.3
+fn write_real(out: &mut Vec<u8>, r: f64) {
+    // There's no syntax for infinities or NaN
+    let mut s = if r.is_finite() {
+        r.to_string()
+    } else {
+        String::from("0")
+    };
+    if !s.contains('.') {
+        s.push_str(".0");
+    }
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// Escapes the characters that would end the string or be changed by reading it back: unbalanced
+/// parentheses, backslashes and end-of-line markers. See PDF Spec section 7.3.4.2
+fn write_literal_string(out: &mut Vec<u8>, s: &[u8]) {
+    out.push(b'(');
+    for byte in s {
+        match byte {
+            b'(' | b')' | b'\\' => out.extend_from_slice(&[b'\\', *byte]),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            _ => out.push(*byte),
+        }
+    }
+    out.push(b')');
+}
+
+/// Writes bytes outside the printable ASCII range, delimiters and `#` as `#xx` escapes. See PDF
+/// Spec section 7.3.5
+fn write_name(out: &mut Vec<u8>, name: &Name) {
+    out.push(b'/');
+    for byte in name.as_bytes() {
+        match byte {
+            b'#' | b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%' => {
+                out.extend_from_slice(format!("#{byte:02x}").as_bytes())
+            }
+            0x21..=0x7e => out.push(*byte),
+            _ => out.extend_from_slice(format!("#{byte:02x}").as_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Document;
+    use crate::ast::object::Stream;
+    use crate::crypt::{CryptError, Password, Permissions};
+    use crate::error::{Error, ParseMode};
+
+    fn name(name: &[u8]) -> Object {
+        Object::Name(Name::from(name))
+    }
+
+    fn int(val: i64) -> Object {
+        Object::Number(Number::Integer(val))
+    }
+
+    fn dict(entries: &[(&[u8], Object)]) -> Object {
+        Object::Dictionary(
+            entries
+                .iter()
+                .map(|(key, val)| (Name::from(*key), val.clone()))
+                .collect(),
+        )
+    }
+
+    fn stream(entries: &[(&[u8], Object)], data: &[u8]) -> Object {
+        let Object::Dictionary(dict) = dict(entries) else {
+            unreachable!()
+        };
+        Object::Stream(Stream {
+            dict,
+            data: data.to_vec(),
+        })
+    }
+
+    #[test]
+    fn objects() {
+        let obj = Object::Array(vec![
+            Object::Boolean(true),
+            int(-12),
+            Object::Number(Number::Real(1.0)),
+            Object::Number(Number::Real(-0.25)),
+            Object::Number(Number::Real(1e21)),
+            Object::String(PdfString::Literal(b"a (b) \\ \r\n".to_vec())),
+            Object::String(PdfString::Hex(vec![0x00, 0xab])),
+            name(b"A b#/\xff"),
+            Object::Null,
+            Object::Reference(Reference {
+                number: 3,
+                generation: 1,
+            }),
+            dict(&[(b"Type", name(b"Catalog")), (b"Count", int(0))]),
+        ]);
+        assert_eq!(
+            serialize(&obj),
+            b"[true -12 1.0 -0.25 1000000000000000000000.0 (a \\(b\\) \\\\ \\r\\n) <00ab> \
+              /A#20b#23#2f#ff null 3 1 R <</Count 0/Type /Catalog>>]"
+        );
+        assert_eq!(
+            serialize(&stream(&[(b"Length", int(99))], b"data")),
+            b"<</Length 4>>\nstream\ndata\nendstream"
+        );
+    }
+
+    /// Writes a small document, with a string, a stream and a metadata stream
+    fn sample(encryption: Option<Encryption>) -> (Vec<Object>, Vec<u8>) {
+        let objects = vec![
+            dict(&[(b"Type", name(b"Catalog")), (b"Metadata", int(0))]),
+            dict(&[
+                (
+                    b"Title",
+                    Object::String(PdfString::Literal(b"A (secret) title".to_vec())),
+                ),
+                (b"Keywords", Object::String(PdfString::Hex(Vec::new()))),
+            ]),
+            stream(&[(b"Length", int(0))], b"BT /F1 12 Tf (Hello) Tj ET"),
+            stream(
+                &[(b"Type", name(b"Metadata")), (b"Subtype", name(b"XML"))],
+                b"<x:xmpmeta/>",
+            ),
+        ];
+        let mut writer = Writer::new(Version { major: 1, minor: 4 });
+        let references: Vec<_> = objects.iter().map(|obj| writer.add(obj.clone())).collect();
+        writer
+            .trailer_mut()
+            .insert(Name::from(b"Root"), Object::Reference(references[0]));
+        writer
+            .trailer_mut()
+            .insert(Name::from(b"Info"), Object::Reference(references[1]));
+        if let Some(encryption) = encryption {
+            writer.encrypt(encryption);
+        }
+        (objects, writer.finish().unwrap())
+    }
+
+    fn reference(number: u32) -> Reference {
+        Reference {
+            number,
+            generation: 0,
+        }
+    }
+
+    /// Checks that the objects read back are the ones written. Encryption may change the length
+    /// of stream data, so `/Length` is ignored.
+    fn check(doc: &Document, objects: &[Object]) {
+        let without_length = |obj: Object| match obj {
+            Object::Stream(mut stream) => {
+                stream.dict.remove(b"Length");
+                Object::Stream(stream)
+            }
+            obj => obj,
+        };
+        for (i, obj) in objects.iter().enumerate() {
+            let read = doc.get(reference(i as u32 + 1)).unwrap();
+            assert_eq!(without_length(read), without_length(obj.clone()));
+        }
+    }
+
+    #[test]
+    fn unencrypted() {
+        let (objects, data) = sample(None);
+        let doc = Document::open(data).unwrap();
+        assert!(doc.repairs().is_empty());
+        assert_eq!(doc.version(), Version { major: 1, minor: 4 });
+        assert!(doc.security().is_none());
+        assert!(doc.trailer().contains_key(b"ID"));
+        check(&doc, &objects);
+    }
+
+    #[test]
+    fn encrypted() {
+        let permissions = Permissions {
+            copy: false,
+            modify: false,
+            ..Permissions::all()
+        };
+        for algorithm in [Algorithm::Rc4, Algorithm::Aes128, Algorithm::Aes256] {
+            let (objects, data) = sample(Some(Encryption {
+                permissions,
+                ..Encryption::new(algorithm, b"user", b"owner")
+            }));
+            // Nothing is readable without the password
+            assert!(!data.windows(6).any(|w| w == b"secret"));
+            assert!(!data.windows(5).any(|w| w == b"Hello"));
+            assert!(!data.windows(7).any(|w| w == b"xmpmeta"));
+
+            let doc = Document::open_with_password(&data[..], ParseMode::Strict, b"user").unwrap();
+            let security = doc.security().unwrap();
+            assert_eq!(security.password, Password::User);
+            assert_eq!(doc.permissions(), permissions);
+            check(&doc, &objects);
+
+            let doc = Document::open_with_password(&data[..], ParseMode::Strict, b"owner").unwrap();
+            assert_eq!(doc.security().unwrap().password, Password::Owner);
+            assert_eq!(doc.permissions(), Permissions::all());
+            check(&doc, &objects);
+
+            assert!(matches!(
+                Document::open(&data[..]),
+                Err(Error::Crypt(CryptError::IncorrectPassword))
+            ));
+        }
+    }
+
+    #[test]
+    fn unencrypted_metadata() {
+        for algorithm in [Algorithm::Rc4, Algorithm::Aes128, Algorithm::Aes256] {
+            let (objects, data) = sample(Some(Encryption {
+                encrypt_metadata: false,
+                ..Encryption::new(algorithm, b"", b"owner")
+            }));
+            assert!(data.windows(7).any(|w| w == b"xmpmeta"));
+            assert!(!data.windows(5).any(|w| w == b"Hello"));
+
+            // With an empty user password, the document opens without one
+            let doc = Document::open(&data[..]).unwrap();
+            assert!(!doc.security().unwrap().encrypt_metadata);
+            check(&doc, &objects);
+        }
+    }
+
+    #[test]
+    fn owner_password_defaults_to_user() {
+        let (objects, data) = sample(Some(Encryption::new(Algorithm::Aes128, b"pw", b"")));
+        let doc = Document::open_with_password(&data[..], ParseMode::Strict, b"pw").unwrap();
+        assert_eq!(doc.security().unwrap().password, Password::Owner);
+        assert_eq!(doc.version(), Version { major: 1, minor: 6 });
+        check(&doc, &objects);
+    }
+}
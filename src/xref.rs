@@ -0,0 +1,1022 @@
+//! Parsing of cross-reference tables, cross-reference streams and trailers, which let us find
+//! objects in a file without reading all of it, and of the object streams compressed objects
+//! live in. See PDF Spec sections 7.5.4 through 7.5.8
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::ast::object::{Dictionary, IndirectObject, Object, Reference, Stream};
+use crate::error::ParseError;
+use crate::filter::{FilterError, decode_stream};
+use crate::lexer::{PdfLexer, is_whitespace};
+use crate::parser_helper::ParseContext;
+use crate::pdf::{DictionaryParser, IndirectObjectParser, ObjectParser};
+
+mod repair;
+
+pub use repair::{RepairReport, read_xref};
+
+/// An entry in the cross-reference table
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum XrefEntry {
+    /// An object that is not in use. `next` is the next free object number in the linked list of
+    /// free objects.
+    Free { next: u32, generation: u16 },
+    /// An object that is in use, starting at byte `offset` of the file.
+    InUse { offset: usize, generation: u16 },
+    /// An object that is stored as the `index`th object of the object stream with object number
+    /// `stream`. Only cross-reference streams contain these entries.
+    Compressed { stream: u32, index: u32 },
+}
+
+impl XrefEntry {
+    pub fn generation(&self) -> u16 {
+        match self {
+            XrefEntry::Free { generation, .. } | XrefEntry::InUse { generation, .. } => *generation,
+            // PDF Spec section 7.5.8.3: The generation number of the object is implicitly 0
+            XrefEntry::Compressed { .. } => 0,
+        }
+    }
+}
+
+/// A mapping from object numbers to where the objects can be found
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XrefTable {
+    entries: BTreeMap<u32, XrefEntry>,
+}
+
+impl XrefTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, number: u32) -> Option<&XrefEntry> {
+        self.entries.get(&number)
+    }
+
+    /// Returns the byte offset of the object `reference` refers to, if it's in use and its
+    /// generation matches.
+    pub fn offset_of(&self, reference: Reference) -> Option<usize> {
+        match self.entries.get(&reference.number)? {
+            XrefEntry::InUse { offset, generation } if *generation == reference.generation => {
+                Some(*offset)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, number: u32, entry: XrefEntry) -> Option<XrefEntry> {
+        self.entries.insert(number, entry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &XrefEntry)> {
+        self.entries.iter().map(|(k, v)| (*k, v))
+    }
+
+    /// Adds the entries of `older` for objects that aren't already in this table
+    pub fn merge_older(&mut self, older: &XrefTable) {
+        for (num, entry) in &older.entries {
+            self.entries.entry(*num).or_insert(*entry);
+        }
+    }
+}
+
+/// A single cross-reference section together with its trailer dictionary
+#[derive(Clone, Debug, PartialEq)]
+pub struct XrefSection {
+    pub table: XrefTable,
+    pub trailer: Dictionary,
+}
+
+/// The cross-reference section written by a single save of the document. See PDF Spec section
+/// 7.5.6
+#[derive(Clone, Debug, PartialEq)]
+pub struct Revision {
+    /// The byte offset of the section
+    pub offset: usize,
+    /// The objects added, changed or freed in this revision. For hybrid-reference files, this
+    /// includes the entries of the `/XRefStm` cross-reference stream.
+    pub table: XrefTable,
+    pub trailer: Dictionary,
+}
+
+/// All the cross-reference sections of a document, from following the `/Prev` chain
+#[derive(Clone, Debug, PartialEq)]
+pub struct XrefChain {
+    /// Revisions in the order they were written, i.e. oldest first
+    revisions: Vec<Revision>,
+    /// The effective table, where newer revisions take precedence over older ones
+    table: XrefTable,
+    /// The effective trailer, where newer revisions take precedence over older ones
+    trailer: Dictionary,
+}
+
+impl XrefChain {
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    pub fn table(&self) -> &XrefTable {
+        &self.table
+    }
+
+    pub fn trailer(&self) -> &Dictionary {
+        &self.trailer
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum XrefError {
+    /// The `startxref` keyword (or the offset following it) couldn't be found
+    MissingStartXref,
+    /// The section at the given offset doesn't start with the `xref` keyword
+    MissingXrefKeyword(usize),
+    /// A subsection header or entry at the given offset is malformed
+    MalformedEntry(usize),
+    /// The `trailer` keyword wasn't found after the section at the given offset
+    MissingTrailer(usize),
+    /// The trailer dictionary at the given offset couldn't be parsed
+    InvalidTrailer(usize),
+    /// There's no parsable indirect object at the given offset
+    InvalidObject(usize),
+    /// The cross-reference stream at the given offset is malformed
+    InvalidXrefStream(usize),
+    /// The object stream with the given object number is malformed
+    InvalidObjectStream(u32),
+    /// A stream we need to read couldn't be decoded
+    Filter(FilterError),
+    /// Following `/Prev` (or `/XRefStm`) led back to the section at the given offset
+    PrevLoop(usize),
+}
+
+impl Display for XrefError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XrefError::MissingStartXref => write!(f, "Couldn't find `startxref`"),
+            XrefError::MissingXrefKeyword(loc) => {
+                write!(f, "Expected cross-reference section at byte {loc}")
+            }
+            XrefError::MalformedEntry(loc) => {
+                write!(f, "Malformed cross-reference entry at byte {loc}")
+            }
+            XrefError::MissingTrailer(loc) => write!(f, "Expected `trailer` at byte {loc}"),
+            XrefError::InvalidTrailer(loc) => write!(f, "Invalid trailer dictionary at byte {loc}"),
+            XrefError::InvalidObject(loc) => write!(f, "Expected an indirect object at byte {loc}"),
+            XrefError::InvalidXrefStream(loc) => {
+                write!(f, "Invalid cross-reference stream at byte {loc}")
+            }
+            XrefError::InvalidObjectStream(num) => write!(f, "Invalid object stream {num}"),
+            XrefError::Filter(e) => e.fmt(f),
+            XrefError::PrevLoop(loc) => {
+                write!(
+                    f,
+                    "Cross-reference section at byte {loc} is its own ancestor"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for XrefError {}
+
+// How far from the end of the file to look for `startxref`. The spec requires it to be in the
+// last 1024 bytes, but some producers append garbage after `%%EOF`.
+static STARTXREF_SEARCH_LEN: usize = 4096;
+
+/// Finds the byte offset of the last cross-reference section from the `startxref` keyword near
+/// the end of the file. See PDF Spec section 7.5.5
+pub fn find_startxref(input: &[u8]) -> Result<usize, XrefError> {
+    let search_start = input.len().saturating_sub(STARTXREF_SEARCH_LEN);
+    let pos = input[search_start..]
+        .windows(b"startxref".len())
+        .rposition(|w| w == b"startxref")
+        .ok_or(XrefError::MissingStartXref)?
+        + search_start;
+
+    let mut cursor = Cursor::new(input, pos + b"startxref".len());
+    cursor.skip_whitespace();
+    cursor.integer().ok_or(XrefError::MissingStartXref)
+}
+
+/// Parses every cross-reference section of the document, starting from the one at `startxref` and
+/// following `/Prev` links back to the original. See PDF Spec section 7.5.6
+pub fn parse_xref_chain(input: &[u8], startxref: usize) -> Result<XrefChain, XrefError> {
+    let mut visited = Vec::new();
+    let mut revisions = Vec::new();
+    let mut next = Some(startxref);
+
+    while let Some(offset) = next {
+        if visited.contains(&offset) {
+            return Err(XrefError::PrevLoop(offset));
+        }
+        visited.push(offset);
+
+        let XrefSection { mut table, trailer } = parse_xref(input, offset)?;
+
+        // PDF Spec section 7.5.8.4: In hybrid-reference files, the entries of the stream at
+        // `/XRefStm` are searched after those of the table, but before those of `/Prev`
+        if let Some(stm_offset) = dict_usize(&trailer, b"XRefStm") {
+            if visited.contains(&stm_offset) {
+                return Err(XrefError::PrevLoop(stm_offset));
+            }
+            visited.push(stm_offset);
+            table.merge_older(&parse_xref_stream(input, stm_offset)?.table);
+        }
+
+        next = dict_usize(&trailer, b"Prev");
+        revisions.push(Revision {
+            offset,
+            table,
+            trailer,
+        });
+    }
+
+    let mut table = XrefTable::new();
+    let mut trailer = Dictionary::new();
+    for rev in &revisions {
+        table.merge_older(&rev.table);
+        for (key, value) in rev.trailer.iter() {
+            if !trailer.contains_key(key.as_bytes()) {
+                trailer.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    revisions.reverse();
+
+    Ok(XrefChain {
+        revisions,
+        table,
+        trailer,
+    })
+}
+
+/// Parses the cross-reference section starting at byte `offset`, which is either a classic
+/// `xref` table or a cross-reference stream.
+pub fn parse_xref(input: &[u8], offset: usize) -> Result<XrefSection, XrefError> {
+    let mut cursor = Cursor::new(input, offset);
+    cursor.skip_whitespace();
+    if cursor.eat(b"xref") {
+        parse_xref_section(input, offset)
+    } else {
+        parse_xref_stream(input, offset)
+    }
+}
+
+/// Parses the classic cross-reference section (`xref` keyword, subsections and `trailer`)
+/// starting at byte `offset`. See PDF Spec section 7.5.4
+pub fn parse_xref_section(input: &[u8], offset: usize) -> Result<XrefSection, XrefError> {
+    let mut cursor = Cursor::new(input, offset);
+    cursor.skip_whitespace();
+    if !cursor.eat(b"xref") {
+        return Err(XrefError::MissingXrefKeyword(offset));
+    }
+
+    let mut table = XrefTable::new();
+    loop {
+        cursor.skip_whitespace();
+        if cursor.eat(b"trailer") {
+            break;
+        }
+        if cursor.at_end() || !cursor.peek().is_some_and(|c| c.is_ascii_digit()) {
+            return Err(XrefError::MissingTrailer(cursor.pos));
+        }
+        parse_subsection(&mut cursor, &mut table)?;
+    }
+
+    let trailer_start = cursor.pos;
+    let trailer = PdfLexer::new_dictionary(input, trailer_start)
+        .ok()
+        .and_then(|lexer| {
+            DictionaryParser::new()
+                .parse(input, &ParseContext::default(), lexer)
+                .ok()
+        })
+        .ok_or(XrefError::InvalidTrailer(trailer_start))?;
+
+    Ok(XrefSection { table, trailer })
+}
+
+/// Parses a subsection header (`first count`) and its `count` entries into `table`. Entries
+/// already present in `table` are not overwritten, since per the spec an object shall only
+/// appear once per section.
+fn parse_subsection(cursor: &mut Cursor, table: &mut XrefTable) -> Result<(), XrefError> {
+    let header = cursor.pos;
+    let first: u32 = cursor.integer().ok_or(XrefError::MalformedEntry(header))?;
+    cursor.skip_spaces();
+    let count: u32 = cursor.integer().ok_or(XrefError::MalformedEntry(header))?;
+    cursor.skip_whitespace();
+
+    for i in 0..count {
+        let entry_start = cursor.pos;
+        let entry = cursor
+            .take(20)
+            .and_then(parse_entry)
+            .ok_or(XrefError::MalformedEntry(entry_start))?;
+        let number = first
+            .checked_add(i)
+            .ok_or(XrefError::MalformedEntry(entry_start))?;
+        table.entries.entry(number).or_insert(entry);
+    }
+    Ok(())
+}
+
+/// Parses a single 20-byte entry of the form `nnnnnnnnnn ggggg n` followed by a two-character
+/// end-of-line marker. See PDF Spec section 7.5.4
+fn parse_entry(entry: &[u8]) -> Option<XrefEntry> {
+    fn digits<T: std::str::FromStr>(val: &[u8]) -> Option<T> {
+        if !val.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        str::from_utf8(val).ok()?.parse().ok()
+    }
+
+    if entry[10] != b' ' || entry[16] != b' ' || !entry[18..].iter().all(|c| is_whitespace(*c)) {
+        return None;
+    }
+    let field1 = &entry[0..10];
+    let generation = digits(&entry[11..16])?;
+    match entry[17] {
+        b'n' => Some(XrefEntry::InUse {
+            offset: digits(field1)?,
+            generation,
+        }),
+        b'f' => Some(XrefEntry::Free {
+            next: digits(field1)?,
+            generation,
+        }),
+        _ => None,
+    }
+}
+
+/// Parses the cross-reference stream object at byte `offset`. The stream dictionary doubles as the
+/// trailer dictionary. See PDF Spec section 7.5.8
+pub fn parse_xref_stream(input: &[u8], offset: usize) -> Result<XrefSection, XrefError> {
+    let invalid = || XrefError::InvalidXrefStream(offset);
+
+    let obj = parse_indirect_object(input, offset, &ParseContext::default())
+        .map_err(|_| XrefError::InvalidObject(offset))?;
+    let Object::Stream(stream) = obj.object else {
+        return Err(invalid());
+    };
+    if stream.dict.get(b"Type").and_then(Object::as_name) != Some(&b"XRef".into()) {
+        return Err(invalid());
+    }
+    let data = decode_stream(&stream).map_err(XrefError::Filter)?;
+
+    // The width in bytes of each of the three fields of an entry
+    let widths = stream
+        .dict
+        .get(b"W")
+        .and_then(Object::as_array)
+        .and_then(integers::<usize>)
+        .filter(|w| w.len() == 3 && w.iter().all(|w| *w <= 8))
+        .ok_or_else(invalid)?;
+    let entry_len: usize = widths.iter().sum();
+    if entry_len == 0 {
+        return Err(invalid());
+    }
+
+    let size = stream
+        .dict
+        .get(b"Size")
+        .and_then(Object::as_integer)
+        .and_then(|s| u32::try_from(s).ok())
+        .ok_or_else(invalid)?;
+    // Pairs of (first object number, count). The default is [0 Size]
+    let index = match stream.dict.get(b"Index") {
+        Some(index) => index
+            .as_array()
+            .and_then(integers::<u32>)
+            .filter(|i| i.len() % 2 == 0)
+            .ok_or_else(invalid)?,
+        None => vec![0, size],
+    };
+
+    let mut table = XrefTable::new();
+    let mut rows = data.chunks_exact(entry_len);
+    for subsection in index.chunks_exact(2) {
+        let (first, count) = (subsection[0], subsection[1]);
+        for i in 0..count {
+            let row = rows.next().ok_or_else(invalid)?;
+            let (field1, rest) = row.split_at(widths[0]);
+            let (field2, field3) = rest.split_at(widths[1]);
+
+            // PDF Spec section 7.5.8.2: If the first element is zero, the type field shall
+            // not be present, and shall default to type 1.
+            let kind = if widths[0] == 0 { 1 } else { be_int(field1) };
+            let (field2, field3) = (be_int(field2), be_int(field3));
+            let entry = match kind {
+                0 => XrefEntry::Free {
+                    next: u32::try_from(field2).map_err(|_| invalid())?,
+                    generation: u16::try_from(field3).map_err(|_| invalid())?,
+                },
+                1 => XrefEntry::InUse {
+                    offset: usize::try_from(field2).map_err(|_| invalid())?,
+                    generation: u16::try_from(field3).map_err(|_| invalid())?,
+                },
+                2 => XrefEntry::Compressed {
+                    stream: u32::try_from(field2).map_err(|_| invalid())?,
+                    index: u32::try_from(field3).map_err(|_| invalid())?,
+                },
+                // PDF Spec section 7.5.8.3: Any other value shall be interpreted as a reference
+                // to the null object
+                _ => continue,
+            };
+            let number = first.checked_add(i).ok_or_else(invalid)?;
+            table.entries.entry(number).or_insert(entry);
+        }
+    }
+
+    Ok(XrefSection {
+        table,
+        trailer: stream.dict,
+    })
+}
+
+/// The objects stored in an object stream. See PDF Spec section 7.5.7
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectStream {
+    /// The object number of the stream itself
+    number: u32,
+    /// Pairs of (object number, offset of the object in `data`)
+    headers: Vec<(u32, usize)>,
+    /// The decoded stream data
+    data: Vec<u8>,
+}
+
+impl ObjectStream {
+    /// Reads the header of the object stream `stream`, whose object number is `number`
+    pub fn new(number: u32, stream: &Stream) -> Result<Self, XrefError> {
+        let invalid = || XrefError::InvalidObjectStream(number);
+
+        if stream.dict.get(b"Type").and_then(Object::as_name) != Some(&b"ObjStm".into()) {
+            return Err(invalid());
+        }
+        let count = dict_usize(&stream.dict, b"N").ok_or_else(invalid)?;
+        let first = dict_usize(&stream.dict, b"First").ok_or_else(invalid)?;
+        let data = decode_stream(stream).map_err(XrefError::Filter)?;
+        if first > data.len() {
+            return Err(invalid());
+        }
+
+        // The header is `count` pairs of integers: object number and offset relative to `first`
+        let mut headers = Vec::with_capacity(count.min(data.len()));
+        let mut cursor = Cursor::new(&data[..first], 0);
+        for _ in 0..count {
+            cursor.skip_whitespace();
+            let num = cursor.integer().ok_or_else(invalid)?;
+            cursor.skip_whitespace();
+            let offset: usize = cursor.integer().ok_or_else(invalid)?;
+            let offset = offset.checked_add(first).ok_or_else(invalid)?;
+            if offset > data.len() {
+                return Err(invalid());
+            }
+            headers.push((num, offset));
+        }
+
+        Ok(Self {
+            number,
+            headers,
+            data,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    /// The object numbers of the objects in this stream, in order
+    pub fn object_numbers(&self) -> impl Iterator<Item = u32> {
+        self.headers.iter().map(|(num, _)| *num)
+    }
+
+    /// Parses the `index`th object in the stream, returning its object number and value. The
+    /// offsets of parse errors are relative to the start of the decoded stream data.
+    pub fn get(&self, index: usize) -> crate::Result<(u32, Object)> {
+        self.parse_object(index, &ParseContext::default())
+    }
+
+    /// Like [`ObjectStream::get`], but with the leniency (and warnings) of `ctx`
+    pub(crate) fn parse_object(
+        &self,
+        index: usize,
+        ctx: &ParseContext,
+    ) -> crate::Result<(u32, Object)> {
+        let (num, start) = *self
+            .headers
+            .get(index)
+            .ok_or(XrefError::InvalidObjectStream(self.number))?;
+        // Objects are stored in order, so an object ends where the next one begins
+        let end = self
+            .headers
+            .get(index + 1)
+            .map_or(self.data.len(), |(_, offset)| *offset)
+            .max(start);
+        let data = &self.data[start..end];
+        let relocate = |mut err: ParseError| {
+            err.offset += start;
+            err
+        };
+        let lexer = PdfLexer::new(data).map_err(|e| relocate(ParseError::from_lex(data, e)))?;
+        let local = ParseContext::new(ctx.mode());
+        let obj = ObjectParser::new()
+            .parse(data, &local, lexer)
+            .map_err(|e| relocate(ParseError::from_parse(data, 0, e)))?;
+        for warning in local.into_warnings() {
+            ctx.warn(warning.kind, warning.offset + start);
+        }
+        Ok((num, obj))
+    }
+}
+
+/// Parses the indirect object (`N G obj ... endobj`) starting at byte `offset`
+pub(crate) fn parse_indirect_object(
+    input: &[u8],
+    offset: usize,
+    ctx: &ParseContext,
+) -> Result<IndirectObject, ParseError> {
+    let lexer =
+        PdfLexer::new_object(input, offset, ctx).map_err(|e| ParseError::from_lex(input, e))?;
+    IndirectObjectParser::new()
+        .parse(input, ctx, lexer)
+        .map_err(|e| ParseError::from_parse(input, offset, e))
+}
+
+fn dict_usize(dict: &Dictionary, key: &[u8]) -> Option<usize> {
+    dict.get(key)?.as_integer()?.try_into().ok()
+}
+
+/// Converts an array of objects to integers, if they're all integers in range
+fn integers<T: TryFrom<i64>>(arr: &[Object]) -> Option<Vec<T>> {
+    arr.iter()
+        .map(|o| o.as_integer()?.try_into().ok())
+        .collect()
+}
+
+/// Reads a big-endian unsigned integer of up to 8 bytes
+fn be_int(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+/// A simple cursor for the line-oriented parts of the file structure, which aren't tokenized
+/// by the lexer
+pub(crate) struct Cursor<'input> {
+    input: &'input [u8],
+    pub(crate) pos: usize,
+}
+
+impl<'input> Cursor<'input> {
+    pub(crate) fn new(input: &'input [u8], pos: usize) -> Self {
+        Self { input, pos }
+    }
+
+    pub(crate) fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    /// Skips whitespace, including end-of-line markers, and comments
+    pub(crate) fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if is_whitespace(c) {
+                self.pos += 1;
+            } else if c == b'%' {
+                while !matches!(self.peek(), None | Some(b'\r') | Some(b'\n')) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Skips spaces, but not end-of-line markers
+    pub(crate) fn skip_spaces(&mut self) {
+        while let Some(b' ') | Some(b'\t') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes `keyword` if it's next in the input
+    pub(crate) fn eat(&mut self, keyword: &[u8]) -> bool {
+        if self.input[self.pos.min(self.input.len())..].starts_with(keyword) {
+            self.pos += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Option<&'input [u8]> {
+        let slice = self.input.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    /// Consumes an unsigned decimal integer
+    pub(crate) fn integer<T: std::str::FromStr>(&mut self) -> Option<T> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return None;
+        }
+        str::from_utf8(&self.input[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::object::{Number, Object};
+
+    static SIMPLE: &[u8] = b"%PDF-1.4\n\
+1 0 obj\n<</Type/Catalog>>\nendobj\n\
+xref\n\
+0 2\n\
+0000000000 65535 f\r\n\
+0000000009 00000 n\r\n\
+trailer\n<</Size 2/Root 1 0 R>>\n\
+startxref\n42\n%%EOF\n";
+
+    #[test]
+    fn startxref() {
+        assert_eq!(find_startxref(SIMPLE), Ok(42));
+        assert_eq!(
+            find_startxref(b"no xref here"),
+            Err(XrefError::MissingStartXref)
+        );
+    }
+
+    #[test]
+    fn simple() {
+        let section = parse_xref_section(SIMPLE, find_startxref(SIMPLE).unwrap()).unwrap();
+        assert_eq!(
+            section.table.get(0),
+            Some(&XrefEntry::Free {
+                next: 0,
+                generation: 65535
+            })
+        );
+        assert_eq!(
+            section.table.offset_of(Reference {
+                number: 1,
+                generation: 0
+            }),
+            Some(9)
+        );
+        assert_eq!(
+            section.trailer.get(b"Size"),
+            Some(&Object::Number(Number::Integer(2)))
+        );
+    }
+
+    #[test]
+    fn multiple_subsections() {
+        let inp = b"xref\n0 1\n0000000000 65535 f \n3 2\n0000000025 00000 n \n0000000099 00002 n \ntrailer<</Size 5>>";
+        let section = parse_xref_section(inp, 0).unwrap();
+        assert_eq!(section.table.len(), 3);
+        assert_eq!(
+            section.table.get(4),
+            Some(&XrefEntry::InUse {
+                offset: 99,
+                generation: 2
+            })
+        );
+        // Wrong generation
+        assert_eq!(
+            section.table.offset_of(Reference {
+                number: 4,
+                generation: 0
+            }),
+            None
+        );
+    }
+
+    /// Builds an uncompressed stream object from its dictionary contents and data
+    fn stream_obj(header: &str, dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut out =
+            format!("{header} obj\n<<{dict}/Length {}>>\nstream\n", data.len()).into_bytes();
+        out.extend_from_slice(data);
+        out.extend_from_slice(b"\nendstream\nendobj\n");
+        out
+    }
+
+    #[test]
+    fn xref_stream() {
+        let rows: &[u8] = &[
+            0, 0, 0, 0xff, //
+            1, 0, 0x10, 0, //
+            2, 0, 5, 1, //
+            1, 0x01, 0x00, 0, //
+        ];
+        let inp = stream_obj("7 0", "/Type/XRef/Size 9/W[1 2 1]/Index[0 2 6 2]", rows);
+        let section = parse_xref(&inp, 0).unwrap();
+        assert_eq!(
+            section.table.get(0),
+            Some(&XrefEntry::Free {
+                next: 0,
+                generation: 255
+            })
+        );
+        assert_eq!(
+            section.table.get(1),
+            Some(&XrefEntry::InUse {
+                offset: 0x10,
+                generation: 0
+            })
+        );
+        assert_eq!(
+            section.table.get(6),
+            Some(&XrefEntry::Compressed {
+                stream: 5,
+                index: 1
+            })
+        );
+        assert_eq!(
+            section.table.get(7),
+            Some(&XrefEntry::InUse {
+                offset: 0x100,
+                generation: 0
+            })
+        );
+        assert_eq!(section.table.len(), 4);
+        assert_eq!(
+            section.trailer.get(b"Size"),
+            Some(&Object::Number(Number::Integer(9)))
+        );
+    }
+
+    #[test]
+    fn xref_stream_predictor() {
+        // The same rows as above, Up-predicted and compressed, as most producers write them
+        let rows: &[u8] = &[
+            2, 0, 0, 0, 0xff, //
+            2, 1, 0, 0x10, 0x01, //
+            2, 1, 0, 0xf5, 1, //
+            2, 0xff, 0x01, 0xfb, 0xff, //
+        ];
+        let mut inp = stream_obj(
+            "7 0",
+            "/Type/XRef/Size 9/W[1 2 1]/Index[0 2 6 2]/Filter/FlateDecode\
+/DecodeParms<</Predictor 12/Columns 4>>",
+            &crate::filter::tests::zlib(rows),
+        );
+        let section = parse_xref(&inp, 0).unwrap();
+        assert_eq!(
+            section.table.get(7),
+            Some(&XrefEntry::InUse {
+                offset: 0x100,
+                generation: 0
+            })
+        );
+        assert_eq!(section.table.len(), 4);
+
+        // Corrupting the compressed data
+        let data_start = crate::lexer::find_subslice(&inp, b"stream\n").unwrap() + 7;
+        inp[data_start] ^= 0xff;
+        assert!(matches!(
+            parse_xref(&inp, 0),
+            Err(XrefError::Filter(FilterError::InvalidData(_)))
+        ));
+    }
+
+    #[test]
+    fn xref_stream_default_type() {
+        // With a zero-width type field, every entry is type 1
+        let inp = stream_obj("3 0", "/Type/XRef/Size 2/W[0 1 0]", &[9, 42]);
+        let section = parse_xref_stream(&inp, 0).unwrap();
+        assert_eq!(
+            section.table.get(1),
+            Some(&XrefEntry::InUse {
+                offset: 42,
+                generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn xref_stream_failure() {
+        // Not enough rows for /Size
+        let inp = stream_obj("3 0", "/Type/XRef/Size 2/W[1 1 0]", &[1, 9]);
+        assert_eq!(
+            parse_xref_stream(&inp, 0),
+            Err(XrefError::InvalidXrefStream(0))
+        );
+
+        // Missing /W
+        let inp = stream_obj("3 0", "/Type/XRef/Size 1", &[1, 9]);
+        assert_eq!(
+            parse_xref_stream(&inp, 0),
+            Err(XrefError::InvalidXrefStream(0))
+        );
+
+        // Not an xref stream
+        let inp = stream_obj("3 0", "/Size 1/W[1 1 0]", &[1, 9]);
+        assert_eq!(
+            parse_xref_stream(&inp, 0),
+            Err(XrefError::InvalidXrefStream(0))
+        );
+    }
+
+    #[test]
+    fn object_stream() {
+        let data = b"11 0 12 10 13 20 <</A 1>> [1 2 0 R] (end)";
+        let inp = stream_obj("5 0", "/Type/ObjStm/N 3/First 16", data);
+        let obj = parse_indirect_object(&inp, 0, &ParseContext::default()).unwrap();
+        let objstm = ObjectStream::new(5, obj.object.as_stream().unwrap()).unwrap();
+
+        assert_eq!(objstm.len(), 3);
+        assert_eq!(
+            objstm.object_numbers().collect::<Vec<_>>(),
+            vec![11, 12, 13]
+        );
+        let (num, obj) = objstm.get(0).unwrap();
+        assert_eq!(num, 11);
+        assert_eq!(
+            obj.as_dictionary().unwrap().get(b"A"),
+            Some(&Object::Number(Number::Integer(1)))
+        );
+        let (num, obj) = objstm.get(1).unwrap();
+        assert_eq!(num, 12);
+        assert_eq!(
+            obj,
+            Object::Array(vec![
+                Object::Number(Number::Integer(1)),
+                Object::Reference(Reference {
+                    number: 2,
+                    generation: 0
+                })
+            ])
+        );
+        assert_eq!(objstm.get(2).unwrap().0, 13);
+        assert_eq!(
+            objstm.get(3),
+            Err(crate::Error::Xref(XrefError::InvalidObjectStream(5)))
+        );
+    }
+
+    #[test]
+    fn object_stream_failure() {
+        let inp = stream_obj("5 0", "/Type/ObjStm/N 2/First 4", b"11 0");
+        let obj = parse_indirect_object(&inp, 0, &ParseContext::default()).unwrap();
+        assert_eq!(
+            ObjectStream::new(5, obj.object.as_stream().unwrap()),
+            Err(XrefError::InvalidObjectStream(5))
+        );
+
+        let inp = stream_obj("5 0", "/Type/ObjStm/N 1/First 4/Filter/Foo", b"11 0");
+        let obj = parse_indirect_object(&inp, 0, &ParseContext::default()).unwrap();
+        assert_eq!(
+            ObjectStream::new(5, obj.object.as_stream().unwrap()),
+            Err(XrefError::Filter(FilterError::UnsupportedFilter(
+                b"Foo".to_vec()
+            )))
+        );
+    }
+
+    /// A document with an original save and two incremental updates
+    fn incremental() -> Vec<u8> {
+        let mut inp = SIMPLE.to_vec();
+
+        // Update 1: change object 1 and add object 2
+        let obj1 = inp.len();
+        inp.extend_from_slice(b"1 0 obj\n<</Type/Catalog/V 2>>\nendobj\n");
+        let obj2 = inp.len();
+        inp.extend_from_slice(b"2 0 obj\n(two)\nendobj\n");
+        let xref1 = inp.len();
+        inp.extend_from_slice(
+            format!(
+                "xref\n1 2\n{obj1:010} 00000 n\r\n{obj2:010} 00000 n\r\n\
+                 trailer\n<</Size 3/Root 1 0 R/Prev 42/Info 2 0 R>>\nstartxref\n{xref1}\n%%EOF\n"
+            )
+            .as_bytes(),
+        );
+
+        // Update 2: delete object 2
+        let xref2 = inp.len();
+        inp.extend_from_slice(
+            format!(
+                "xref\n2 1\n0000000000 00001 f\r\n\
+                 trailer\n<</Size 3/Root 1 0 R/Prev {xref1}>>\nstartxref\n{xref2}\n%%EOF\n"
+            )
+            .as_bytes(),
+        );
+        inp
+    }
+
+    #[test]
+    fn prev_chain() {
+        let inp = incremental();
+        let chain = parse_xref_chain(&inp, find_startxref(&inp).unwrap()).unwrap();
+
+        let revs = chain.revisions();
+        assert_eq!(revs.len(), 3);
+        assert_eq!(revs[0].offset, 42);
+        assert_eq!(revs[1].table.len(), 2);
+        assert_eq!(revs[2].table.len(), 1);
+
+        // Object 1 comes from the first update, not the original
+        assert_ne!(chain.table().get(1), revs[0].table.get(1),);
+        assert_eq!(chain.table().get(1), revs[1].table.get(1));
+        // Object 2 was deleted by the second update
+        assert_eq!(
+            chain.table().get(2),
+            Some(&XrefEntry::Free {
+                next: 0,
+                generation: 1
+            })
+        );
+        assert_eq!(chain.table().len(), 3);
+
+        // `/Info` was only in the first update's trailer
+        assert!(chain.trailer().contains_key(b"Info"));
+        assert_eq!(chain.trailer().get(b"Prev"), revs[2].trailer.get(b"Prev"));
+    }
+
+    #[test]
+    fn prev_loop() {
+        let inp = b"xref\n0 0\ntrailer<</Prev 0>>";
+        assert_eq!(parse_xref_chain(inp, 0), Err(XrefError::PrevLoop(0)));
+    }
+
+    #[test]
+    fn hybrid() {
+        let mut inp = b"%PDF-1.5\n".to_vec();
+        let stm = inp.len();
+        inp.extend_from_slice(&stream_obj(
+            "9 0",
+            "/Type/XRef/Size 10/W[1 1 1]/Index[3 2]",
+            &[2, 8, 0, 1, 7, 0],
+        ));
+        let xref = inp.len();
+        inp.extend_from_slice(
+            format!(
+                "xref\n0 1\n0000000000 65535 f\r\n4 1\n0000000005 00000 n\r\n\
+                 trailer<</Size 10/XRefStm {stm}>>"
+            )
+            .as_bytes(),
+        );
+
+        let chain = parse_xref_chain(&inp, xref).unwrap();
+        assert_eq!(chain.revisions().len(), 1);
+        assert_eq!(
+            chain.table().get(3),
+            Some(&XrefEntry::Compressed {
+                stream: 8,
+                index: 0
+            })
+        );
+        // The table takes precedence over the stream
+        assert_eq!(
+            chain.table().get(4),
+            Some(&XrefEntry::InUse {
+                offset: 5,
+                generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn failure() {
+        assert_eq!(
+            parse_xref_section(b"xref\n0 1\n000000000 65535 f\r\ntrailer<<>>", 0),
+            Err(XrefError::MalformedEntry(9))
+        );
+        assert_eq!(
+            parse_xref_section(b"xref\n0 1\n0000000000 65535 x\r\ntrailer<<>>", 0),
+            Err(XrefError::MalformedEntry(9))
+        );
+        assert_eq!(
+            parse_xref_section(b"1 0 obj", 0),
+            Err(XrefError::MissingXrefKeyword(0))
+        );
+        assert_eq!(
+            parse_xref_section(b"xref\n0 0\n", 0),
+            Err(XrefError::MissingTrailer(9))
+        );
+        assert_eq!(
+            parse_xref_section(b"xref\n0 0\ntrailer<</A>>", 0),
+            Err(XrefError::InvalidTrailer(16))
+        );
+    }
+}
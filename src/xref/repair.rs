@@ -0,0 +1,403 @@
+//! Recovery of the cross-reference table of damaged files, by scanning the whole file for object
+//! headers and trailers instead of trusting `startxref`
+
+use crate::ast::object::{Dictionary, Number, Object, Reference};
+use crate::lexer::{PdfLexer, find_subslice, is_delimiter, is_whitespace};
+use crate::parser_helper::ParseContext;
+use crate::pdf::DictionaryParser;
+
+use super::{
+    Cursor, ObjectStream, XrefChain, XrefEntry, XrefError, XrefTable, find_startxref,
+    parse_indirect_object, parse_xref_chain, parse_xref_stream,
+};
+
+/// What had to be repaired to make a file's cross-reference data usable
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RepairReport {
+    /// Why the declared cross-reference data couldn't be used at all, if it couldn't. In that
+    /// case the whole table was rebuilt by scanning the file.
+    pub error: Option<XrefError>,
+    /// Objects whose declared offset didn't point at the object, but which were found elsewhere
+    pub relocated: Vec<u32>,
+    /// Objects whose declared offset didn't point at the object, and which couldn't be found
+    pub missing: Vec<u32>,
+    /// How many object headers were found by scanning the file
+    pub objects_found: usize,
+    /// Whether the trailer had to be pieced together from scanned trailers and objects
+    pub trailer_rebuilt: bool,
+}
+
+impl RepairReport {
+    /// Whether anything was actually repaired
+    pub fn is_empty(&self) -> bool {
+        self.error.is_none()
+            && self.relocated.is_empty()
+            && self.missing.is_empty()
+            && !self.trailer_rebuilt
+    }
+}
+
+/// Reads the cross-reference data of the file, repairing it if it's damaged. If nothing needed
+/// repairing, the returned report [is empty](RepairReport::is_empty).
+pub fn read_xref(input: &[u8]) -> Result<(XrefChain, RepairReport), XrefError> {
+    let declared = find_startxref(input).and_then(|offset| parse_xref_chain(input, offset));
+    let mut chain = match declared {
+        Ok(chain) => chain,
+        Err(error) => return reconstruct(input, error),
+    };
+
+    let mut report = RepairReport::default();
+    let wrong: Vec<_> = chain
+        .table
+        .iter()
+        .filter_map(|(num, entry)| match entry {
+            XrefEntry::InUse { offset, generation } => {
+                let expected = Reference {
+                    number: num,
+                    generation: *generation,
+                };
+                (object_header_at(input, *offset) != Some(expected)).then_some(num)
+            }
+            _ => None,
+        })
+        .collect();
+    let has_root = chain.trailer.contains_key(b"Root");
+    if wrong.is_empty() && has_root {
+        return Ok((chain, report));
+    }
+
+    let scanned = scan(input);
+    report.objects_found = scanned.table.len();
+    for num in wrong {
+        match scanned.table.get(num) {
+            Some(entry) => {
+                chain.table.insert(num, *entry);
+                report.relocated.push(num);
+            }
+            None => report.missing.push(num),
+        }
+    }
+    if !has_root && let Some(root) = scanned.trailer.get(b"Root") {
+        chain.trailer.insert(b"Root".into(), root.clone());
+        report.trailer_rebuilt = true;
+    }
+
+    Ok((chain, report))
+}
+
+/// Rebuilds the cross-reference table from scratch, because the declared one is unusable due to
+/// `error`
+fn reconstruct(input: &[u8], error: XrefError) -> Result<(XrefChain, RepairReport), XrefError> {
+    let scanned = scan(input);
+    if scanned.table.is_empty() {
+        return Err(error);
+    }
+
+    let report = RepairReport {
+        error: Some(error),
+        objects_found: scanned.table.len(),
+        trailer_rebuilt: true,
+        ..Default::default()
+    };
+    let chain = XrefChain {
+        revisions: Vec::new(),
+        table: scanned.table,
+        trailer: scanned.trailer,
+    };
+    Ok((chain, report))
+}
+
+struct Scanned {
+    table: XrefTable,
+    trailer: Dictionary,
+}
+
+// How many bytes after an object header to look at when guessing whether it's an interesting
+// object (an xref stream, object stream or catalog) that's worth parsing
+static HEADER_PEEK_LEN: usize = 256;
+
+/// Scans the whole file for object headers and trailers. Where an object is defined more than
+/// once, the last definition wins, as it would in an incremental update.
+fn scan(input: &[u8]) -> Scanned {
+    let mut table = XrefTable::new();
+    // Trailers (and xref stream dictionaries) in file order
+    let mut trailers = Vec::new();
+    let mut catalog = None;
+    let mut object_streams = Vec::new();
+
+    let mut pos = 0;
+    while pos < input.len() {
+        let rest = &input[pos..];
+        if rest.starts_with(b"trailer") && is_token_start(input, pos) {
+            if let Some(trailer) = PdfLexer::new_dictionary(input, pos + b"trailer".len())
+                .ok()
+                .and_then(|lexer| {
+                    DictionaryParser::new()
+                        .parse(input, &ParseContext::default(), lexer)
+                        .ok()
+                })
+            {
+                trailers.push(trailer);
+            }
+            pos += b"trailer".len();
+        } else if rest.starts_with(b"obj")
+            && rest
+                .get(3)
+                .is_none_or(|c| is_whitespace(*c) || is_delimiter(*c))
+            && let Some((start, id)) = object_header_before(input, pos)
+        {
+            table.insert(
+                id.number,
+                XrefEntry::InUse {
+                    offset: start,
+                    generation: id.generation,
+                },
+            );
+
+            let peek = &input[pos..(pos + HEADER_PEEK_LEN).min(input.len())];
+            if find_subslice(peek, b"/XRef").is_some()
+                && let Ok(section) = parse_xref_stream(input, start)
+            {
+                trailers.push(section.trailer);
+            } else if find_subslice(peek, b"/ObjStm").is_some() {
+                object_streams.push((start, id.number));
+            } else if find_subslice(peek, b"/Catalog").is_some() {
+                catalog = Some(id);
+            }
+
+            pos += b"obj".len();
+        } else if rest.starts_with(b"stream")
+            && pos > 0
+            && matches!(input[pos - 1], b'>' | b' ' | b'\r' | b'\n')
+            && matches!(rest.get(6), Some(b'\r') | Some(b'\n'))
+        {
+            // Skip over the stream data so we don't pick up headers from binary garbage
+            pos = match find_subslice(rest, b"endstream") {
+                Some(end) => pos + end + b"endstream".len(),
+                None => input.len(),
+            };
+        } else {
+            pos += 1;
+        }
+    }
+
+    // Objects in object streams only count if they weren't also defined directly
+    for (offset, number) in object_streams {
+        let Ok(obj) = parse_indirect_object(input, offset, &ParseContext::default()) else {
+            continue;
+        };
+        let Some(Ok(objstm)) = obj.object.as_stream().map(|s| ObjectStream::new(number, s)) else {
+            continue;
+        };
+        for (index, num) in objstm.object_numbers().enumerate() {
+            if table.get(num).is_none() {
+                table.insert(
+                    num,
+                    XrefEntry::Compressed {
+                        stream: number,
+                        index: index as u32,
+                    },
+                );
+            }
+        }
+    }
+
+    // Newer trailers take precedence over older ones
+    let mut trailer = Dictionary::new();
+    for t in trailers.iter().rev() {
+        for (key, value) in t.iter() {
+            if !trailer.contains_key(key.as_bytes()) {
+                trailer.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    for key in [
+        &b"Prev"[..],
+        b"XRefStm",
+        b"Index",
+        b"W",
+        b"Length",
+        b"Filter",
+    ] {
+        trailer.remove(key);
+    }
+    if !trailer.contains_key(b"Root")
+        && let Some(catalog) = catalog
+    {
+        trailer.insert(b"Root".into(), Object::Reference(catalog));
+    }
+    let size = table.iter().last().map_or(0, |(num, _)| num as i64 + 1);
+    trailer.insert(b"Size".into(), Object::Number(Number::Integer(size)));
+
+    Scanned { table, trailer }
+}
+
+/// Whether a token can start at `pos`, i.e. it's preceded by whitespace or a delimiter
+fn is_token_start(input: &[u8], pos: usize) -> bool {
+    pos == 0 || is_whitespace(input[pos - 1]) || is_delimiter(input[pos - 1])
+}
+
+/// Given the position of an `obj` keyword, looks backwards for the `N G` before it. Returns the
+/// offset of `N` and the object's ID.
+fn object_header_before(input: &[u8], obj_pos: usize) -> Option<(usize, Reference)> {
+    // Walks backwards over a run of bytes matching `pred`, returning the start of the run
+    let run_start = |end: usize, pred: fn(u8) -> bool| {
+        let len = input[..end].iter().rev().take_while(|c| pred(**c)).count();
+        (len > 0).then(|| end - len)
+    };
+
+    let gen_end = run_start(obj_pos, is_whitespace)?;
+    let gen_start = run_start(gen_end, |c| c.is_ascii_digit())?;
+    let num_end = run_start(gen_start, is_whitespace)?;
+    let num_start = run_start(num_end, |c| c.is_ascii_digit())?;
+    if !is_token_start(input, num_start) {
+        return None;
+    }
+
+    let id = object_header_at(input, num_start)?;
+    Some((num_start, id))
+}
+
+/// Reads the `N G obj` header at `offset`, if there is one
+fn object_header_at(input: &[u8], offset: usize) -> Option<Reference> {
+    let mut cursor = Cursor::new(input, offset);
+    let number = cursor.integer()?;
+    let after_number = cursor.pos;
+    cursor.skip_whitespace();
+    if cursor.pos == after_number {
+        return None;
+    }
+    let generation = cursor.integer()?;
+    cursor.skip_whitespace();
+    if !cursor.eat(b"obj") {
+        return None;
+    }
+    Some(Reference { number, generation })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static BODY: &[u8] = b"%PDF-1.4\n\
+1 0 obj\n<</Type/Catalog/Pages 2 0 R>>\nendobj\n\
+2 0 obj\n<</Type/Pages/Kids[]/Count 0>>\nendobj\n";
+
+    fn with_xref(body: &[u8], offsets: &[usize], startxref: usize) -> Vec<u8> {
+        let mut inp = body.to_vec();
+        inp.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f\r\n", offsets.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            inp.extend_from_slice(format!("{offset:010} 00000 n\r\n").as_bytes());
+        }
+        inp.extend_from_slice(
+            format!(
+                "trailer\n<</Size {}/Root 1 0 R>>\nstartxref\n{startxref}\n%%EOF\n",
+                offsets.len() + 1
+            )
+            .as_bytes(),
+        );
+        inp
+    }
+
+    #[test]
+    fn intact() {
+        let inp = with_xref(BODY, &[9, 54], BODY.len());
+        let (chain, report) = read_xref(&inp).unwrap();
+        assert!(report.is_empty());
+        assert_eq!(chain.revisions().len(), 1);
+    }
+
+    #[test]
+    fn wrong_startxref() {
+        let inp = with_xref(BODY, &[9, 54], BODY.len() + 7);
+        let (chain, report) = read_xref(&inp).unwrap();
+        assert!(report.error.is_some());
+        assert_eq!(report.objects_found, 2);
+        assert!(chain.revisions().is_empty());
+        assert_eq!(
+            chain.table().offset_of(Reference {
+                number: 2,
+                generation: 0
+            }),
+            Some(54)
+        );
+        assert_eq!(
+            chain.trailer().get(b"Root"),
+            Some(&Object::Reference(Reference {
+                number: 1,
+                generation: 0
+            }))
+        );
+    }
+
+    #[test]
+    fn shifted_object() {
+        let inp = with_xref(BODY, &[9, 50], BODY.len());
+        let (chain, report) = read_xref(&inp).unwrap();
+        assert_eq!(report.error, None);
+        assert_eq!(report.relocated, vec![2]);
+        assert!(report.missing.is_empty());
+        assert_eq!(
+            chain.table().get(2),
+            Some(&XrefEntry::InUse {
+                offset: 54,
+                generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn missing_object() {
+        let inp = with_xref(BODY, &[9, 54, 3], BODY.len());
+        let (_, report) = read_xref(&inp).unwrap();
+        assert_eq!(report.missing, vec![3]);
+    }
+
+    #[test]
+    fn truncated() {
+        // No xref or trailer at all, so the catalog has to be found by scanning
+        let (chain, report) = read_xref(BODY).unwrap();
+        assert_eq!(report.error, Some(XrefError::MissingStartXref));
+        assert_eq!(chain.table().len(), 2);
+        assert_eq!(
+            chain.trailer().get(b"Root"),
+            Some(&Object::Reference(Reference {
+                number: 1,
+                generation: 0
+            }))
+        );
+    }
+
+    #[test]
+    fn redefined_object() {
+        let mut inp = BODY.to_vec();
+        let offset = inp.len();
+        inp.extend_from_slice(b"2 0 obj\n<</Type/Pages/Kids[]/Count 0/V 2>>\nendobj\n");
+        let (chain, _) = read_xref(&inp).unwrap();
+        assert_eq!(
+            chain.table().get(2),
+            Some(&XrefEntry::InUse {
+                offset,
+                generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_stream_data() {
+        let mut inp = BODY.to_vec();
+        inp.extend_from_slice(
+            b"3 0 obj\n<</Length 12>>\nstream\n 4 0 obj junk\nendstream\nendobj\n",
+        );
+        let (chain, _) = read_xref(&inp).unwrap();
+        assert!(chain.table().get(3).is_some());
+        assert!(chain.table().get(4).is_none());
+    }
+
+    #[test]
+    fn unrecoverable() {
+        assert_eq!(read_xref(b"not a pdf"), Err(XrefError::MissingStartXref));
+    }
+}